@@ -15,7 +15,9 @@ use serde::Serialize;
 
 use crate::bank::{Bank, BankKeeper, BankSudo};
 use crate::contracts::Contract;
+use crate::coverage::CoverageRecorder;
 use crate::executor::{AppResponse, Executor};
+use crate::fault::FaultInjector;
 use crate::module::{FailingModule, Module};
 use crate::staking::{Distribution, FailingDistribution, FailingStaking, Staking, StakingSudo};
 use crate::transactions::transactional;
@@ -571,6 +573,18 @@ where
     pub fn dump_wasm_raw(&self, address: &Addr) -> Vec<Record> {
         self.read_module(|router, _, storage| router.wasm.dump_wasm_raw(storage, address))
     }
+
+    /// This records all further calls into contracts (per code id, entry point, message variant
+    /// and returned error) with the given recorder. The same recorder may be shared by many apps.
+    pub fn record_coverage(&mut self, recorder: &CoverageRecorder) {
+        self.init_modules(|router, _, _| router.wasm.set_coverage(recorder.clone()))
+    }
+
+    /// Makes contract queries, and bank sends and contract executions dispatched by contracts,
+    /// fail according to the rules of the given injector. Use `FaultyApi` to break `Api` calls.
+    pub fn inject_faults(&mut self, faults: &FaultInjector) {
+        self.init_modules(|router, _, _| router.wasm.set_faults(faults.clone()))
+    }
 }
 
 impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
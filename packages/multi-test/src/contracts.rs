@@ -11,6 +11,8 @@ use cosmwasm_std::{
 
 use anyhow::{anyhow, bail, Result as AnyResult};
 
+use crate::schema::ContractSchemas;
+
 /// Interface to call into a Contract
 pub trait Contract<T, Q = Empty>
 where
@@ -91,6 +93,7 @@ pub struct ContractWrapper<
     sudo_fn: Option<PermissionedClosure<T4, C, E4, Q>>,
     reply_fn: Option<ReplyClosure<C, E5, Q>>,
     migrate_fn: Option<PermissionedClosure<T6, C, E6, Q>>,
+    schemas: Option<ContractSchemas>,
 }
 
 impl<T1, T2, T3, E1, E2, E3, C, Q> ContractWrapper<T1, T2, T3, E1, E2, E3, C, Q>
@@ -116,6 +119,7 @@ where
             sudo_fn: None,
             reply_fn: None,
             migrate_fn: None,
+            schemas: None,
         }
     }
 
@@ -133,6 +137,7 @@ where
             sudo_fn: None,
             reply_fn: None,
             migrate_fn: None,
+            schemas: None,
         }
     }
 }
@@ -169,6 +174,7 @@ where
             sudo_fn: Some(Box::new(sudo_fn)),
             reply_fn: self.reply_fn,
             migrate_fn: self.migrate_fn,
+            schemas: self.schemas,
         }
     }
 
@@ -187,6 +193,7 @@ where
             sudo_fn: Some(customize_permissioned_fn(sudo_fn)),
             reply_fn: self.reply_fn,
             migrate_fn: self.migrate_fn,
+            schemas: self.schemas,
         }
     }
 
@@ -204,6 +211,7 @@ where
             sudo_fn: self.sudo_fn,
             reply_fn: Some(Box::new(reply_fn)),
             migrate_fn: self.migrate_fn,
+            schemas: self.schemas,
         }
     }
 
@@ -222,6 +230,7 @@ where
             sudo_fn: self.sudo_fn,
             reply_fn: Some(customize_permissioned_fn(reply_fn)),
             migrate_fn: self.migrate_fn,
+            schemas: self.schemas,
         }
     }
 
@@ -240,6 +249,7 @@ where
             sudo_fn: self.sudo_fn,
             reply_fn: self.reply_fn,
             migrate_fn: Some(Box::new(migrate_fn)),
+            schemas: self.schemas,
         }
     }
 
@@ -258,8 +268,18 @@ where
             sudo_fn: self.sudo_fn,
             reply_fn: self.reply_fn,
             migrate_fn: Some(customize_permissioned_fn(migrate_fn)),
+            schemas: self.schemas,
         }
     }
+
+    /// Validates all messages passed to the contract, and the query responses and response data
+    /// it returns, against the given schemas (as generated by `schemars::schema_for!`). A message
+    /// not matching its schema fails the call with `Error::SchemaMismatch`, pointing to the
+    /// offending field.
+    pub fn with_schema_validation(mut self, schemas: ContractSchemas) -> Self {
+        self.schemas = Some(schemas);
+        self
+    }
 }
 
 fn customize_fn<T, C, E, Q>(raw_fn: ContractFn<T, Empty, E, Empty>) -> ContractClosure<T, C, E, Q>
@@ -388,8 +408,15 @@ where
         info: MessageInfo,
         msg: Vec<u8>,
     ) -> AnyResult<Response<C>> {
-        let msg: T1 = from_slice(&msg)?;
-        (self.execute_fn)(deps, env, info, msg).map_err(|err| anyhow!(err))
+        if let Some(schemas) = &self.schemas {
+            schemas.validate_execute(&msg)?;
+        }
+        let parsed: T1 = from_slice(&msg)?;
+        let res = (self.execute_fn)(deps, env, info, parsed).map_err(|err| anyhow!(err))?;
+        if let Some(schemas) = &self.schemas {
+            schemas.validate_execute_response(&msg, res.data.as_deref())?;
+        }
+        Ok(res)
     }
 
     fn instantiate(
@@ -399,22 +426,43 @@ where
         info: MessageInfo,
         msg: Vec<u8>,
     ) -> AnyResult<Response<C>> {
+        if let Some(schemas) = &self.schemas {
+            schemas.validate_instantiate(&msg)?;
+        }
         let msg: T2 = from_slice(&msg)?;
-        (self.instantiate_fn)(deps, env, info, msg).map_err(|err| anyhow!(err))
+        let res = (self.instantiate_fn)(deps, env, info, msg).map_err(|err| anyhow!(err))?;
+        if let Some(schemas) = &self.schemas {
+            schemas.validate_instantiate_response(res.data.as_deref())?;
+        }
+        Ok(res)
     }
 
     fn query(&self, deps: Deps<Q>, env: Env, msg: Vec<u8>) -> AnyResult<Binary> {
-        let msg: T3 = from_slice(&msg)?;
-        (self.query_fn)(deps, env, msg).map_err(|err| anyhow!(err))
+        if let Some(schemas) = &self.schemas {
+            schemas.validate_query(&msg)?;
+        }
+        let parsed: T3 = from_slice(&msg)?;
+        let res = (self.query_fn)(deps, env, parsed).map_err(|err| anyhow!(err))?;
+        if let Some(schemas) = &self.schemas {
+            schemas.validate_query_response(&msg, &res)?;
+        }
+        Ok(res)
     }
 
     // this returns an error if the contract doesn't implement sudo
     fn sudo(&self, deps: DepsMut<Q>, env: Env, msg: Vec<u8>) -> AnyResult<Response<C>> {
-        let msg = from_slice(&msg)?;
-        match &self.sudo_fn {
-            Some(sudo) => sudo(deps, env, msg).map_err(|err| anyhow!(err)),
+        if let Some(schemas) = &self.schemas {
+            schemas.validate_sudo(&msg)?;
+        }
+        let parsed = from_slice(&msg)?;
+        let res = match &self.sudo_fn {
+            Some(sudo) => sudo(deps, env, parsed).map_err(|err| anyhow!(err))?,
             None => bail!("sudo not implemented for contract"),
+        };
+        if let Some(schemas) = &self.schemas {
+            schemas.validate_sudo_response(&msg, res.data.as_deref())?;
         }
+        Ok(res)
     }
 
     // this returns an error if the contract doesn't implement reply
@@ -427,10 +475,17 @@ where
 
     // this returns an error if the contract doesn't implement migrate
     fn migrate(&self, deps: DepsMut<Q>, env: Env, msg: Vec<u8>) -> AnyResult<Response<C>> {
+        if let Some(schemas) = &self.schemas {
+            schemas.validate_migrate(&msg)?;
+        }
         let msg = from_slice(&msg)?;
-        match &self.migrate_fn {
-            Some(migrate) => migrate(deps, env, msg).map_err(|err| anyhow!(err)),
+        let res = match &self.migrate_fn {
+            Some(migrate) => migrate(deps, env, msg).map_err(|err| anyhow!(err))?,
             None => bail!("migrate not implemented for contract"),
+        };
+        if let Some(schemas) = &self.schemas {
+            schemas.validate_migrate_response(res.data.as_deref())?;
         }
+        Ok(res)
     }
 }
@@ -0,0 +1,334 @@
+//! Records which entry points, message variants and errors of the stored contracts were
+//! exercised, so untested paths can be spotted.
+//!
+//! A `CoverageRecorder` is a cheap handle to shared state. It can be installed on many `App`s
+//! (eg. one per test case), and the resulting `CoverageReport` dumped as JSON at the end of the
+//! suite. The recorder is `Send + Sync`, so it may be kept in a `static` shared by all tests.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use schemars::schema::{RootSchema, Schema};
+use serde::{Deserialize, Serialize};
+
+use anyhow::Result as AnyResult;
+
+use crate::schema::msg_variant;
+
+/// Contract entry point called by the `WasmKeeper`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryPoint {
+    Instantiate,
+    Execute,
+    Query,
+    Sudo,
+    Reply,
+    Migrate,
+}
+
+impl EntryPoint {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntryPoint::Instantiate => "instantiate",
+            EntryPoint::Execute => "execute",
+            EntryPoint::Query => "query",
+            EntryPoint::Sudo => "sudo",
+            EntryPoint::Reply => "reply",
+            EntryPoint::Migrate => "migrate",
+        }
+    }
+}
+
+impl fmt::Display for EntryPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Usage statistics of a single entry point of a contract code
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct EntryPointCoverage {
+    /// Number of times the entry point was called
+    pub calls: u64,
+    /// Number of those calls which returned an error
+    pub failures: u64,
+    /// Calls per top-level message variant (eg. `"transfer"` for cw20 `ExecuteMsg::Transfer`).
+    /// Messages which are not an externally tagged enum are counted under `""`, replies under
+    /// their submessage id.
+    pub variants: BTreeMap<String, u64>,
+    /// Number of times every distinct error was returned, keyed by the error message
+    pub errors: BTreeMap<String, u64>,
+}
+
+/// Usage statistics of all entry points of a contract code
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CodeCoverage {
+    pub entry_points: BTreeMap<String, EntryPointCoverage>,
+}
+
+/// Machine-readable summary of recorded contract calls, keyed by code id
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CoverageReport {
+    pub codes: BTreeMap<u64, CodeCoverage>,
+}
+
+impl CoverageReport {
+    pub fn entry_point(
+        &self,
+        code_id: u64,
+        entry_point: EntryPoint,
+    ) -> Option<&EntryPointCoverage> {
+        self.codes
+            .get(&code_id)
+            .and_then(|code| code.entry_points.get(entry_point.as_str()))
+    }
+
+    /// Adds all the counters of `other` to this report. Useful to combine reports of separate
+    /// test binaries.
+    pub fn merge(&mut self, other: &CoverageReport) {
+        for (code_id, code) in &other.codes {
+            let target = self.codes.entry(*code_id).or_default();
+            for (name, ep) in &code.entry_points {
+                let t = target.entry_points.entry(name.clone()).or_default();
+                t.calls += ep.calls;
+                t.failures += ep.failures;
+                for (variant, count) in &ep.variants {
+                    *t.variants.entry(variant.clone()).or_default() += count;
+                }
+                for (err, count) in &ep.errors {
+                    *t.errors.entry(err.clone()).or_default() += count;
+                }
+            }
+        }
+    }
+
+    /// Lists the variants declared in the message schema (as generated by `schemars`) which
+    /// were never called on the given entry point.
+    pub fn uncovered_variants(
+        &self,
+        code_id: u64,
+        entry_point: EntryPoint,
+        schema: &RootSchema,
+    ) -> Vec<String> {
+        let called = self.entry_point(code_id, entry_point);
+        schema_variants(schema)
+            .into_iter()
+            .filter(|variant| !matches!(called, Some(ep) if ep.variants.contains_key(variant)))
+            .collect()
+    }
+
+    pub fn to_json(&self) -> AnyResult<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> AnyResult<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// Names of all variants of an externally tagged enum schema
+fn schema_variants(schema: &RootSchema) -> Vec<String> {
+    let alternatives = schema
+        .schema
+        .subschemas
+        .as_ref()
+        .and_then(|sub| sub.one_of.as_ref().or(sub.any_of.as_ref()));
+    let alternatives = match alternatives {
+        Some(alternatives) => alternatives.iter().collect(),
+        None => vec![],
+    };
+
+    let mut variants = vec![];
+    for alternative in alternatives {
+        if let Schema::Object(obj) = alternative {
+            if let Some(values) = &obj.enum_values {
+                // unit variants are serialized as plain strings
+                variants.extend(values.iter().filter_map(|v| v.as_str().map(str::to_owned)));
+            } else if let Some(object) = &obj.object {
+                variants.extend(object.required.iter().cloned());
+            }
+        }
+    }
+    // `schemars` may also generate a plain string enum at the top level
+    if let Some(values) = &schema.schema.enum_values {
+        variants.extend(values.iter().filter_map(|v| v.as_str().map(str::to_owned)));
+    }
+    variants
+}
+
+/// Shared handle collecting `CoverageReport` data. Install it with `WasmKeeper::with_coverage`
+/// or `App::record_coverage` - all clones write to the same report.
+#[derive(Clone, Debug, Default)]
+pub struct CoverageRecorder {
+    report: Arc<Mutex<CoverageReport>>,
+}
+
+impl CoverageRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a snapshot of everything recorded so far
+    pub fn report(&self) -> CoverageReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    pub fn reset(&self) {
+        *self.report.lock().unwrap() = CoverageReport::default();
+    }
+
+    pub(crate) fn record<T>(
+        &self,
+        code_id: u64,
+        entry_point: EntryPoint,
+        variant: String,
+        res: &AnyResult<T>,
+    ) {
+        let mut report = self.report.lock().unwrap();
+        let ep = report
+            .codes
+            .entry(code_id)
+            .or_default()
+            .entry_points
+            .entry(entry_point.as_str().to_owned())
+            .or_default();
+        ep.calls += 1;
+        *ep.variants.entry(variant).or_default() += 1;
+        if let Err(err) = res {
+            ep.failures += 1;
+            *ep.errors.entry(err.to_string()).or_default() += 1;
+        }
+    }
+}
+
+/// Name of the variant of a raw JSON message, as recorded in the report
+pub(crate) fn raw_msg_variant(msg: &[u8]) -> String {
+    serde_json::from_slice(msg)
+        .ok()
+        .and_then(|msg| msg_variant(&msg))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::{coin, Addr};
+    use schemars::{schema_for, JsonSchema};
+
+    use crate::test_helpers::contracts::{error, payout};
+    use crate::test_helpers::EmptyMsg;
+    use crate::{App, Executor};
+
+    #[allow(dead_code)]
+    #[derive(Serialize, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    enum QueryMsg {
+        Count {},
+        Payout {},
+        Config {},
+    }
+
+    #[test]
+    fn records_calls_variants_and_errors() {
+        let owner = Addr::unchecked("owner");
+        let recorder = CoverageRecorder::new();
+        let mut app = App::new(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, vec![coin(100, "eth")])
+                .unwrap();
+        });
+        app.record_coverage(&recorder);
+
+        let payout_id = app.store_code(payout::contract());
+        let error_id = app.store_code(error::contract(true));
+
+        let msg = payout::InstantiateMessage {
+            payout: coin(5, "eth"),
+        };
+        let payout_addr = app
+            .instantiate_contract(payout_id, owner.clone(), &msg, &[], "Payout", None)
+            .unwrap();
+        let error_addr = app
+            .instantiate_contract(error_id, owner.clone(), &EmptyMsg {}, &[], "Error", None)
+            .unwrap();
+
+        for _ in 0..2 {
+            let _: payout::CountResponse = app
+                .wrap()
+                .query_wasm_smart(&payout_addr, &payout::QueryMsg::Count {})
+                .unwrap();
+        }
+        app.execute_contract(owner.clone(), error_addr.clone(), &EmptyMsg {}, &[])
+            .unwrap_err();
+        app.execute_contract(owner, error_addr, &EmptyMsg {}, &[])
+            .unwrap_err();
+
+        let report = recorder.report();
+
+        let query = report.entry_point(payout_id, EntryPoint::Query).unwrap();
+        assert_eq!(query.calls, 2);
+        assert_eq!(query.failures, 0);
+        assert_eq!(query.variants.get("Count"), Some(&2));
+
+        let execute = report.entry_point(error_id, EntryPoint::Execute).unwrap();
+        assert_eq!(execute.calls, 2);
+        assert_eq!(execute.failures, 2);
+        assert_eq!(execute.variants.get(""), Some(&2));
+        assert_eq!(execute.errors.get("Generic error: Handle failed"), Some(&2));
+
+        assert_eq!(
+            report
+                .entry_point(error_id, EntryPoint::Instantiate)
+                .unwrap()
+                .calls,
+            1
+        );
+        assert_eq!(report.entry_point(error_id, EntryPoint::Query), None);
+    }
+
+    #[test]
+    fn report_round_trips_and_merges() {
+        let recorder = CoverageRecorder::new();
+        recorder.record::<()>(1, EntryPoint::Execute, "transfer".to_owned(), &Ok(()));
+        recorder.record::<()>(
+            1,
+            EntryPoint::Execute,
+            "burn".to_owned(),
+            &Err(anyhow::anyhow!("Unauthorized")),
+        );
+
+        let report = recorder.report();
+        let json = report.to_json().unwrap();
+        assert_eq!(CoverageReport::from_json(&json).unwrap(), report);
+
+        let mut merged = report.clone();
+        merged.merge(&report);
+        let execute = merged.entry_point(1, EntryPoint::Execute).unwrap();
+        assert_eq!(execute.calls, 4);
+        assert_eq!(execute.failures, 2);
+        assert_eq!(execute.variants.get("burn"), Some(&2));
+        assert_eq!(execute.errors.get("Unauthorized"), Some(&2));
+
+        recorder.reset();
+        assert_eq!(recorder.report(), CoverageReport::default());
+    }
+
+    #[test]
+    fn lists_uncovered_variants() {
+        let recorder = CoverageRecorder::new();
+        recorder.record::<()>(3, EntryPoint::Query, "count".to_owned(), &Ok(()));
+
+        let report = recorder.report();
+        let schema = schema_for!(QueryMsg);
+        assert_eq!(
+            report.uncovered_variants(3, EntryPoint::Query, &schema),
+            vec!["payout".to_owned(), "config".to_owned()]
+        );
+        assert_eq!(
+            report.uncovered_variants(4, EntryPoint::Query, &schema),
+            vec!["count".to_owned(), "payout".to_owned(), "config".to_owned()]
+        );
+    }
+}
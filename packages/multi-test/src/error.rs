@@ -1,6 +1,8 @@
 use cosmwasm_std::{WasmMsg, WasmQuery};
 use thiserror::Error;
 
+use crate::fault::FaultTarget;
+
 #[derive(Debug, Error, PartialEq)]
 pub enum Error {
     #[error("Empty attribute key. Value: {value}")]
@@ -23,6 +25,20 @@ pub enum Error {
 
     #[error("Unregistered code id")]
     UnregisteredCodeId(usize),
+
+    #[error("Invalid {kind} at \"{path}\" (schema {schema_path}): {reason}")]
+    SchemaMismatch {
+        kind: String,
+        path: String,
+        schema_path: String,
+        reason: String,
+    },
+
+    #[error("Injected failure of {target} for \"{subject}\"")]
+    InjectedFault {
+        target: FaultTarget,
+        subject: String,
+    },
 }
 
 impl Error {
@@ -43,4 +59,25 @@ impl Error {
     pub fn event_type_too_short(ty: impl Into<String>) -> Self {
         Self::EventTypeTooShort(ty.into())
     }
+
+    pub fn schema_mismatch(
+        kind: impl ToString,
+        path: impl Into<String>,
+        schema_path: impl Into<String>,
+        reason: impl ToString,
+    ) -> Self {
+        Self::SchemaMismatch {
+            kind: kind.to_string(),
+            path: path.into(),
+            schema_path: schema_path.into(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn injected_fault(target: FaultTarget, subject: impl Into<String>) -> Self {
+        Self::InjectedFault {
+            target,
+            subject: subject.into(),
+        }
+    }
 }
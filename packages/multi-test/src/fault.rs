@@ -0,0 +1,501 @@
+//! Failure injection, to exercise the error and rollback paths of contracts.
+//!
+//! A `FaultInjector` is a shared handle to a set of `Fault` rules. Every rule names the kind of
+//! call it breaks (`FaultTarget`), optionally the subject it applies to (address, contract,
+//! public key) and which of the matching calls should fail. Api calls are broken by wrapping
+//! the app `Api` in a `FaultyApi`, bank sends and contract calls dispatched by contracts by
+//! installing the injector with `App::inject_faults`.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use cosmwasm_std::{
+    Addr, Api, CanonicalAddr, RecoverPubkeyError, StdError, StdResult, VerificationError,
+};
+
+use crate::error::Error;
+
+/// Kind of call which may be made to fail
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultTarget {
+    /// `Api::addr_validate`, subject is the validated address
+    AddrValidate,
+    /// `Api::addr_canonicalize`, subject is the human address
+    AddrCanonicalize,
+    /// `Api::addr_humanize`, subject is the canonical address in hex
+    AddrHumanize,
+    /// `Api::secp256k1_verify`, subject is the public key in hex
+    Secp256k1Verify,
+    /// `Api::secp256k1_recover_pubkey`, subject is the message hash in hex
+    Secp256k1RecoverPubkey,
+    /// `Api::ed25519_verify`, subject is the public key in hex
+    Ed25519Verify,
+    /// `Api::ed25519_batch_verify`, subject is empty
+    Ed25519BatchVerify,
+    /// `BankMsg::Send` dispatched by a contract, subject is the recipient
+    BankSend,
+    /// `WasmMsg::Execute` dispatched by a contract, subject is the called contract
+    WasmExecute,
+    /// Smart or raw query of a contract, subject is the queried contract
+    WasmQuery,
+}
+
+impl FaultTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FaultTarget::AddrValidate => "addr_validate",
+            FaultTarget::AddrCanonicalize => "addr_canonicalize",
+            FaultTarget::AddrHumanize => "addr_humanize",
+            FaultTarget::Secp256k1Verify => "secp256k1_verify",
+            FaultTarget::Secp256k1RecoverPubkey => "secp256k1_recover_pubkey",
+            FaultTarget::Ed25519Verify => "ed25519_verify",
+            FaultTarget::Ed25519BatchVerify => "ed25519_batch_verify",
+            FaultTarget::BankSend => "bank_send",
+            FaultTarget::WasmExecute => "wasm_execute",
+            FaultTarget::WasmQuery => "wasm_query",
+        }
+    }
+}
+
+impl fmt::Display for FaultTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Which of the matching calls are failed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Trigger {
+    Always,
+    /// Only the nth call (1-based)
+    Nth(u64),
+    /// The nth call and all the following ones
+    FromNth(u64),
+    /// The first n calls
+    Times(u64),
+}
+
+impl Trigger {
+    fn fires(&self, call: u64) -> bool {
+        match *self {
+            Trigger::Always => true,
+            Trigger::Nth(n) => call == n,
+            Trigger::FromNth(n) => call >= n,
+            Trigger::Times(n) => call <= n,
+        }
+    }
+}
+
+type Matcher = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Single failure injection rule. By default it fails every call of its target.
+#[derive(Clone)]
+pub struct Fault {
+    target: FaultTarget,
+    matcher: Option<Matcher>,
+    trigger: Trigger,
+}
+
+impl Fault {
+    pub fn new(target: FaultTarget) -> Self {
+        Self {
+            target,
+            matcher: None,
+            trigger: Trigger::Always,
+        }
+    }
+
+    /// Only applies to calls with exactly this subject
+    pub fn matching(self, subject: impl Into<String>) -> Self {
+        let subject = subject.into();
+        self.matching_with(move |s| s == subject)
+    }
+
+    /// Only applies to calls for which the predicate holds on the subject
+    pub fn matching_with(
+        mut self,
+        predicate: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.matcher = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Fails only the nth (1-based) matching call
+    pub fn on_call(mut self, n: u64) -> Self {
+        self.trigger = Trigger::Nth(n);
+        self
+    }
+
+    /// Fails the nth (1-based) matching call and every one after it
+    pub fn from_call(mut self, n: u64) -> Self {
+        self.trigger = Trigger::FromNth(n);
+        self
+    }
+
+    /// Fails the first n matching calls
+    pub fn times(mut self, n: u64) -> Self {
+        self.trigger = Trigger::Times(n);
+        self
+    }
+
+    fn applies(&self, target: FaultTarget, subject: &str) -> bool {
+        self.target == target
+            && match &self.matcher {
+                Some(matcher) => matcher(subject),
+                None => true,
+            }
+    }
+}
+
+impl fmt::Debug for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fault")
+            .field("target", &self.target)
+            .field("matching", &self.matcher.is_some())
+            .field("trigger", &self.trigger)
+            .finish()
+    }
+}
+
+/// Record of a call failed by the injector
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InjectedFault {
+    pub target: FaultTarget,
+    pub subject: String,
+}
+
+#[derive(Debug, Default)]
+struct Faults {
+    /// Rules with the number of matching calls seen so far
+    rules: Vec<(Fault, u64)>,
+    injected: Vec<InjectedFault>,
+}
+
+/// Shared handle to a set of `Fault` rules. All clones see the same rules and counters.
+#[derive(Clone, Debug, Default)]
+pub struct FaultInjector {
+    faults: Arc<Mutex<Faults>>,
+}
+
+impl FaultInjector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule. Calls are counted per rule, starting from the moment it is added.
+    pub fn inject(&self, fault: Fault) {
+        self.faults.lock().unwrap().rules.push((fault, 0));
+    }
+
+    /// Removes all rules. The log of injected failures is kept.
+    pub fn clear(&self) {
+        self.faults.lock().unwrap().rules.clear();
+    }
+
+    /// All the calls failed so far, in order
+    pub fn injected(&self) -> Vec<InjectedFault> {
+        self.faults.lock().unwrap().injected.clone()
+    }
+
+    /// Counts the call against all applying rules, and returns the error to fail it with if
+    /// any of them fires.
+    pub(crate) fn check(&self, target: FaultTarget, subject: &str) -> Option<Error> {
+        let mut faults = self.faults.lock().unwrap();
+        let mut fires = false;
+        for (fault, calls) in faults.rules.iter_mut() {
+            if fault.applies(target, subject) {
+                *calls += 1;
+                fires |= fault.trigger.fires(*calls);
+            }
+        }
+        if !fires {
+            return None;
+        }
+
+        faults.injected.push(InjectedFault {
+            target,
+            subject: subject.to_owned(),
+        });
+        Some(Error::injected_fault(target, subject))
+    }
+
+    fn check_std(&self, target: FaultTarget, subject: &str) -> StdResult<()> {
+        match self.check(target, subject) {
+            Some(err) => Err(StdError::generic_err(err.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `Api` wrapper failing the calls selected by a `FaultInjector`, and delegating all the
+/// others. Install it with `AppBuilder::with_api`.
+pub struct FaultyApi<A> {
+    inner: A,
+    faults: FaultInjector,
+}
+
+impl<A: Api> FaultyApi<A> {
+    pub fn new(inner: A, faults: FaultInjector) -> Self {
+        Self { inner, faults }
+    }
+
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl<A: Api> Api for FaultyApi<A> {
+    fn addr_validate(&self, human: &str) -> StdResult<Addr> {
+        self.faults.check_std(FaultTarget::AddrValidate, human)?;
+        self.inner.addr_validate(human)
+    }
+
+    fn addr_canonicalize(&self, human: &str) -> StdResult<CanonicalAddr> {
+        self.faults
+            .check_std(FaultTarget::AddrCanonicalize, human)?;
+        self.inner.addr_canonicalize(human)
+    }
+
+    fn addr_humanize(&self, canonical: &CanonicalAddr) -> StdResult<Addr> {
+        self.faults
+            .check_std(FaultTarget::AddrHumanize, &to_hex(canonical.as_slice()))?;
+        self.inner.addr_humanize(canonical)
+    }
+
+    fn secp256k1_verify(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        if self
+            .faults
+            .check(FaultTarget::Secp256k1Verify, &to_hex(public_key))
+            .is_some()
+        {
+            return Err(VerificationError::GenericErr);
+        }
+        self.inner
+            .secp256k1_verify(message_hash, signature, public_key)
+    }
+
+    fn secp256k1_recover_pubkey(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        recovery_param: u8,
+    ) -> Result<Vec<u8>, RecoverPubkeyError> {
+        if self
+            .faults
+            .check(FaultTarget::Secp256k1RecoverPubkey, &to_hex(message_hash))
+            .is_some()
+        {
+            return Err(RecoverPubkeyError::unknown_err(0));
+        }
+        self.inner
+            .secp256k1_recover_pubkey(message_hash, signature, recovery_param)
+    }
+
+    fn ed25519_verify(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        if self
+            .faults
+            .check(FaultTarget::Ed25519Verify, &to_hex(public_key))
+            .is_some()
+        {
+            return Err(VerificationError::GenericErr);
+        }
+        self.inner.ed25519_verify(message, signature, public_key)
+    }
+
+    fn ed25519_batch_verify(
+        &self,
+        messages: &[&[u8]],
+        signatures: &[&[u8]],
+        public_keys: &[&[u8]],
+    ) -> Result<bool, VerificationError> {
+        if self
+            .faults
+            .check(FaultTarget::Ed25519BatchVerify, "")
+            .is_some()
+        {
+            return Err(VerificationError::GenericErr);
+        }
+        self.inner
+            .ed25519_batch_verify(messages, signatures, public_keys)
+    }
+
+    fn debug(&self, message: &str) {
+        self.inner.debug(message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockApi;
+    use cosmwasm_std::{coins, BankMsg, Empty, ReplyOn, SubMsg, SubMsgResult};
+
+    use crate::test_helpers::contracts::{payout, reflect};
+    use crate::test_helpers::{CustomMsg, EmptyMsg};
+    use crate::{custom_app, AppBuilder, Executor};
+
+    #[test]
+    fn triggers_select_matching_calls() {
+        let faults = FaultInjector::new();
+        let api = FaultyApi::new(MockApi::default(), faults.clone());
+
+        faults.inject(
+            Fault::new(FaultTarget::AddrValidate)
+                .matching("bob")
+                .on_call(2),
+        );
+        api.addr_validate("bob").unwrap();
+        api.addr_validate("alice").unwrap();
+        let err = api.addr_validate("bob").unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Injected failure of addr_validate for \"bob\"")
+        );
+        api.addr_validate("bob").unwrap();
+
+        faults.clear();
+        faults.inject(Fault::new(FaultTarget::AddrCanonicalize).times(1));
+        faults.inject(Fault::new(FaultTarget::AddrCanonicalize).from_call(3));
+        api.addr_canonicalize("alice").unwrap_err();
+        api.addr_canonicalize("alice").unwrap();
+        api.addr_canonicalize("alice").unwrap_err();
+        api.addr_canonicalize("alice").unwrap_err();
+
+        faults.inject(
+            Fault::new(FaultTarget::Secp256k1Verify).matching_with(|key| key.starts_with("02")),
+        );
+        let err = api
+            .secp256k1_verify(&[0; 32], &[0; 64], &[2; 33])
+            .unwrap_err();
+        assert_eq!(err, VerificationError::GenericErr);
+
+        assert_eq!(
+            faults.injected(),
+            vec![
+                InjectedFault {
+                    target: FaultTarget::AddrValidate,
+                    subject: "bob".to_owned()
+                },
+                InjectedFault {
+                    target: FaultTarget::AddrCanonicalize,
+                    subject: "alice".to_owned()
+                },
+                InjectedFault {
+                    target: FaultTarget::AddrCanonicalize,
+                    subject: "alice".to_owned()
+                },
+                InjectedFault {
+                    target: FaultTarget::AddrCanonicalize,
+                    subject: "alice".to_owned()
+                },
+                InjectedFault {
+                    target: FaultTarget::Secp256k1Verify,
+                    subject: to_hex(&[2; 33])
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn failing_api_breaks_contract_queries() {
+        let owner = Addr::unchecked("owner");
+        let faults = FaultInjector::new();
+        let mut app = AppBuilder::new()
+            .with_api(FaultyApi::new(MockApi::default(), faults.clone()))
+            .build(|_, _, _| {});
+
+        let code_id = app.store_code(payout::contract());
+        let msg = payout::InstantiateMessage {
+            payout: coins(5, "eth").pop().unwrap(),
+        };
+        let addr = app
+            .instantiate_contract(code_id, owner, &msg, &[], "Payout", None)
+            .unwrap();
+
+        faults.inject(Fault::new(FaultTarget::AddrValidate).matching(addr.as_str()));
+        let err = app
+            .wrap()
+            .query_wasm_smart::<payout::CountResponse>(&addr, &payout::QueryMsg::Count {})
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Injected failure of addr_validate"));
+    }
+
+    #[test]
+    fn failed_submessages_are_replied() {
+        let owner = Addr::unchecked("owner");
+        let faults = FaultInjector::new();
+        let mut app = custom_app::<CustomMsg, Empty, _>(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(100, "eth"))
+                .unwrap();
+        });
+        app.inject_faults(&faults);
+
+        let reflect_id = app.store_code(reflect::contract());
+        let reflect_addr = app
+            .instantiate_contract(
+                reflect_id,
+                owner.clone(),
+                &EmptyMsg {},
+                &[],
+                "Reflect",
+                None,
+            )
+            .unwrap();
+
+        faults.inject(Fault::new(FaultTarget::BankSend).matching("bob"));
+        let send = |to: &str, id| SubMsg {
+            id,
+            msg: BankMsg::Send {
+                to_address: to.to_owned(),
+                amount: coins(1, "eth"),
+            }
+            .into(),
+            gas_limit: None,
+            reply_on: ReplyOn::Error,
+        };
+        let msg = reflect::Message {
+            messages: vec![send("carol", 1), send("bob", 2)],
+        };
+        app.execute_contract(owner, reflect_addr.clone(), &msg, &coins(2, "eth"))
+            .unwrap();
+
+        // the failed send was replied to, the successful one was executed
+        let reply: cosmwasm_std::Reply = app
+            .wrap()
+            .query_wasm_smart(&reflect_addr, &reflect::QueryMsg::Reply { id: 2 })
+            .unwrap();
+        assert_eq!(
+            reply.result,
+            SubMsgResult::Err("Injected failure of bank_send for \"bob\"".to_owned())
+        );
+        assert_eq!(
+            app.wrap().query_all_balances("carol").unwrap(),
+            coins(1, "eth")
+        );
+        assert_eq!(app.wrap().query_all_balances("bob").unwrap(), vec![]);
+
+        faults.inject(Fault::new(FaultTarget::WasmQuery).matching(reflect_addr.as_str()));
+        app.wrap()
+            .query_wasm_smart::<payout::CountResponse>(&reflect_addr, &reflect::QueryMsg::Count {})
+            .unwrap_err();
+        assert_eq!(faults.injected().len(), 2);
+    }
+}
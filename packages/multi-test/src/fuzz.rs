@@ -0,0 +1,437 @@
+//! Property based testing of contracts on top of `App`.
+//!
+//! A `Fuzzer` builds random sequences of actions (a sender picked from a list plus a message
+//! produced by one of the registered generators), executes them against a fresh app and checks
+//! all the registered invariants after every step. Failing actions are fine - they are expected to
+//! leave the state untouched - only broken invariants are reported. When an invariant breaks, the
+//! sequence of actions is shrunk to a (locally) minimal one still breaking it.
+//!
+//! Everything is driven by a seed, so any failure can be reproduced by running with the seed
+//! reported in `FuzzFailure`.
+
+use std::fmt;
+use std::ops::Range;
+
+use cosmwasm_std::{Addr, CosmosMsg};
+use schemars::JsonSchema;
+
+use anyhow::Result as AnyResult;
+
+use crate::executor::Executor;
+
+/// Small deterministic random number generator (SplitMix64). It is not cryptographically
+/// secure, but it is fast and gives the same sequence for the same seed on every platform.
+#[derive(Clone, Debug)]
+pub struct FuzzRng {
+    state: u64,
+}
+
+impl FuzzRng {
+    pub fn new(seed: u64) -> Self {
+        FuzzRng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number from the range (exclusive on the upper end). Panics on empty range.
+    pub fn gen_range(&mut self, range: Range<u64>) -> u64 {
+        assert!(range.start < range.end, "empty range {:?}", range);
+        range.start + self.next_u64() % (range.end - range.start)
+    }
+
+    /// Returns `true` with the given probability
+    pub fn gen_bool(&mut self, probability: f64) -> bool {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 <= probability
+    }
+
+    /// Picks a random element of the slice. Panics on empty slice.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        let idx = self.gen_range(0..items.len() as u64);
+        &items[idx as usize]
+    }
+}
+
+/// Single step of a fuzzed sequence
+#[derive(Clone, Debug, PartialEq)]
+pub struct Action<C>
+where
+    C: Clone + fmt::Debug + PartialEq + JsonSchema,
+{
+    pub sender: Addr,
+    pub msg: CosmosMsg<C>,
+}
+
+type Setup<A> = Box<dyn Fn() -> A>;
+type Generator<C> = Box<dyn Fn(&mut FuzzRng, &Addr) -> CosmosMsg<C>>;
+type Invariant<A> = Box<dyn Fn(&A) -> AnyResult<()>>;
+type Hook<A> = Box<dyn Fn(&mut A)>;
+
+/// Reproducible description of a broken invariant
+#[derive(Clone, Debug)]
+pub struct FuzzFailure<C>
+where
+    C: Clone + fmt::Debug + PartialEq + JsonSchema,
+{
+    /// Seed of the failing run
+    pub seed: u64,
+    /// Name of the broken invariant
+    pub invariant: String,
+    /// Error returned by the invariant
+    pub error: String,
+    /// Length of the sequence of actions which first broke the invariant
+    pub original_len: usize,
+    /// Shrunk sequence of actions breaking the invariant after its last step
+    pub actions: Vec<Action<C>>,
+}
+
+impl<C> fmt::Display for FuzzFailure<C>
+where
+    C: Clone + fmt::Debug + PartialEq + JsonSchema,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Invariant \"{}\" broken (seed {}): {}",
+            self.invariant, self.seed, self.error
+        )?;
+        writeln!(
+            f,
+            "Minimal sequence ({} of {} actions):",
+            self.actions.len(),
+            self.original_len
+        )?;
+        for (idx, action) in self.actions.iter().enumerate() {
+            writeln!(f, "  {}. {}: {:?}", idx + 1, action.sender, action.msg)?;
+        }
+        Ok(())
+    }
+}
+
+/// Property based test runner. `A` is the app the actions are executed on (usually some `App`),
+/// created from scratch by the `setup` function for every run.
+pub struct Fuzzer<A, C>
+where
+    C: Clone + fmt::Debug + PartialEq + JsonSchema,
+{
+    setup: Setup<A>,
+    senders: Vec<Addr>,
+    generators: Vec<Generator<C>>,
+    invariants: Vec<(String, Invariant<A>)>,
+    after_step: Option<Hook<A>>,
+    seed: u64,
+    runs: u32,
+    steps: u32,
+}
+
+impl<A, C> Fuzzer<A, C>
+where
+    A: Executor<C>,
+    C: Clone + fmt::Debug + PartialEq + JsonSchema + 'static,
+{
+    /// By default 10 runs of 100 steps each are executed, starting with seed 0
+    pub fn new(setup: impl Fn() -> A + 'static) -> Self {
+        Fuzzer {
+            setup: Box::new(setup),
+            senders: vec![],
+            generators: vec![],
+            invariants: vec![],
+            after_step: None,
+            seed: 0,
+            runs: 10,
+            steps: 100,
+        }
+    }
+
+    /// Accounts actions are sent from, every action picks one at random
+    pub fn with_senders(mut self, senders: impl IntoIterator<Item = Addr>) -> Self {
+        self.senders.extend(senders);
+        self
+    }
+
+    /// Registers a message generator. Every action uses a random one of them, called with the
+    /// picked sender.
+    pub fn with_action(
+        mut self,
+        generator: impl Fn(&mut FuzzRng, &Addr) -> CosmosMsg<C> + 'static,
+    ) -> Self {
+        self.generators.push(Box::new(generator));
+        self
+    }
+
+    /// Registers a named invariant, checked after every step (including failed ones)
+    pub fn with_invariant(
+        mut self,
+        name: impl Into<String>,
+        invariant: impl Fn(&A) -> AnyResult<()> + 'static,
+    ) -> Self {
+        self.invariants.push((name.into(), Box::new(invariant)));
+        self
+    }
+
+    /// Called after every step, before invariants are checked - eg. to advance the block
+    pub fn with_after_step(mut self, hook: impl Fn(&mut A) + 'static) -> Self {
+        self.after_step = Some(Box::new(hook));
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_runs(mut self, runs: u32) -> Self {
+        self.runs = runs;
+        self
+    }
+
+    pub fn with_steps(mut self, steps: u32) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    /// Executes all the runs, returning the first (shrunk) failure
+    pub fn run(&self) -> Result<(), FuzzFailure<C>> {
+        assert!(!self.senders.is_empty(), "no senders registered");
+        assert!(!self.generators.is_empty(), "no actions registered");
+
+        let mut seeds = FuzzRng::new(self.seed);
+        for _ in 0..self.runs {
+            let seed = seeds.next_u64();
+            self.run_seed(seed)?;
+        }
+        Ok(())
+    }
+
+    /// Like `run`, but panics with a readable description of the failure
+    #[track_caller]
+    pub fn check(&self) {
+        if let Err(failure) = self.run() {
+            panic!("{}", failure);
+        }
+    }
+
+    /// Executes a single run for the given seed (as reported in `FuzzFailure`)
+    pub fn run_seed(&self, seed: u64) -> Result<(), FuzzFailure<C>> {
+        let mut rng = FuzzRng::new(seed);
+        let mut app = (self.setup)();
+        let mut actions = vec![];
+
+        for _ in 0..self.steps {
+            let sender = rng.choose(&self.senders).clone();
+            let msg = (rng.choose(&self.generators))(&mut rng, &sender);
+            let action = Action { sender, msg };
+            actions.push(action.clone());
+
+            if let Err((invariant, error)) = self.step(&mut app, action) {
+                let original_len = actions.len();
+                let actions = self.shrink(&invariant, actions);
+                // the shrunk sequence breaks the same invariant, maybe with another error
+                let error = self.replay(&actions).map_or(error, |(_, error)| error);
+                return Err(FuzzFailure {
+                    seed,
+                    invariant,
+                    error,
+                    original_len,
+                    actions,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes a single action and verifies the invariants, returning the first broken one
+    fn step(&self, app: &mut A, action: Action<C>) -> Result<(), (String, String)> {
+        // errors are a valid outcome, as long as they don't break any invariant
+        let _ = app.execute(action.sender, action.msg);
+        if let Some(hook) = &self.after_step {
+            hook(app);
+        }
+        for (name, invariant) in &self.invariants {
+            invariant(app).map_err(|err| (name.clone(), err.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Replays the actions on a fresh app, returns the broken invariant if any
+    fn replay(&self, actions: &[Action<C>]) -> Option<(String, String)> {
+        let mut app = (self.setup)();
+        actions
+            .iter()
+            .find_map(|action| self.step(&mut app, action.clone()).err())
+    }
+
+    /// Removes ever smaller chunks of actions, as long as the remaining sequence still breaks
+    /// the given invariant first. Every kept sequence is also truncated right after the breaking
+    /// step. Repeats until no single action can be removed anymore.
+    fn shrink(&self, invariant: &str, mut actions: Vec<Action<C>>) -> Vec<Action<C>> {
+        loop {
+            let len = actions.len();
+            let mut chunk = len / 2;
+            while chunk > 0 {
+                let mut start = 0;
+                while start < actions.len() {
+                    let end = (start + chunk).min(actions.len());
+                    let mut candidate = actions[..start].to_vec();
+                    candidate.extend_from_slice(&actions[end..]);
+                    let broken = self.replay(&candidate);
+                    if matches!(broken, Some((name, _)) if name == invariant) {
+                        actions = self.truncate(candidate);
+                    } else {
+                        start += chunk;
+                    }
+                }
+                chunk /= 2;
+            }
+            if actions.len() == len {
+                return actions;
+            }
+        }
+    }
+
+    /// Cuts the actions following the first step breaking an invariant
+    fn truncate(&self, mut actions: Vec<Action<C>>) -> Vec<Action<C>> {
+        let mut app = (self.setup)();
+        let breaking = actions
+            .iter()
+            .position(|action| self.step(&mut app, action.clone()).is_err());
+        if let Some(idx) = breaking {
+            actions.truncate(idx + 1);
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::{coins, BankMsg, Empty, Uint128};
+
+    use crate::{next_block, App};
+
+    const DENOM: &str = "eth";
+
+    fn users() -> Vec<Addr> {
+        ["alice", "bob", "carol"]
+            .iter()
+            .map(|name| Addr::unchecked(*name))
+            .collect()
+    }
+
+    fn setup() -> App {
+        App::new(|router, _, storage| {
+            for user in users() {
+                router
+                    .bank
+                    .init_balance(storage, &user, coins(100, DENOM))
+                    .unwrap();
+            }
+        })
+    }
+
+    fn send(rng: &mut FuzzRng, _sender: &Addr) -> CosmosMsg<Empty> {
+        BankMsg::Send {
+            to_address: rng.choose(&users()).to_string(),
+            // sometimes more than the balance, so some sends fail
+            amount: coins(rng.gen_range(1..150) as u128, DENOM),
+        }
+        .into()
+    }
+
+    fn total_supply(app: &App) -> AnyResult<Uint128> {
+        users().iter().try_fold(Uint128::zero(), |total, user| {
+            Ok(total + app.wrap().query_balance(user, DENOM)?.amount)
+        })
+    }
+
+    fn fuzzer() -> Fuzzer<App, Empty> {
+        Fuzzer::new(setup)
+            .with_senders(users())
+            .with_action(send)
+            .with_after_step(|app| app.update_block(next_block))
+            .with_invariant("supply is constant", |app| {
+                let total = total_supply(app)?;
+                anyhow::ensure!(total.u128() == 300, "total supply is {}", total);
+                Ok(())
+            })
+    }
+
+    #[test]
+    fn rng_is_deterministic() {
+        let mut a = FuzzRng::new(42);
+        let mut b = FuzzRng::new(42);
+        for _ in 0..100 {
+            let x = a.gen_range(10..20);
+            assert_eq!(x, b.gen_range(10..20));
+            assert!((10..20).contains(&x));
+        }
+        assert_ne!(FuzzRng::new(1).next_u64(), FuzzRng::new(2).next_u64());
+    }
+
+    #[test]
+    fn holding_invariants_pass() {
+        fuzzer().with_runs(3).with_steps(30).check();
+    }
+
+    #[test]
+    fn broken_invariant_is_shrunk() {
+        let fuzzer = fuzzer().with_invariant("bob stays poor", |app| {
+            let balance = app.wrap().query_balance("bob", DENOM)?.amount;
+            anyhow::ensure!(balance.u128() <= 150, "bob has {}", balance);
+            Ok(())
+        });
+
+        let failure = fuzzer.run().unwrap_err();
+        assert_eq!(failure.invariant, "bob stays poor");
+        assert!(failure.actions.len() <= failure.original_len);
+        // every action is needed: dropping any of them fixes the sequence
+        for idx in 0..failure.actions.len() {
+            let mut actions = failure.actions.clone();
+            actions.remove(idx);
+            assert_eq!(fuzzer.replay(&actions), None);
+        }
+        assert!(fuzzer.replay(&failure.actions).is_some());
+
+        // same seed, same failure
+        let again = fuzzer.run_seed(failure.seed).unwrap_err();
+        assert_eq!(again.actions, failure.actions);
+    }
+
+    #[test]
+    fn shrinking_keeps_the_broken_invariant() {
+        let fuzzer = fuzzer()
+            .with_runs(10)
+            .with_invariant("bob stays poor", |app| {
+                let balance = app.wrap().query_balance("bob", DENOM)?.amount;
+                anyhow::ensure!(balance.u128() <= 150, "bob has {}", balance);
+                Ok(())
+            })
+            .with_invariant("carol stays rich", |app| {
+                let balance = app.wrap().query_balance("carol", DENOM)?.amount;
+                anyhow::ensure!(balance.u128() >= 20, "carol has {}", balance);
+                Ok(())
+            });
+
+        let mut seeds = FuzzRng::new(7);
+        for _ in 0..10 {
+            let failure = fuzzer.run_seed(seeds.next_u64()).unwrap_err();
+            // the reported error is the one of the shrunk sequence
+            assert_eq!(
+                fuzzer.replay(&failure.actions),
+                Some((failure.invariant.clone(), failure.error.clone()))
+            );
+            // dropping any action doesn't break the same invariant anymore
+            for idx in 0..failure.actions.len() {
+                let mut actions = failure.actions.clone();
+                actions.remove(idx);
+                let broken = fuzzer.replay(&actions).map(|(name, _)| name);
+                assert_ne!(broken, Some(failure.invariant.clone()));
+            }
+        }
+    }
+}
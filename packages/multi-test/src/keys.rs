@@ -0,0 +1,245 @@
+//! Deterministic key pairs to sign messages in tests.
+//!
+//! The signatures are verified by the `Api` crypto functions exactly like on chain, and the
+//! derived bech32 addresses are valid `Addr`s for the default `MockApi`. This allows testing
+//! contracts authorizing actions by off-chain signatures (permits, relayed transactions).
+
+use bech32::{ToBase32, Variant};
+use cosmwasm_std::Addr;
+use k256::ecdsa::signature::DigestSigner;
+use k256::ecdsa::{recoverable, Signature, SigningKey};
+use ripemd160::Ripemd160;
+use sha2::{Digest, Sha256};
+
+/// SHA-256 hash of the data, as expected as `message_hash` by `Api::secp256k1_verify`
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+fn bech32_address(prefix: &str, data: &[u8]) -> Addr {
+    let address =
+        bech32::encode(prefix, data.to_base32(), Variant::Bech32).expect("invalid bech32 prefix");
+    Addr::unchecked(address)
+}
+
+/// Secp256k1 key pair, as used by Cosmos SDK accounts
+#[derive(Clone)]
+pub struct Secp256k1KeyPair {
+    signing_key: SigningKey,
+}
+
+impl Secp256k1KeyPair {
+    /// Derives the key from the seed, so every test run uses the same keys
+    pub fn from_seed(seed: &str) -> Self {
+        let signing_key =
+            SigningKey::from_bytes(&sha256(seed.as_bytes())).expect("invalid secp256k1 key");
+        Self { signing_key }
+    }
+
+    /// Compressed SEC1 encoded public key (33 bytes)
+    pub fn public_key(&self) -> Vec<u8> {
+        self.signing_key.verifying_key().to_bytes().to_vec()
+    }
+
+    /// Account address, derived like the Cosmos SDK does: `ripemd160(sha256(public_key))`
+    pub fn address(&self, prefix: &str) -> Addr {
+        let hash = Ripemd160::digest(&sha256(&self.public_key()));
+        bech32_address(prefix, &hash)
+    }
+
+    /// Signs the SHA-256 hash of the message. Returns the 64 byte `r || s` signature to be
+    /// verified with `secp256k1_verify(&sha256(message), &signature, &public_key)`.
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        let signature: Signature = self.signing_key.sign_digest(Sha256::new().chain(message));
+        signature.as_ref().to_vec()
+    }
+
+    /// Like `sign`, also returning the recovery param to use with `secp256k1_recover_pubkey`
+    pub fn sign_recoverable(&self, message: &[u8]) -> (Vec<u8>, u8) {
+        let signature: recoverable::Signature =
+            self.signing_key.sign_digest(Sha256::new().chain(message));
+        let recovery_param = signature.recovery_id().into();
+        (Signature::from(signature).as_ref().to_vec(), recovery_param)
+    }
+}
+
+/// Ed25519 key pair, as used by Tendermint validators
+#[derive(Clone, Copy)]
+pub struct Ed25519KeyPair {
+    signing_key: ed25519_zebra::SigningKey,
+}
+
+impl Ed25519KeyPair {
+    /// Derives the key from the seed, so every test run uses the same keys
+    pub fn from_seed(seed: &str) -> Self {
+        let signing_key = ed25519_zebra::SigningKey::from(sha256(seed.as_bytes()));
+        Self { signing_key }
+    }
+
+    /// Raw public key (32 bytes)
+    pub fn public_key(&self) -> Vec<u8> {
+        let key = ed25519_zebra::VerificationKey::from(&self.signing_key);
+        <[u8; 32]>::from(key).to_vec()
+    }
+
+    /// Address derived like Tendermint does: the first 20 bytes of `sha256(public_key)`
+    pub fn address(&self, prefix: &str) -> Addr {
+        bech32_address(prefix, &sha256(&self.public_key())[..20])
+    }
+
+    /// Signs the message itself. Returns the 64 byte signature to be verified with
+    /// `ed25519_verify(&message, &signature, &public_key)`.
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        <[u8; 64]>::from(self.signing_key.sign(message)).to_vec()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockApi;
+    use cosmwasm_std::{
+        to_binary, Api, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdError,
+    };
+    use serde::{Deserialize, Serialize};
+
+    use crate::contracts::{Contract, ContractWrapper};
+    use crate::test_helpers::EmptyMsg;
+    use crate::{App, Executor};
+
+    #[test]
+    fn signatures_verify_with_api() {
+        let api = MockApi::default();
+        let message = b"permit: 100 tokens to bob";
+
+        let key = Secp256k1KeyPair::from_seed("alice");
+        let signature = key.sign(message);
+        assert_eq!(signature.len(), 64);
+        assert_eq!(key.public_key().len(), 33);
+        assert!(api
+            .secp256k1_verify(&sha256(message), &signature, &key.public_key())
+            .unwrap());
+        assert!(!api
+            .secp256k1_verify(&sha256(b"other"), &signature, &key.public_key())
+            .unwrap());
+
+        let (signature, recovery_param) = key.sign_recoverable(message);
+        let recovered = api
+            .secp256k1_recover_pubkey(&sha256(message), &signature, recovery_param)
+            .unwrap();
+        assert!(api
+            .secp256k1_verify(&sha256(message), &signature, &recovered)
+            .unwrap());
+        assert_eq!(recovered.len(), 65);
+        assert_eq!(recovered[1..33], key.public_key()[1..]);
+
+        let key = Ed25519KeyPair::from_seed("validator");
+        let signature = key.sign(message);
+        assert!(api
+            .ed25519_verify(message, &signature, &key.public_key())
+            .unwrap());
+        assert!(!api
+            .ed25519_verify(b"other", &signature, &key.public_key())
+            .unwrap());
+    }
+
+    #[test]
+    fn keys_and_addresses_are_deterministic() {
+        let api = MockApi::default();
+
+        let alice = Secp256k1KeyPair::from_seed("alice");
+        assert_eq!(
+            alice.public_key(),
+            Secp256k1KeyPair::from_seed("alice").public_key()
+        );
+        assert_ne!(
+            alice.public_key(),
+            Secp256k1KeyPair::from_seed("bob").public_key()
+        );
+
+        let addr = alice.address("cosmos");
+        assert!(addr.as_str().starts_with("cosmos1"));
+        assert_eq!(addr.as_str().len(), 45);
+        assert_eq!(api.addr_validate(addr.as_str()).unwrap(), addr);
+
+        let addr = Ed25519KeyPair::from_seed("validator").address("juno");
+        assert!(addr.as_str().starts_with("juno1"));
+        assert_eq!(api.addr_validate(addr.as_str()).unwrap(), addr);
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    struct PermitMsg {
+        message: Binary,
+        signature: Binary,
+        public_key: Binary,
+    }
+
+    fn permit_contract() -> Box<dyn Contract<Empty>> {
+        fn execute(
+            deps: DepsMut,
+            _env: Env,
+            _info: MessageInfo,
+            msg: PermitMsg,
+        ) -> Result<Response, StdError> {
+            let valid = deps.api.secp256k1_verify(
+                &sha256(&msg.message),
+                &msg.signature,
+                &msg.public_key,
+            )?;
+            if !valid {
+                return Err(StdError::generic_err("Invalid signature"));
+            }
+            Ok(Response::new().set_data(msg.message))
+        }
+
+        fn instantiate(
+            _deps: DepsMut,
+            _env: Env,
+            _info: MessageInfo,
+            _msg: EmptyMsg,
+        ) -> Result<Response, StdError> {
+            Ok(Response::new())
+        }
+
+        fn query(_deps: Deps, _env: Env, _msg: EmptyMsg) -> Result<Binary, StdError> {
+            to_binary(&EmptyMsg {})
+        }
+
+        Box::new(ContractWrapper::new(execute, instantiate, query))
+    }
+
+    #[test]
+    fn contracts_verify_signatures() {
+        let mut app = App::default();
+        let alice = Secp256k1KeyPair::from_seed("alice");
+        let relayer = Secp256k1KeyPair::from_seed("relayer").address("cosmos");
+
+        let code_id = app.store_code(permit_contract());
+        let contract = app
+            .instantiate_contract(code_id, relayer.clone(), &EmptyMsg {}, &[], "Permit", None)
+            .unwrap();
+
+        let message = b"transfer 5 to bob".to_vec();
+        let msg = PermitMsg {
+            signature: alice.sign(&message).into(),
+            message: message.clone().into(),
+            public_key: alice.public_key().into(),
+        };
+        let res = app
+            .execute_contract(relayer.clone(), contract.clone(), &msg, &[])
+            .unwrap();
+        assert_eq!(res.data, Some(message.into()));
+
+        let msg = PermitMsg {
+            message: b"transfer 500 to bob".to_vec().into(),
+            ..msg
+        };
+        let err = app
+            .execute_contract(relayer, contract, &msg, &[])
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<StdError>().unwrap(),
+            StdError::generic_err("Invalid signature")
+        );
+    }
+}
@@ -10,10 +10,15 @@ mod app;
 mod bank;
 #[allow(clippy::type_complexity)]
 mod contracts;
+mod coverage;
 pub mod custom_handler;
 pub mod error;
 mod executor;
+mod fault;
+pub mod fuzz;
+mod keys;
 mod module;
+mod schema;
 mod staking;
 mod test_helpers;
 mod transactions;
@@ -25,7 +30,13 @@ pub use crate::app::{
 };
 pub use crate::bank::{Bank, BankKeeper, BankSudo};
 pub use crate::contracts::{Contract, ContractWrapper};
+pub use crate::coverage::{
+    CodeCoverage, CoverageRecorder, CoverageReport, EntryPoint, EntryPointCoverage,
+};
 pub use crate::executor::{AppResponse, Executor};
+pub use crate::fault::{Fault, FaultInjector, FaultTarget, FaultyApi, InjectedFault};
+pub use crate::keys::{sha256, Ed25519KeyPair, Secp256k1KeyPair};
 pub use crate::module::{FailingModule, Module};
+pub use crate::schema::ContractSchemas;
 pub use crate::staking::{FailingDistribution, FailingStaking, Staking, StakingSudo};
 pub use crate::wasm::{Wasm, WasmKeeper, WasmSudo};
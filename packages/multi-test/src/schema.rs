@@ -0,0 +1,951 @@
+//! Optional validation of contract messages against their published JSON schemas.
+//!
+//! Contracts export the schemas of their messages (usually in `bin/schema.rs`) with `schemars`.
+//! A message may still serialize fine in Rust and yet not match what was published (eg. when a
+//! field was renamed with serde attributes, or a custom `Serialize` impl is used). Registering
+//! `ContractSchemas` on a `ContractWrapper` makes every call into the contract verify its incoming
+//! JSON, and the JSON it returns (query results and `Response::data`), against those schemas.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use schemars::schema::{InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec};
+use serde_json::Value;
+
+use anyhow::{bail, Result as AnyResult};
+
+use crate::error::Error;
+
+/// Set of schemas used to validate the messages passed to and returned from a contract.
+/// All of them are optional, only the messages with a registered schema are validated.
+///
+/// Query, execute and sudo responses are registered per message variant - the name of the
+/// top-level key of the message (eg. `"balance"` for `{"balance": {"address": "..."}}`).
+/// The responses of instantiate, execute, sudo and migrate are the `data` of the returned
+/// `Response`, which must be set when a schema is registered for it.
+#[derive(Clone, Debug, Default)]
+pub struct ContractSchemas {
+    instantiate: Option<RootSchema>,
+    execute: Option<RootSchema>,
+    query: Option<RootSchema>,
+    sudo: Option<RootSchema>,
+    migrate: Option<RootSchema>,
+    instantiate_response: Option<RootSchema>,
+    execute_responses: HashMap<String, RootSchema>,
+    query_responses: HashMap<String, RootSchema>,
+    sudo_responses: HashMap<String, RootSchema>,
+    migrate_response: Option<RootSchema>,
+}
+
+impl ContractSchemas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_instantiate(mut self, schema: RootSchema) -> Self {
+        self.instantiate = Some(schema);
+        self
+    }
+
+    pub fn with_execute(mut self, schema: RootSchema) -> Self {
+        self.execute = Some(schema);
+        self
+    }
+
+    pub fn with_query(mut self, schema: RootSchema) -> Self {
+        self.query = Some(schema);
+        self
+    }
+
+    pub fn with_sudo(mut self, schema: RootSchema) -> Self {
+        self.sudo = Some(schema);
+        self
+    }
+
+    pub fn with_migrate(mut self, schema: RootSchema) -> Self {
+        self.migrate = Some(schema);
+        self
+    }
+
+    /// Registers the schema of the `Response::data` returned by instantiate
+    pub fn with_instantiate_response(mut self, schema: RootSchema) -> Self {
+        self.instantiate_response = Some(schema);
+        self
+    }
+
+    /// Registers the schema of the `Response::data` returned for the `variant` execute message
+    pub fn with_execute_response(mut self, variant: impl Into<String>, schema: RootSchema) -> Self {
+        self.execute_responses.insert(variant.into(), schema);
+        self
+    }
+
+    /// Registers the schema of the response returned for the `variant` query
+    pub fn with_query_response(mut self, variant: impl Into<String>, schema: RootSchema) -> Self {
+        self.query_responses.insert(variant.into(), schema);
+        self
+    }
+
+    /// Registers the schema of the `Response::data` returned for the `variant` sudo message
+    pub fn with_sudo_response(mut self, variant: impl Into<String>, schema: RootSchema) -> Self {
+        self.sudo_responses.insert(variant.into(), schema);
+        self
+    }
+
+    /// Registers the schema of the `Response::data` returned by migrate
+    pub fn with_migrate_response(mut self, schema: RootSchema) -> Self {
+        self.migrate_response = Some(schema);
+        self
+    }
+
+    pub fn validate_instantiate(&self, msg: &[u8]) -> AnyResult<()> {
+        validate_msg(MsgKind::Instantiate, self.instantiate.as_ref(), msg)
+    }
+
+    pub fn validate_execute(&self, msg: &[u8]) -> AnyResult<()> {
+        validate_msg(MsgKind::Execute, self.execute.as_ref(), msg)
+    }
+
+    pub fn validate_query(&self, msg: &[u8]) -> AnyResult<()> {
+        validate_msg(MsgKind::Query, self.query.as_ref(), msg)
+    }
+
+    pub fn validate_sudo(&self, msg: &[u8]) -> AnyResult<()> {
+        validate_msg(MsgKind::Sudo, self.sudo.as_ref(), msg)
+    }
+
+    pub fn validate_migrate(&self, msg: &[u8]) -> AnyResult<()> {
+        validate_msg(MsgKind::Migrate, self.migrate.as_ref(), msg)
+    }
+
+    pub fn validate_instantiate_response(&self, data: Option<&[u8]>) -> AnyResult<()> {
+        let schema = self.instantiate_response.as_ref();
+        validate_data(MsgKind::InstantiateResponse, schema, data)
+    }
+
+    /// Validates the data returned for the given execute message. Responses to messages
+    /// without a registered response schema are accepted as they are.
+    pub fn validate_execute_response(&self, msg: &[u8], data: Option<&[u8]>) -> AnyResult<()> {
+        let schema = variant_schema(&self.execute_responses, msg);
+        validate_data(MsgKind::ExecuteResponse, schema, data)
+    }
+
+    /// Validates the response returned for the given query message. Responses to queries
+    /// without a registered response schema are accepted as they are.
+    pub fn validate_query_response(&self, query: &[u8], response: &[u8]) -> AnyResult<()> {
+        let schema = variant_schema(&self.query_responses, query);
+        validate_msg(MsgKind::QueryResponse, schema, response)
+    }
+
+    /// Validates the data returned for the given sudo message, see `validate_execute_response`
+    pub fn validate_sudo_response(&self, msg: &[u8], data: Option<&[u8]>) -> AnyResult<()> {
+        let schema = variant_schema(&self.sudo_responses, msg);
+        validate_data(MsgKind::SudoResponse, schema, data)
+    }
+
+    pub fn validate_migrate_response(&self, data: Option<&[u8]>) -> AnyResult<()> {
+        let schema = self.migrate_response.as_ref();
+        validate_data(MsgKind::MigrateResponse, schema, data)
+    }
+}
+
+/// Returns the schema registered for the variant of the given message
+fn variant_schema<'a>(
+    schemas: &'a HashMap<String, RootSchema>,
+    msg: &[u8],
+) -> Option<&'a RootSchema> {
+    serde_json::from_slice(msg)
+        .ok()
+        .and_then(|msg| msg_variant(&msg))
+        .and_then(|variant| schemas.get(&variant))
+}
+
+/// Returns the name of the variant of an externally tagged enum message: the only key of
+/// the top-level object, or the string itself for unit variants.
+pub(crate) fn msg_variant(msg: &Value) -> Option<String> {
+    match msg {
+        Value::Object(map) if map.len() == 1 => map.keys().next().cloned(),
+        Value::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum MsgKind {
+    Instantiate,
+    Execute,
+    Query,
+    Sudo,
+    Migrate,
+    InstantiateResponse,
+    ExecuteResponse,
+    QueryResponse,
+    SudoResponse,
+    MigrateResponse,
+}
+
+impl fmt::Display for MsgKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MsgKind::Instantiate => "instantiate message",
+            MsgKind::Execute => "execute message",
+            MsgKind::Query => "query message",
+            MsgKind::Sudo => "sudo message",
+            MsgKind::Migrate => "migrate message",
+            MsgKind::InstantiateResponse => "instantiate response data",
+            MsgKind::ExecuteResponse => "execute response data",
+            MsgKind::QueryResponse => "query response",
+            MsgKind::SudoResponse => "sudo response data",
+            MsgKind::MigrateResponse => "migrate response data",
+        };
+        f.write_str(name)
+    }
+}
+
+fn validate_msg(kind: MsgKind, schema: Option<&RootSchema>, msg: &[u8]) -> AnyResult<()> {
+    let schema = match schema {
+        Some(schema) => schema,
+        None => return Ok(()),
+    };
+
+    let value: Value = match serde_json::from_slice(msg) {
+        Ok(value) => value,
+        Err(err) => bail!(Error::schema_mismatch(kind, "", "#", err)),
+    };
+
+    Validator { root: schema }
+        .validate_object(&schema.schema, &value, "", "#")
+        .map_err(|v| Error::schema_mismatch(kind, v.instance_path, v.schema_path, v.reason).into())
+}
+
+/// Validates the `Response::data` of a call, which must be set when there is a schema for it
+fn validate_data(kind: MsgKind, schema: Option<&RootSchema>, data: Option<&[u8]>) -> AnyResult<()> {
+    match (schema, data) {
+        (Some(_), None) => bail!(Error::schema_mismatch(
+            kind,
+            "",
+            "#",
+            "no data was returned"
+        )),
+        (schema, Some(data)) => validate_msg(kind, schema, data),
+        (None, None) => Ok(()),
+    }
+}
+
+/// Single failure of a value to match its schema
+struct Violation {
+    /// JSON pointer to the offending part of the validated message
+    instance_path: String,
+    /// Location of the failing keyword in the schema, `$ref`s are followed
+    schema_path: String,
+    reason: String,
+}
+
+/// Validates values against the subset of JSON schema draft 7 emitted by `schemars`.
+/// The `pattern`, `patternProperties` and `if`/`then`/`else` keywords are not checked.
+struct Validator<'a> {
+    root: &'a RootSchema,
+}
+
+type Validation = Result<(), Violation>;
+
+fn violation(instance_path: &str, schema_path: String, reason: impl Into<String>) -> Violation {
+    Violation {
+        instance_path: instance_path.to_owned(),
+        schema_path,
+        reason: reason.into(),
+    }
+}
+
+impl<'a> Validator<'a> {
+    fn validate(
+        &self,
+        schema: &Schema,
+        value: &Value,
+        path: &str,
+        schema_path: &str,
+    ) -> Validation {
+        match schema {
+            Schema::Bool(true) => Ok(()),
+            Schema::Bool(false) => Err(violation(
+                path,
+                schema_path.to_owned(),
+                "no value is allowed here",
+            )),
+            Schema::Object(obj) => self.validate_object(obj, value, path, schema_path),
+        }
+    }
+
+    fn validate_object(
+        &self,
+        schema: &SchemaObject,
+        value: &Value,
+        path: &str,
+        schema_path: &str,
+    ) -> Validation {
+        if let Some(reference) = &schema.reference {
+            let name = reference.trim_start_matches("#/definitions/");
+            return match self.root.definitions.get(name) {
+                Some(def) => self.validate(def, value, path, reference),
+                None => Err(violation(
+                    path,
+                    format!("{}/$ref", schema_path),
+                    format!("unresolved reference {}", reference),
+                )),
+            };
+        }
+
+        if let Some(types) = &schema.instance_type {
+            let matches = match types {
+                SingleOrVec::Single(ty) => type_matches(ty, value),
+                SingleOrVec::Vec(tys) => tys.iter().any(|ty| type_matches(ty, value)),
+            };
+            if !matches {
+                return Err(violation(
+                    path,
+                    format!("{}/type", schema_path),
+                    format!("expected {}, got {}", type_names(types), value_type(value)),
+                ));
+            }
+        }
+
+        if let Some(format) = &schema.format {
+            check_format(format, value)
+                .map_err(|reason| violation(path, format!("{}/format", schema_path), reason))?;
+        }
+
+        if let Some(values) = &schema.enum_values {
+            if !values.contains(value) {
+                return Err(violation(
+                    path,
+                    format!("{}/enum", schema_path),
+                    format!("{} is not one of {:?}", value, values),
+                ));
+            }
+        }
+
+        if let Some(expected) = &schema.const_value {
+            if expected != value {
+                return Err(violation(
+                    path,
+                    format!("{}/const", schema_path),
+                    format!("expected {}", expected),
+                ));
+            }
+        }
+
+        if let Some(subschemas) = &schema.subschemas {
+            if let Some(all_of) = &subschemas.all_of {
+                for (idx, sub) in all_of.iter().enumerate() {
+                    self.validate(sub, value, path, &format!("{}/allOf/{}", schema_path, idx))?;
+                }
+            }
+            if let Some(any_of) = &subschemas.any_of {
+                let sub_path = format!("{}/anyOf", schema_path);
+                self.validate_alternatives(any_of, value, path, &sub_path, false)?;
+            }
+            if let Some(one_of) = &subschemas.one_of {
+                let sub_path = format!("{}/oneOf", schema_path);
+                self.validate_alternatives(one_of, value, path, &sub_path, true)?;
+            }
+            if let Some(not) = &subschemas.not {
+                let sub_path = format!("{}/not", schema_path);
+                if self.validate(not, value, path, &sub_path).is_ok() {
+                    return Err(violation(path, sub_path, "value matches forbidden schema"));
+                }
+            }
+        }
+
+        if let (Some(number), Some(n)) = (&schema.number, value.as_f64()) {
+            let violated = [
+                ("minimum", number.minimum.filter(|min| n < *min)),
+                ("maximum", number.maximum.filter(|max| n > *max)),
+                (
+                    "exclusiveMinimum",
+                    number.exclusive_minimum.filter(|min| n <= *min),
+                ),
+                (
+                    "exclusiveMaximum",
+                    number.exclusive_maximum.filter(|max| n >= *max),
+                ),
+            ];
+            for (keyword, limit) in violated {
+                if let Some(limit) = limit {
+                    return Err(violation(
+                        path,
+                        format!("{}/{}", schema_path, keyword),
+                        format!("{} violates {} {}", n, keyword, limit),
+                    ));
+                }
+            }
+            if let Some(multiple_of) = number.multiple_of {
+                if (n / multiple_of).fract() != 0.0 {
+                    return Err(violation(
+                        path,
+                        format!("{}/multipleOf", schema_path),
+                        format!("{} is not a multiple of {}", n, multiple_of),
+                    ));
+                }
+            }
+        }
+
+        if let (Some(string), Some(s)) = (&schema.string, value.as_str()) {
+            let len = s.chars().count() as u32;
+            if let Some(min) = under(string.min_length, len) {
+                return Err(violation(
+                    path,
+                    format!("{}/minLength", schema_path),
+                    format!("string is shorter than {}", min),
+                ));
+            }
+            if let Some(max) = over(string.max_length, len) {
+                return Err(violation(
+                    path,
+                    format!("{}/maxLength", schema_path),
+                    format!("string is longer than {}", max),
+                ));
+            }
+        }
+
+        if let (Some(array), Some(items)) = (&schema.array, value.as_array()) {
+            let len = items.len() as u32;
+            if let Some(min) = under(array.min_items, len) {
+                return Err(violation(
+                    path,
+                    format!("{}/minItems", schema_path),
+                    format!("expected at least {} items", min),
+                ));
+            }
+            if let Some(max) = over(array.max_items, len) {
+                return Err(violation(
+                    path,
+                    format!("{}/maxItems", schema_path),
+                    format!("expected at most {} items", max),
+                ));
+            }
+            if array.unique_items == Some(true) {
+                for (idx, item) in items.iter().enumerate() {
+                    if items[..idx].contains(item) {
+                        return Err(violation(
+                            &format!("{}/{}", path, idx),
+                            format!("{}/uniqueItems", schema_path),
+                            "duplicated item",
+                        ));
+                    }
+                }
+            }
+            match &array.items {
+                Some(SingleOrVec::Single(item_schema)) => {
+                    for (idx, item) in items.iter().enumerate() {
+                        self.validate(
+                            item_schema,
+                            item,
+                            &format!("{}/{}", path, idx),
+                            &format!("{}/items", schema_path),
+                        )?;
+                    }
+                }
+                Some(SingleOrVec::Vec(item_schemas)) => {
+                    for (idx, item) in items.iter().enumerate() {
+                        let item_path = format!("{}/{}", path, idx);
+                        match item_schemas.get(idx) {
+                            Some(item_schema) => self.validate(
+                                item_schema,
+                                item,
+                                &item_path,
+                                &format!("{}/items/{}", schema_path, idx),
+                            )?,
+                            None => {
+                                if let Some(additional) = &array.additional_items {
+                                    self.validate(
+                                        additional,
+                                        item,
+                                        &item_path,
+                                        &format!("{}/additionalItems", schema_path),
+                                    )?;
+                                }
+                            }
+                        }
+                    }
+                }
+                None => {}
+            }
+            if let Some(contains) = &array.contains {
+                let sub_path = format!("{}/contains", schema_path);
+                if !items
+                    .iter()
+                    .any(|item| self.validate(contains, item, path, &sub_path).is_ok())
+                {
+                    return Err(violation(path, sub_path, "no item matches the schema"));
+                }
+            }
+        }
+
+        if let (Some(object), Some(fields)) = (&schema.object, value.as_object()) {
+            let len = fields.len() as u32;
+            if let Some(min) = under(object.min_properties, len) {
+                return Err(violation(
+                    path,
+                    format!("{}/minProperties", schema_path),
+                    format!("expected at least {} properties", min),
+                ));
+            }
+            if let Some(max) = over(object.max_properties, len) {
+                return Err(violation(
+                    path,
+                    format!("{}/maxProperties", schema_path),
+                    format!("expected at most {} properties", max),
+                ));
+            }
+            for required in &object.required {
+                if !fields.contains_key(required) {
+                    return Err(violation(
+                        path,
+                        format!("{}/required", schema_path),
+                        format!("missing property \"{}\"", required),
+                    ));
+                }
+            }
+            for (name, field) in fields {
+                let field_path = format!("{}/{}", path, name);
+                if let Some(names) = &object.property_names {
+                    self.validate(
+                        names,
+                        &Value::String(name.clone()),
+                        &field_path,
+                        &format!("{}/propertyNames", schema_path),
+                    )?;
+                }
+                match object.properties.get(name) {
+                    Some(field_schema) => self.validate(
+                        field_schema,
+                        field,
+                        &field_path,
+                        &format!("{}/properties/{}", schema_path, name),
+                    )?,
+                    None => match object.additional_properties.as_deref() {
+                        Some(Schema::Bool(false)) => {
+                            return Err(violation(
+                                &field_path,
+                                format!("{}/additionalProperties", schema_path),
+                                format!("unknown property \"{}\"", name),
+                            ))
+                        }
+                        Some(additional) => self.validate(
+                            additional,
+                            field,
+                            &field_path,
+                            &format!("{}/additionalProperties", schema_path),
+                        )?,
+                        None => {}
+                    },
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles `anyOf` and `oneOf`. When no alternative matches, the violation of the alternative
+    /// which got deepest into the value is reported - for enums it is the one matching the
+    /// variant tag, which is far more helpful than a plain "no variant matched".
+    fn validate_alternatives(
+        &self,
+        alternatives: &[Schema],
+        value: &Value,
+        path: &str,
+        schema_path: &str,
+        exactly_one: bool,
+    ) -> Validation {
+        let mut matched = vec![];
+        let mut closest: Option<Violation> = None;
+        for (idx, alternative) in alternatives.iter().enumerate() {
+            match self.validate(
+                alternative,
+                value,
+                path,
+                &format!("{}/{}", schema_path, idx),
+            ) {
+                Ok(()) => matched.push(idx),
+                Err(v) => match &closest {
+                    Some(c) if c.instance_path.len() >= v.instance_path.len() => {}
+                    _ => closest = Some(v),
+                },
+            }
+        }
+
+        match (matched.len(), closest) {
+            (1, _) => Ok(()),
+            (n, _) if n > 1 && !exactly_one => Ok(()),
+            (0, Some(closest)) if closest.instance_path.len() > path.len() => Err(closest),
+            (0, _) => Err(violation(
+                path,
+                schema_path.to_owned(),
+                "value does not match any of the alternatives",
+            )),
+            (_, _) => Err(violation(
+                path,
+                schema_path.to_owned(),
+                format!("value matches more than one alternative: {:?}", matched),
+            )),
+        }
+    }
+}
+
+/// Returns the `min` limit if `len` is below it
+fn under(min: Option<u32>, len: u32) -> Option<u32> {
+    min.filter(|min| len < *min)
+}
+
+/// Returns the `max` limit if `len` exceeds it
+fn over(max: Option<u32>, len: u32) -> Option<u32> {
+    max.filter(|max| len > *max)
+}
+
+fn type_matches(ty: &InstanceType, value: &Value) -> bool {
+    match ty {
+        InstanceType::Null => value.is_null(),
+        InstanceType::Boolean => value.is_boolean(),
+        InstanceType::Object => value.is_object(),
+        InstanceType::Array => value.is_array(),
+        InstanceType::Number => value.is_number(),
+        InstanceType::String => value.is_string(),
+        InstanceType::Integer => {
+            value.is_i64()
+                || value.is_u64()
+                || matches!(value.as_f64(), Some(f) if f.fract() == 0.0)
+        }
+    }
+}
+
+fn type_name(ty: &InstanceType) -> &'static str {
+    match ty {
+        InstanceType::Null => "null",
+        InstanceType::Boolean => "boolean",
+        InstanceType::Object => "object",
+        InstanceType::Array => "array",
+        InstanceType::Number => "number",
+        InstanceType::String => "string",
+        InstanceType::Integer => "integer",
+    }
+}
+
+fn type_names(types: &SingleOrVec<InstanceType>) -> String {
+    match types {
+        SingleOrVec::Single(ty) => type_name(ty).to_owned(),
+        SingleOrVec::Vec(tys) => tys.iter().map(type_name).collect::<Vec<_>>().join(" or "),
+    }
+}
+
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// `schemars` marks Rust integers with their exact type in `format`, check they fit
+fn check_format(format: &str, value: &Value) -> Result<(), String> {
+    let (min, max): (i128, i128) = match format {
+        "uint8" => (0, u8::MAX.into()),
+        "uint16" => (0, u16::MAX.into()),
+        "uint32" => (0, u32::MAX.into()),
+        "uint64" | "uint" => (0, u64::MAX.into()),
+        "int8" => (i8::MIN.into(), i8::MAX.into()),
+        "int16" => (i16::MIN.into(), i16::MAX.into()),
+        "int32" => (i32::MIN.into(), i32::MAX.into()),
+        "int64" | "int" => (i64::MIN.into(), i64::MAX.into()),
+        _ => return Ok(()),
+    };
+    let n = match value {
+        Value::Number(n) => n,
+        _ => return Ok(()),
+    };
+    let n = match (n.as_u64(), n.as_i64()) {
+        (Some(n), _) => i128::from(n),
+        (None, Some(n)) => i128::from(n),
+        (None, None) => return Err(format!("{} is not a valid {}", n, format)),
+    };
+    if n < min || n > max {
+        return Err(format!("{} is out of range for {}", n, format));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::{
+        to_binary, to_vec, Addr, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response,
+        StdError, Uint128,
+    };
+    use schemars::{schema_for, JsonSchema};
+    use serde::{Deserialize, Serialize};
+
+    use crate::{App, ContractWrapper, Executor};
+
+    #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    enum ExecuteMsg {
+        Transfer {
+            recipient: String,
+            amount: Uint128,
+        },
+        SetLimit {
+            limit: Option<u32>,
+        },
+        Reset {},
+        // the schema limits the fee, which Rust doesn't check when deserializing
+        SetFee {
+            #[schemars(range(max = 100))]
+            percent: u32,
+        },
+    }
+
+    #[derive(Serialize, JsonSchema)]
+    struct BalanceResponse {
+        balance: Uint128,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "snake_case")]
+    enum QueryMsg {
+        Balance {},
+    }
+
+    fn assert_mismatch(res: AnyResult<()>, path: &str, schema_path: &str) {
+        match res.unwrap_err().downcast().unwrap() {
+            Error::SchemaMismatch {
+                path: p,
+                schema_path: s,
+                ..
+            } => {
+                assert_eq!(path, p);
+                assert_eq!(schema_path, s);
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn valid_messages_pass() {
+        let schemas = ContractSchemas::new().with_execute(schema_for!(ExecuteMsg));
+
+        let msgs = [
+            ExecuteMsg::Transfer {
+                recipient: "bob".to_owned(),
+                amount: Uint128::new(100),
+            },
+            ExecuteMsg::SetLimit { limit: Some(7) },
+            ExecuteMsg::SetLimit { limit: None },
+            ExecuteMsg::Reset {},
+            ExecuteMsg::SetFee { percent: 100 },
+        ];
+        for msg in &msgs {
+            schemas.validate_execute(&to_vec(msg).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn unregistered_schemas_are_not_checked() {
+        let schemas = ContractSchemas::new();
+        schemas.validate_execute(br#"{"foo": 1}"#).unwrap();
+        schemas
+            .validate_query_response(br#"{"foo": {}}"#, b"[]")
+            .unwrap();
+    }
+
+    #[test]
+    fn reports_failing_path() {
+        let schemas = ContractSchemas::new().with_execute(schema_for!(ExecuteMsg));
+
+        // amount is a Uint128, which is serialized as a string
+        let msg = br#"{"transfer": {"recipient": "bob", "amount": 100}}"#;
+        assert_mismatch(
+            schemas.validate_execute(msg),
+            "/transfer/amount",
+            "#/definitions/Uint128/type",
+        );
+
+        // limit is an u32
+        let msg = br#"{"set_limit": {"limit": 5000000000}}"#;
+        assert_mismatch(
+            schemas.validate_execute(msg),
+            "/set_limit/limit",
+            "#/oneOf/1/properties/set_limit/properties/limit/format",
+        );
+
+        let msg = br#"{"transfer": {"amount": "100"}}"#;
+        assert_mismatch(
+            schemas.validate_execute(msg),
+            "/transfer",
+            "#/oneOf/0/properties/transfer/required",
+        );
+
+        let msg = br#"{"set_fee": {"percent": 101}}"#;
+        assert_mismatch(
+            schemas.validate_execute(msg),
+            "/set_fee/percent",
+            "#/oneOf/3/properties/set_fee/properties/percent/maximum",
+        );
+
+        let msg = br#"{"burn": {}}"#;
+        assert_mismatch(schemas.validate_execute(msg), "", "#/oneOf");
+
+        let msg = b"not json";
+        assert_mismatch(schemas.validate_execute(msg), "", "#");
+    }
+
+    #[test]
+    fn validates_query_responses_per_variant() {
+        let schemas =
+            ContractSchemas::new().with_query_response("balance", schema_for!(BalanceResponse));
+
+        let query = br#"{"balance": {"address": "bob"}}"#;
+        schemas
+            .validate_query_response(query, br#"{"balance": "12"}"#)
+            .unwrap();
+        assert_mismatch(
+            schemas.validate_query_response(query, br#"{"amount": "12"}"#),
+            "",
+            "#/required",
+        );
+
+        // other queries are not checked
+        schemas
+            .validate_query_response(br#"{"token_info": {}}"#, br#"{"amount": "12"}"#)
+            .unwrap();
+    }
+
+    #[test]
+    fn validates_response_data() {
+        let schemas = ContractSchemas::new()
+            .with_instantiate_response(schema_for!(BalanceResponse))
+            .with_execute_response("transfer", schema_for!(BalanceResponse))
+            .with_sudo_response("transfer", schema_for!(BalanceResponse))
+            .with_migrate_response(schema_for!(BalanceResponse));
+        let valid: &[u8] = br#"{"balance": "12"}"#;
+        let invalid: &[u8] = br#""12""#;
+        let transfer = br#"{"transfer": {"recipient": "bob", "amount": "12"}}"#;
+
+        schemas.validate_instantiate_response(Some(valid)).unwrap();
+        assert_mismatch(
+            schemas.validate_instantiate_response(Some(invalid)),
+            "",
+            "#/type",
+        );
+        schemas
+            .validate_execute_response(transfer, Some(valid))
+            .unwrap();
+        assert_mismatch(
+            schemas.validate_execute_response(transfer, Some(invalid)),
+            "",
+            "#/type",
+        );
+        assert_mismatch(
+            schemas.validate_sudo_response(transfer, Some(invalid)),
+            "",
+            "#/type",
+        );
+        assert_mismatch(
+            schemas.validate_migrate_response(Some(invalid)),
+            "",
+            "#/type",
+        );
+
+        // the data is required when there is a schema for it
+        assert_mismatch(schemas.validate_migrate_response(None), "", "#");
+        assert_mismatch(schemas.validate_execute_response(transfer, None), "", "#");
+
+        // other messages are not checked
+        schemas
+            .validate_execute_response(br#"{"reset": {}}"#, Some(invalid))
+            .unwrap();
+        schemas
+            .validate_execute_response(br#"{"reset": {}}"#, None)
+            .unwrap();
+        ContractSchemas::new()
+            .validate_instantiate_response(None)
+            .unwrap();
+    }
+
+    fn instantiate(_: DepsMut, _: Env, _: MessageInfo, _: Empty) -> Result<Response, StdError> {
+        Ok(Response::new())
+    }
+
+    // Returns data which doesn't match the `BalanceResponse` schema for transfers
+    fn execute(_: DepsMut, _: Env, _: MessageInfo, msg: ExecuteMsg) -> Result<Response, StdError> {
+        match msg {
+            ExecuteMsg::Transfer { amount, .. } => {
+                Ok(Response::new().set_data(to_binary(&amount)?))
+            }
+            _ => Ok(Response::new()),
+        }
+    }
+
+    // Returns a response which doesn't match the `BalanceResponse` schema
+    fn query(_: Deps, _: Env, _: serde_json::Value) -> Result<Binary, StdError> {
+        to_binary(&Uint128::new(12))
+    }
+
+    #[test]
+    fn wrapper_validates_messages() {
+        let mut app = App::default();
+        let owner = Addr::unchecked("owner");
+
+        let schemas = ContractSchemas::new()
+            .with_execute(schema_for!(ExecuteMsg))
+            .with_execute_response("transfer", schema_for!(BalanceResponse))
+            .with_query_response("balance", schema_for!(BalanceResponse));
+        let contract =
+            ContractWrapper::new(execute, instantiate, query).with_schema_validation(schemas);
+        let code_id = app.store_code(Box::new(contract));
+        let addr = app
+            .instantiate_contract(code_id, owner.clone(), &Empty {}, &[], "Schema", None)
+            .unwrap();
+        let unchecked_id =
+            app.store_code(Box::new(ContractWrapper::new(execute, instantiate, query)));
+        let unchecked = app
+            .instantiate_contract(unchecked_id, owner.clone(), &Empty {}, &[], "Raw", None)
+            .unwrap();
+
+        app.execute_contract(owner.clone(), addr.clone(), &ExecuteMsg::Reset {}, &[])
+            .unwrap();
+
+        // Rust deserialization accepts the fee, but it is above the maximum of the schema
+        let too_high = ExecuteMsg::SetFee { percent: 150 };
+        app.execute_contract(owner.clone(), unchecked.clone(), &too_high, &[])
+            .unwrap();
+        let err = app
+            .execute_contract(owner.clone(), addr.clone(), &too_high, &[])
+            .unwrap_err();
+        assert!(matches!(
+            err.root_cause().downcast_ref(),
+            Some(Error::SchemaMismatch { path, .. }) if path == "/set_fee/percent"
+        ));
+
+        // the returned data is checked too
+        let transfer = ExecuteMsg::Transfer {
+            recipient: "bob".to_owned(),
+            amount: Uint128::new(100),
+        };
+        app.execute_contract(owner.clone(), unchecked, &transfer, &[])
+            .unwrap();
+        let err = app
+            .execute_contract(owner, addr.clone(), &transfer, &[])
+            .unwrap_err();
+        assert!(matches!(
+            err.root_cause().downcast_ref(),
+            Some(Error::SchemaMismatch { kind, .. }) if kind == "execute response data"
+        ));
+
+        let err = app
+            .wrap()
+            .query_wasm_smart::<Uint128>(&addr, &QueryMsg::Balance {})
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid query response"));
+    }
+}
@@ -4,9 +4,9 @@ use std::ops::Deref;
 
 use cosmwasm_std::{
     to_binary, Addr, Api, Attribute, BankMsg, Binary, BlockInfo, Coin, ContractInfo,
-    ContractInfoResponse, CustomQuery, Deps, DepsMut, Env, Event, MessageInfo, Order, Querier,
-    QuerierWrapper, Record, Reply, ReplyOn, Response, StdResult, Storage, SubMsg, SubMsgResponse,
-    SubMsgResult, TransactionInfo, WasmMsg, WasmQuery,
+    ContractInfoResponse, CosmosMsg, CustomQuery, Deps, DepsMut, Env, Event, MessageInfo, Order,
+    Querier, QuerierWrapper, Record, Reply, ReplyOn, Response, StdResult, Storage, SubMsg,
+    SubMsgResponse, SubMsgResult, TransactionInfo, WasmMsg, WasmQuery,
 };
 use cosmwasm_storage::{prefixed, prefixed_read, PrefixedStorage, ReadonlyPrefixedStorage};
 use prost::Message;
@@ -18,8 +18,10 @@ use cw_storage_plus::Map;
 
 use crate::app::{CosmosRouter, RouterQuerier};
 use crate::contracts::Contract;
+use crate::coverage::{raw_msg_variant, CoverageRecorder, EntryPoint};
 use crate::error::Error;
 use crate::executor::AppResponse;
+use crate::fault::{FaultInjector, FaultTarget};
 use crate::transactions::transactional;
 use cosmwasm_std::testing::mock_wasmd_attr;
 
@@ -100,6 +102,10 @@ pub struct WasmKeeper<ExecC, QueryC> {
     /// code is in-memory lookup that stands in for wasm code
     /// this can only be edited on the WasmRouter, and just read in caches
     codes: HashMap<usize, Box<dyn Contract<ExecC, QueryC>>>,
+    /// Optional recorder of all the calls into contracts
+    coverage: Option<CoverageRecorder>,
+    /// Optional failure injection for contract queries and dispatched messages
+    faults: Option<FaultInjector>,
     /// Just markers to make type elision fork when using it as `Wasm` trait
     _p: std::marker::PhantomData<QueryC>,
 }
@@ -108,6 +114,8 @@ impl<ExecC, QueryC> Default for WasmKeeper<ExecC, QueryC> {
     fn default() -> Self {
         Self {
             codes: HashMap::default(),
+            coverage: None,
+            faults: None,
             _p: std::marker::PhantomData,
         }
     }
@@ -129,10 +137,12 @@ where
         match request {
             WasmQuery::Smart { contract_addr, msg } => {
                 let addr = api.addr_validate(&contract_addr)?;
+                self.check_fault(FaultTarget::WasmQuery, addr.as_str())?;
                 self.query_smart(addr, api, storage, querier, block, msg.into())
             }
             WasmQuery::Raw { contract_addr, key } => {
                 let addr = api.addr_validate(&contract_addr)?;
+                self.check_fault(FaultTarget::WasmQuery, addr.as_str())?;
                 Ok(self.query_raw(addr, storage, &key))
             }
             WasmQuery::ContractInfo { contract_addr } => {
@@ -192,6 +202,67 @@ impl<ExecC, QueryC> WasmKeeper<ExecC, QueryC> {
             .map_err(Into::into)
     }
 
+    /// Records every call into the contracts with the given recorder
+    pub fn with_coverage(mut self, recorder: CoverageRecorder) -> Self {
+        self.coverage = Some(recorder);
+        self
+    }
+
+    pub fn set_coverage(&mut self, recorder: CoverageRecorder) {
+        self.coverage = Some(recorder);
+    }
+
+    /// Fails the selected contract queries, and bank sends and contract executions dispatched
+    /// by contracts, according to the rules of the given injector
+    pub fn with_faults(mut self, faults: FaultInjector) -> Self {
+        self.faults = Some(faults);
+        self
+    }
+
+    pub fn set_faults(&mut self, faults: FaultInjector) {
+        self.faults = Some(faults);
+    }
+
+    fn check_fault(&self, target: FaultTarget, subject: &str) -> AnyResult<()> {
+        match self.faults.as_ref().and_then(|f| f.check(target, subject)) {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks if the submessage dispatched by a contract should be failed
+    fn check_msg_fault(&self, msg: &CosmosMsg<ExecC>) -> AnyResult<()> {
+        match msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, .. }) => {
+                self.check_fault(FaultTarget::BankSend, to_address)
+            }
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) => {
+                self.check_fault(FaultTarget::WasmExecute, contract_addr)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Name of the message variant to be recorded, or `None` if coverage is not recorded
+    fn coverage_variant(&self, msg: &[u8]) -> Option<String> {
+        self.coverage.as_ref().map(|_| raw_msg_variant(msg))
+    }
+
+    fn record_coverage<T>(
+        &self,
+        storage: &dyn Storage,
+        address: &Addr,
+        entry_point: EntryPoint,
+        variant: Option<String>,
+        res: &AnyResult<T>,
+    ) {
+        if let (Some(recorder), Some(variant)) = (&self.coverage, variant) {
+            if let Ok(contract) = self.load_contract(storage, address) {
+                recorder.record(contract.code_id as u64, entry_point, variant, res);
+            }
+        }
+    }
+
     pub fn dump_wasm_raw(&self, storage: &dyn Storage, address: &Addr) -> Vec<Record> {
         let storage = self.contract_storage_readonly(storage, address);
         storage.range(None, None, Order::Ascending).collect()
@@ -285,14 +356,17 @@ where
         block: &BlockInfo,
         msg: Vec<u8>,
     ) -> AnyResult<Binary> {
-        self.with_storage_readonly(
+        let variant = self.coverage_variant(&msg);
+        let res = self.with_storage_readonly(
             api,
             storage,
             querier,
             block,
-            address,
+            address.clone(),
             |handler, deps, env| handler.query(deps, env, msg),
-        )
+        );
+        self.record_coverage(storage, &address, EntryPoint::Query, variant, &res);
+        res
     }
 
     pub fn query_raw(&self, address: Addr, storage: &dyn Storage, key: &[u8]) -> Binary {
@@ -501,8 +575,10 @@ where
         } = msg;
 
         // execute in cache
-        let res = transactional(storage, |write_cache, _| {
-            router.execute(api, write_cache, block, contract.clone(), msg)
+        let res = self.check_msg_fault(&msg).and_then(|_| {
+            transactional(storage, |write_cache, _| {
+                router.execute(api, write_cache, block, contract.clone(), msg)
+            })
         });
 
         // call reply if meaningful
@@ -673,14 +749,17 @@ where
         info: MessageInfo,
         msg: Vec<u8>,
     ) -> AnyResult<Response<ExecC>> {
-        Self::verify_response(self.with_storage(
+        let variant = self.coverage_variant(&msg);
+        let res = self.with_storage(
             api,
             storage,
             router,
             block,
-            address,
+            address.clone(),
             |contract, deps, env| contract.execute(deps, env, info, msg),
-        )?)
+        );
+        self.record_coverage(storage, &address, EntryPoint::Execute, variant, &res);
+        Self::verify_response(res?)
     }
 
     pub fn call_instantiate(
@@ -693,14 +772,17 @@ where
         info: MessageInfo,
         msg: Vec<u8>,
     ) -> AnyResult<Response<ExecC>> {
-        Self::verify_response(self.with_storage(
+        let variant = self.coverage_variant(&msg);
+        let res = self.with_storage(
             api,
             storage,
             router,
             block,
-            address,
+            address.clone(),
             |contract, deps, env| contract.instantiate(deps, env, info, msg),
-        )?)
+        );
+        self.record_coverage(storage, &address, EntryPoint::Instantiate, variant, &res);
+        Self::verify_response(res?)
     }
 
     pub fn call_reply(
@@ -712,14 +794,17 @@ where
         block: &BlockInfo,
         reply: Reply,
     ) -> AnyResult<Response<ExecC>> {
-        Self::verify_response(self.with_storage(
+        let variant = self.coverage.as_ref().map(|_| reply.id.to_string());
+        let res = self.with_storage(
             api,
             storage,
             router,
             block,
-            address,
+            address.clone(),
             |contract, deps, env| contract.reply(deps, env, reply),
-        )?)
+        );
+        self.record_coverage(storage, &address, EntryPoint::Reply, variant, &res);
+        Self::verify_response(res?)
     }
 
     pub fn call_sudo(
@@ -731,14 +816,17 @@ where
         block: &BlockInfo,
         msg: Vec<u8>,
     ) -> AnyResult<Response<ExecC>> {
-        Self::verify_response(self.with_storage(
+        let variant = self.coverage_variant(&msg);
+        let res = self.with_storage(
             api,
             storage,
             router,
             block,
-            address,
+            address.clone(),
             |contract, deps, env| contract.sudo(deps, env, msg),
-        )?)
+        );
+        self.record_coverage(storage, &address, EntryPoint::Sudo, variant, &res);
+        Self::verify_response(res?)
     }
 
     pub fn call_migrate(
@@ -750,14 +838,17 @@ where
         block: &BlockInfo,
         msg: Vec<u8>,
     ) -> AnyResult<Response<ExecC>> {
-        Self::verify_response(self.with_storage(
+        let variant = self.coverage_variant(&msg);
+        let res = self.with_storage(
             api,
             storage,
             router,
             block,
-            address,
+            address.clone(),
             |contract, deps, env| contract.migrate(deps, env, msg),
-        )?)
+        );
+        self.record_coverage(storage, &address, EntryPoint::Migrate, variant, &res);
+        Self::verify_response(res?)
     }
 
     fn get_env<T: Into<Addr>>(&self, address: T, block: &BlockInfo) -> Env {
@@ -1,37 +1,366 @@
 use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
 use syn::{
-    Ident,
-    __private::{quote::quote, Span},
-    parse_macro_input, ItemStruct,
+    parse::{Parse, ParseStream},
+    parse_macro_input, parse_quote,
+    spanned::Spanned,
+    Data, DeriveInput, Error, Expr, Fields, Ident, ItemStruct, LitStr, Token, Type,
 };
 
+/// Implements `IndexList` for a struct of indexes, given the type of the indexed values:
+///
+/// ```ignore
+/// #[index_list(crate::state::Token)]
+/// struct TokenIndexes<'a> {
+///     owner: MultiIndex<'a, Addr, Token, String>,
+/// }
+/// ```
+///
+/// The value type can be any type path, and the struct can be generic over lifetimes and types.
+///
+/// If every field has an `#[index(...)]` attribute, it also generates `new(pk_namespace)`,
+/// building the indexes, and `indexed_map(pk_namespace)`, building the whole `IndexedMap`:
+///
+/// ```ignore
+/// #[index_list(Token)]
+/// struct TokenIndexes<'a> {
+///     #[index(multi, fn = |t: &Token| t.owner.clone(), namespace = "tokens__owner")]
+///     owner: MultiIndex<'a, Addr, Token, String>,
+///     #[index(unique, fn = token_name, namespace = "tokens__name")]
+///     name: UniqueIndex<'a, String, Token, String>,
+/// }
+///
+/// fn tokens<'a>() -> IndexedMap<'a, &'a str, Token, TokenIndexes<'a>> {
+///     TokenIndexes::indexed_map("tokens")
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn index_list(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(item as ItemStruct);
-
-    let ty = Ident::new(&attr.to_string(), Span::call_site());
-    let struct_ty = input.ident.clone();
-
-    let names = input
-        .fields
-        .clone()
-        .into_iter()
-        .map(|e| {
-            let name = e.ident.unwrap();
-            quote! { &self.#name }
+    let mut input = parse_macro_input!(item as ItemStruct);
+    if attr.is_empty() {
+        return Error::new(
+            Span::call_site(),
+            "expected the type of the indexed values, like #[index_list(Token)]",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let ty = parse_macro_input!(attr as Type);
+
+    TokenStream::from(index_list_impl(&mut input, &ty).unwrap_or_else(|err| err.to_compile_error()))
+}
+
+/// `#[index(kind, fn = ..., namespace = "...")]` on a field of an `index_list` struct, where
+/// the kind is `multi`, `multi_value` or `unique`
+struct IndexAttr {
+    kind: Ident,
+    idx_fn: Expr,
+    namespace: LitStr,
+}
+
+impl Parse for IndexAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let kind: Ident = input.parse()?;
+        if kind != "multi" && kind != "multi_value" && kind != "unique" {
+            return Err(Error::new_spanned(
+                kind,
+                "expected the kind of index: `multi`, `multi_value` or `unique`",
+            ));
+        }
+        let mut idx_fn = None;
+        let mut namespace = None;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.peek(Token![fn]) {
+                input.parse::<Token![fn]>()?;
+                input.parse::<Token![=]>()?;
+                idx_fn = Some(input.parse()?);
+            } else {
+                let name: Ident = input.parse()?;
+                if name != "namespace" {
+                    return Err(Error::new_spanned(
+                        name,
+                        "unknown index option, expected `fn` or `namespace`",
+                    ));
+                }
+                input.parse::<Token![=]>()?;
+                namespace = Some(input.parse()?);
+            }
+        }
+        let span = kind.span();
+        Ok(IndexAttr {
+            kind,
+            idx_fn: idx_fn
+                .ok_or_else(|| Error::new(span, "missing the index function: `fn = ...`"))?,
+            namespace: namespace.ok_or_else(|| {
+                Error::new(span, "missing the index namespace: `namespace = \"...\"`")
+            })?,
         })
-        .collect::<Vec<_>>();
+    }
+}
+
+fn index_list_impl(input: &mut ItemStruct, ty: &Type) -> syn::Result<TokenStream2> {
+    let fields = match &mut input.fields {
+        Fields::Named(fields) => &mut fields.named,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "index_list can only be used on structs with named fields",
+            ))
+        }
+    };
+
+    // take the `#[index]` attributes out of the fields, as they are not real attributes
+    let mut names = vec![];
+    let mut types = vec![];
+    let mut attrs = vec![];
+    for field in fields.iter_mut() {
+        let (index, others) = field
+            .attrs
+            .drain(..)
+            .partition::<Vec<_>, _>(|attr| attr.path.is_ident("index"));
+        field.attrs = others;
+        attrs.push(match index.as_slice() {
+            [] => None,
+            [attr] => Some(attr.parse_args::<IndexAttr>()?),
+            [_, attr, ..] => return Err(Error::new_spanned(attr, "duplicated index attribute")),
+        });
+        names.push(field.ident.clone().unwrap());
+        types.push(field.ty.clone());
+    }
 
-    let expanded = quote! {
+    let struct_ty = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    // every field must be an index of the values, which also covers generic value types
+    let mut predicates = where_clause
+        .map(|w| w.predicates.iter().cloned().collect())
+        .unwrap_or_else(Vec::new);
+    predicates.extend(
+        types
+            .iter()
+            .map(|field_ty| parse_quote! { #field_ty: cw_storage_plus::Index<#ty> }),
+    );
+
+    let constructor =
+        match attrs.iter().position(Option::is_none) {
+            // no field has the attribute: the indexes are built by hand
+            Some(_) if attrs.iter().all(Option::is_none) => quote! {},
+            Some(missing) => return Err(Error::new_spanned(
+                &names[missing],
+                "missing #[index(...)] attribute, needed on all fields to generate the constructor",
+            )),
+            None => {
+                let lifetime = match input.generics.lifetimes().next() {
+                    Some(def) => def.lifetime.clone(),
+                    None => return Err(Error::new_spanned(
+                        &input.ident,
+                        "the indexes struct needs a lifetime parameter, like `struct Indexes<'a>`",
+                    )),
+                };
+                let indexes = attrs.iter().flatten().zip(&types).map(|(attr, field_ty)| {
+                    let IndexAttr {
+                        kind,
+                        idx_fn,
+                        namespace,
+                    } = attr;
+                    // errors about the field type, like not being an index, point at the field
+                    if kind == "unique" {
+                        quote_spanned! { field_ty.span()=> <#field_ty>::new(#idx_fn, #namespace) }
+                    } else {
+                        quote_spanned! { field_ty.span()=>
+                            <#field_ty>::new(#idx_fn, pk_namespace, #namespace)
+                        }
+                    }
+                });
+                quote! {
+                    impl #impl_generics #struct_ty #ty_generics where #(#predicates),* {
+                        /// Builds the indexes of the values stored under `pk_namespace`
+                        pub fn new(pk_namespace: &#lifetime str) -> Self {
+                            #struct_ty {
+                                #(#names: #indexes),*
+                            }
+                        }
+
+                        /// Builds the `IndexedMap` storing the values under `pk_namespace`
+                        pub fn indexed_map<K: cw_storage_plus::PrimaryKey<#lifetime>>(
+                            pk_namespace: &#lifetime str,
+                        ) -> cw_storage_plus::IndexedMap<#lifetime, K, #ty, Self> {
+                            cw_storage_plus::IndexedMap::new(pk_namespace, Self::new(pk_namespace))
+                        }
+                    }
+                }
+            }
+        };
+
+    Ok(quote! {
         #input
 
-        impl cw_storage_plus::IndexList<#ty> for #struct_ty<'_> {
+        impl #impl_generics cw_storage_plus::IndexList<#ty> for #struct_ty #ty_generics where #(#predicates),* {
             fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn cw_storage_plus::Index<#ty>> + '_> {
-                let v: Vec<&dyn cw_storage_plus::Index<#ty>> = vec![#(#names),*];
+                let v: Vec<&dyn cw_storage_plus::Index<#ty>> = vec![#(&self.#names),*];
                 Box::new(v.into_iter())
             }
         }
+
+        #constructor
+    })
+}
+
+/// Derives `PrimaryKey`, `Prefixer` and `KeyDeserialize` for structs with named fields, and
+/// for fieldless enums.
+///
+/// Struct keys are encoded like the tuple of their fields, in declaration order, so
+/// `Prefix`, `SubPrefix`, `Suffix` and `SuperSuffix` are the ones of that tuple. Every field
+/// but the last one has to implement `Prefixer`, and fields have to deserialize to themselves
+/// (`Addr` and `String` rather than `&Addr` and `&str`).
+///
+/// Enum keys are encoded as the `u8` value of their variant.
+///
+/// The generated code refers to `cw_storage_plus` and `cosmwasm_std` by name, so both crates must
+/// be direct dependencies of the crate using the derive, and must not be renamed.
+#[proc_macro_derive(PrimaryKey)]
+pub fn derive_primary_key(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let expanded = match &input.data {
+        _ if !input.generics.params.is_empty() => Err(Error::new_spanned(
+            &input.generics,
+            "PrimaryKey can only be derived for types without generics",
+        )),
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) if !fields.named.is_empty() => {
+                let names = fields.named.iter().map(|f| f.ident.clone().unwrap());
+                let types = fields.named.iter().map(|f| f.ty.clone());
+                Ok(struct_key(&input.ident, names.collect(), types.collect()))
+            }
+            _ => Err(Error::new_spanned(
+                &input.ident,
+                "PrimaryKey can only be derived for structs with named fields",
+            )),
+        },
+        Data::Enum(data) => match data.variants.iter().find(|v| !v.fields.is_empty()) {
+            None => Ok(enum_key(
+                &input.ident,
+                data.variants.iter().map(|v| v.ident.clone()).collect(),
+            )),
+            Some(variant) => Err(Error::new_spanned(
+                variant,
+                "PrimaryKey can only be derived for enums without fields",
+            )),
+        },
+        Data::Union(_) => Err(Error::new_spanned(
+            &input.ident,
+            "PrimaryKey cannot be derived for unions",
+        )),
+    };
+
+    TokenStream::from(expanded.unwrap_or_else(|err| err.to_compile_error()))
+}
+
+/// Type of a key made of the given fields: the field itself or a tuple of them
+fn key_type(types: &[Type]) -> TokenStream2 {
+    match types {
+        [] => quote! { () },
+        [ty] => quote! { #ty },
+        types => quote! { (#(#types),*) },
+    }
+}
+
+fn struct_key(ident: &Ident, names: Vec<Ident>, types: Vec<Type>) -> TokenStream2 {
+    let n = types.len();
+    let (prefix, sub_prefix, suffix, super_suffix) = if n == 1 {
+        let unit = quote! { () };
+        (unit.clone(), unit, quote! { Self }, quote! { Self })
+    } else {
+        let super_suffix = if n == 2 {
+            quote! { Self }
+        } else {
+            key_type(&types[n - 2..])
+        };
+        (
+            key_type(&types[..n - 1]),
+            key_type(&types[..n - 2]),
+            key_type(&types[n - 1..]),
+            super_suffix,
+        )
     };
+    let tuple = key_type(&types);
+    let pattern = match names.as_slice() {
+        [name] => quote! { #name },
+        names => quote! { (#(#names),*) },
+    };
+
+    quote! {
+        impl<'a> cw_storage_plus::PrimaryKey<'a> for #ident {
+            type Prefix = #prefix;
+            type SubPrefix = #sub_prefix;
+            type Suffix = #suffix;
+            type SuperSuffix = #super_suffix;
+
+            fn key(&self) -> Vec<cw_storage_plus::Key> {
+                let mut keys = vec![];
+                #(keys.extend(cw_storage_plus::PrimaryKey::key(&self.#names));)*
+                keys
+            }
+        }
+
+        impl<'a> cw_storage_plus::Prefixer<'a> for #ident {
+            fn prefix(&self) -> Vec<cw_storage_plus::Key> {
+                let mut keys = vec![];
+                #(keys.extend(cw_storage_plus::Prefixer::prefix(&self.#names));)*
+                keys
+            }
+        }
+
+        impl cw_storage_plus::KeyDeserialize for #ident {
+            type Output = Self;
+
+            fn from_vec(value: Vec<u8>) -> cosmwasm_std::StdResult<Self::Output> {
+                // same encoding as the tuple of the fields
+                let #pattern = <#tuple as cw_storage_plus::KeyDeserialize>::from_vec(value)?;
+                Ok(Self { #(#names),* })
+            }
+        }
+    }
+}
+
+fn enum_key(ident: &Ident, variants: Vec<Ident>) -> TokenStream2 {
+    let name = ident.to_string();
+
+    quote! {
+        impl<'a> cw_storage_plus::PrimaryKey<'a> for #ident {
+            type Prefix = ();
+            type SubPrefix = ();
+            type Suffix = Self;
+            type SuperSuffix = Self;
+
+            fn key(&self) -> Vec<cw_storage_plus::Key> {
+                cw_storage_plus::Prefixer::prefix(self)
+            }
+        }
 
-    TokenStream::from(expanded)
+        impl<'a> cw_storage_plus::Prefixer<'a> for #ident {
+            fn prefix(&self) -> Vec<cw_storage_plus::Key> {
+                let value = match self {
+                    #(#ident::#variants => #ident::#variants as u8,)*
+                };
+                vec![cw_storage_plus::Key::Val8([value])]
+            }
+        }
+
+        impl cw_storage_plus::KeyDeserialize for #ident {
+            type Output = Self;
+
+            fn from_vec(value: Vec<u8>) -> cosmwasm_std::StdResult<Self::Output> {
+                match <u8 as cw_storage_plus::KeyDeserialize>::from_vec(value)? {
+                    #(value if value == #ident::#variants as u8 => Ok(#ident::#variants),)*
+                    value => Err(cosmwasm_std::StdError::parse_err(
+                        #name,
+                        format!("unknown variant {}", value),
+                    )),
+                }
+            }
+        }
+    }
 }
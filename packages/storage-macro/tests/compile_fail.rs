@@ -0,0 +1,5 @@
+#[test]
+fn index_list_errors() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -73,4 +73,99 @@ mod test {
             }
         );
     }
+
+    mod state {
+        use cosmwasm_std::Addr;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+        pub struct Token<T> {
+            pub owner: Addr,
+            pub name: String,
+            pub extension: T,
+        }
+
+        pub fn token_name<T>(token: &Token<T>) -> String {
+            token.name.clone()
+        }
+    }
+
+    #[test]
+    fn index_list_accepts_paths_and_generics() {
+        use serde::de::DeserializeOwned;
+
+        #[index_list(state::Token<T>)]
+        struct TokenIndexes<'x, T>
+        where
+            T: Serialize + DeserializeOwned + Clone,
+        {
+            owner: MultiIndex<'x, Addr, state::Token<T>, String>,
+            name: UniqueIndex<'x, String, state::Token<T>, String>,
+        }
+
+        let mut storage = MockStorage::new();
+        let tokens: IndexedMap<&str, state::Token<u64>, TokenIndexes<u64>> = IndexedMap::new(
+            "tokens",
+            TokenIndexes {
+                owner: MultiIndex::new(|t| t.owner.clone(), "tokens", "tokens__owner"),
+                name: UniqueIndex::new(state::token_name, "tokens__name"),
+            },
+        );
+
+        let token = state::Token {
+            owner: Addr::unchecked("owner"),
+            name: "first".to_string(),
+            extension: 42u64,
+        };
+        tokens.save(&mut storage, "1", &token).unwrap();
+        let (_, loaded) = tokens
+            .idx
+            .name
+            .item(&storage, "first".to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded, token);
+    }
+
+    #[test]
+    fn index_list_generates_constructor() {
+        use cosmwasm_std::Order;
+        use state::{token_name, Token};
+
+        #[index_list(Token<bool>)]
+        struct TokenIndexes<'a> {
+            #[index(multi, fn = |t: &Token<bool>| t.owner.clone(), namespace = "tokens__owner")]
+            owner: MultiIndex<'a, Addr, Token<bool>, String>,
+            #[index(unique, fn = token_name, namespace = "tokens__name")]
+            name: UniqueIndex<'a, String, Token<bool>, String>,
+        }
+
+        let mut storage = MockStorage::new();
+        let tokens = TokenIndexes::indexed_map("tokens");
+        for (id, name) in [("1", "first"), ("2", "second")] {
+            let token = Token {
+                owner: Addr::unchecked("owner"),
+                name: name.to_string(),
+                extension: true,
+            };
+            tokens.save(&mut storage, id, &token).unwrap();
+        }
+
+        let owned: Vec<_> = tokens
+            .idx
+            .owner
+            .prefix(Addr::unchecked("owner"))
+            .keys(&storage, None, None, Order::Ascending)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(owned, vec!["1".to_string(), "2".to_string()]);
+
+        // the name must be unique
+        let token = Token {
+            owner: Addr::unchecked("other"),
+            name: "first".to_string(),
+            extension: false,
+        };
+        tokens.save(&mut storage, "3", &token).unwrap_err();
+    }
 }
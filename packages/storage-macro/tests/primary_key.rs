@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod test {
+    use cosmwasm_std::{testing::MockStorage, Addr, Order, StdResult};
+    use cw_storage_plus::{KeyDeserialize, Map, PrimaryKey};
+
+    #[derive(cw_storage_macro::PrimaryKey, Clone, Debug, PartialEq)]
+    struct Balance {
+        owner: Addr,
+        token_id: String,
+    }
+
+    #[derive(cw_storage_macro::PrimaryKey, Clone, Debug, PartialEq)]
+    struct Vote {
+        proposal: u64,
+        voter: Addr,
+        round: u32,
+    }
+
+    #[derive(cw_storage_macro::PrimaryKey, Clone, Debug, PartialEq)]
+    struct Id {
+        id: u64,
+    }
+
+    #[derive(cw_storage_macro::PrimaryKey, Clone, Copy, Debug, PartialEq)]
+    enum Status {
+        Open,
+        Passed,
+        Rejected = 7,
+    }
+
+    fn balance() -> Balance {
+        Balance {
+            owner: Addr::unchecked("owner"),
+            token_id: "token".to_string(),
+        }
+    }
+
+    #[test]
+    fn struct_key_matches_tuple_encoding() {
+        let key = balance();
+        let tuple = (Addr::unchecked("owner"), "token".to_string());
+        assert_eq!(key.joined_key(), tuple.joined_key());
+        assert_eq!(Balance::from_vec(key.joined_key()).unwrap(), key);
+
+        let key = Vote {
+            proposal: 3,
+            voter: Addr::unchecked("voter"),
+            round: 2,
+        };
+        let tuple = (3u64, Addr::unchecked("voter"), 2u32);
+        assert_eq!(key.joined_key(), tuple.joined_key());
+        assert_eq!(Vote::from_slice(&tuple.joined_key()).unwrap(), key);
+
+        let key = Id { id: 42 };
+        assert_eq!(key.joined_key(), 42u64.joined_key());
+        assert_eq!(Id::from_vec(key.joined_key()).unwrap(), key);
+    }
+
+    #[test]
+    fn enum_key_matches_u8_encoding() {
+        assert_eq!(Status::Open.joined_key(), 0u8.joined_key());
+        assert_eq!(Status::Passed.joined_key(), 1u8.joined_key());
+        assert_eq!(Status::Rejected.joined_key(), 7u8.joined_key());
+
+        for status in [Status::Open, Status::Passed, Status::Rejected] {
+            assert_eq!(Status::from_vec(status.joined_key()).unwrap(), status);
+        }
+        assert!(Status::from_vec(2u8.joined_key()).is_err());
+    }
+
+    #[test]
+    fn struct_keys_in_map() {
+        const BALANCES: Map<Balance, u128> = Map::new("balances");
+        let mut store = MockStorage::new();
+
+        BALANCES.save(&mut store, balance(), &100).unwrap();
+        let other = Balance {
+            token_id: "other".to_string(),
+            ..balance()
+        };
+        BALANCES.save(&mut store, other.clone(), &5).unwrap();
+        let another_owner = Balance {
+            owner: Addr::unchecked("another"),
+            ..balance()
+        };
+        BALANCES
+            .save(&mut store, another_owner.clone(), &1)
+            .unwrap();
+
+        assert_eq!(BALANCES.load(&store, balance()).unwrap(), 100);
+
+        // the Prefix is the owner, the Suffix the token id
+        let tokens: StdResult<Vec<_>> = BALANCES
+            .prefix(Addr::unchecked("owner"))
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(
+            tokens.unwrap(),
+            vec![("other".to_string(), 5), ("token".to_string(), 100)]
+        );
+
+        // like with tuples, the owners are ordered by length first
+        let all: StdResult<Vec<_>> = BALANCES
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![(other, 5), (balance(), 100), (another_owner, 1)]
+        );
+
+        // a map keyed by the equivalent tuple reads the same data
+        const TUPLES: Map<(Addr, String), u128> = Map::new("balances");
+        assert_eq!(
+            TUPLES
+                .load(&store, (Addr::unchecked("owner"), "token".to_string()))
+                .unwrap(),
+            100
+        );
+    }
+
+    #[test]
+    fn struct_and_enum_keys_as_prefixes() {
+        const VOTES: Map<(Vote, Status), bool> = Map::new("votes");
+        const BY_STATUS: Map<(Status, u64), bool> = Map::new("by_status");
+        let mut store = MockStorage::new();
+
+        let vote = Vote {
+            proposal: 1,
+            voter: Addr::unchecked("voter"),
+            round: 0,
+        };
+        VOTES
+            .save(&mut store, (vote.clone(), Status::Passed), &true)
+            .unwrap();
+        let statuses: StdResult<Vec<_>> = VOTES
+            .prefix(vote)
+            .keys(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(statuses.unwrap(), vec![Status::Passed]);
+
+        BY_STATUS
+            .save(&mut store, (Status::Rejected, 4), &true)
+            .unwrap();
+        BY_STATUS
+            .save(&mut store, (Status::Open, 5), &true)
+            .unwrap();
+        let rejected: StdResult<Vec<_>> = BY_STATUS
+            .prefix(Status::Rejected)
+            .keys(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(rejected.unwrap(), vec![4]);
+    }
+}
@@ -0,0 +1,17 @@
+use cw_storage_macro::index_list;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Token {
+    owner: String,
+}
+
+struct NotAnIndex;
+
+#[index_list(Token)]
+struct TokenIndexes<'a> {
+    #[index(multi, fn = |t: &Token| t.owner.clone(), namespace = "tokens__owner")]
+    owner: &'a NotAnIndex,
+}
+
+fn main() {}
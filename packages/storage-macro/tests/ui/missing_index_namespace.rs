@@ -0,0 +1,15 @@
+use cw_storage_macro::index_list;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Token {
+    owner: String,
+}
+
+#[index_list(Token)]
+struct TokenIndexes<'a> {
+    #[index(multi, fn = |t: &Token| t.owner.clone())]
+    owner: cw_storage_plus::MultiIndex<'a, String, Token, String>,
+}
+
+fn main() {}
@@ -1,10 +1,16 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::measurement::WallTime;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkGroup, Criterion};
 
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::mem;
 use std::time::Duration;
 
-use cw_storage_plus::IntKey;
+use std::cell::Cell;
+
+use cosmwasm_std::testing::MockStorage;
+use cosmwasm_std::{coins, to_binary, BankMsg, CosmosMsg, Order, Record, Storage, WasmMsg};
+use cw_storage_plus::{CachedStorage, Codec, IntKey, Item, Json, Map};
 
 fn bench_signed_int_key(c: &mut Criterion) {
     let mut group = c.benchmark_group("Signed int keys");
@@ -140,6 +146,189 @@ fn bench_unsigned_int_key(c: &mut Criterion) {
     group.finish();
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Proposal {
+    title: String,
+    description: String,
+    msgs: Vec<CosmosMsg>,
+}
+
+fn proposal() -> Proposal {
+    let msgs = (0..10)
+        .flat_map(|i| {
+            let send = BankMsg::Send {
+                to_address: format!("recipient{}", i),
+                amount: coins(1000 + i, "ucosm"),
+            };
+            let execute = WasmMsg::Execute {
+                contract_addr: format!("contract{}", i),
+                msg: to_binary(&format!("transfer {}", i)).unwrap(),
+                funds: vec![],
+            };
+            vec![send.into(), execute.into()]
+        })
+        .collect();
+    Proposal {
+        title: "Payouts".to_string(),
+        description: "Pays the contributors of the last month".to_string(),
+        msgs,
+    }
+}
+
+fn bench_codec<C: Codec>(group: &mut BenchmarkGroup<WallTime>, name: &str) {
+    let proposals: Map<u64, Proposal, C> = Map::with_codec("proposals");
+    let proposal = proposal();
+
+    let mut store = MockStorage::new();
+    proposals.save(&mut store, 1, &proposal).unwrap();
+    assert_eq!(proposals.load(&store, 1).unwrap(), proposal);
+    println!(
+        "{}: {} bytes",
+        name,
+        C::encode(&proposal).map(|data| data.len()).unwrap()
+    );
+
+    group.bench_function(format!("{} save", name), |b| {
+        let mut store = MockStorage::new();
+        b.iter(|| proposals.save(&mut store, 1, black_box(&proposal)).unwrap());
+    });
+
+    group.bench_function(format!("{} load", name), |b| {
+        b.iter(|| black_box(proposals.load(&store, 1).unwrap()));
+    });
+}
+
+fn bench_value_codecs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Value codecs");
+
+    bench_codec::<Json>(&mut group, "json");
+    // run with `cargo bench --features bincode`
+    #[cfg(feature = "bincode")]
+    bench_codec::<cw_storage_plus::Bincode>(&mut group, "bincode");
+
+    group.finish();
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Config {
+    threshold: u64,
+    max_voting_period: u64,
+}
+
+const CONFIG: Item<Config> = Item::new("config");
+const PROPOSALS: Map<u64, Proposal> = Map::new("proposals");
+const BALLOTS: Map<(u64, &str), u64> = Map::new("votes");
+const VOTERS: Map<&str, u64> = Map::new("voters");
+
+/// Counts the reads reaching the wrapped storage
+struct CountingStorage<'a> {
+    storage: &'a mut dyn Storage,
+    reads: Cell<u64>,
+}
+
+impl<'a> Storage for CountingStorage<'a> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.reads.set(self.reads.get() + 1);
+        self.storage.get(key)
+    }
+
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'b> {
+        self.storage.range(start, end, order)
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.storage.set(key, value)
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.storage.remove(key)
+    }
+}
+
+fn voting_state() -> MockStorage {
+    let mut store = MockStorage::new();
+    let config = Config {
+        threshold: 3,
+        max_voting_period: 1000,
+    };
+    CONFIG.save(&mut store, &config).unwrap();
+    PROPOSALS.save(&mut store, 1, &proposal()).unwrap();
+    for i in 0..10 {
+        VOTERS.save(&mut store, &format!("voter{}", i), &1).unwrap();
+    }
+    store
+}
+
+/// The accesses of a multisig vote: the config and the proposal are loaded by several helpers
+fn vote(store: &mut dyn Storage, voter: &str) {
+    let config = CONFIG.load(store).unwrap();
+    let weight = VOTERS.load(store, voter).unwrap();
+    let proposal = PROPOSALS.load(store, 1).unwrap();
+    if BALLOTS.may_load(store, (1, voter)).unwrap().is_none() {
+        BALLOTS.save(store, (1, voter), &weight).unwrap();
+    }
+    // status checks
+    let _ = CONFIG.load(store).unwrap().threshold <= config.threshold;
+    let tally: u64 = (0..10)
+        .map(|i| {
+            BALLOTS
+                .may_load(store, (1, &format!("voter{}", i)))
+                .unwrap()
+                .unwrap_or_default()
+        })
+        .sum();
+    PROPOSALS.save(store, 1, &proposal).unwrap();
+    // the response
+    black_box((PROPOSALS.load(store, 1).unwrap(), tally));
+}
+
+fn bench_cached_storage(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Cached storage");
+
+    let mut store = voting_state();
+    let mut counting = CountingStorage {
+        storage: &mut store,
+        reads: Cell::new(0),
+    };
+    vote(&mut counting, "voter1");
+    let direct_reads = counting.reads.get();
+    let mut cache = CachedStorage::new(&mut counting);
+    vote(&mut cache, "voter2");
+    cache.flush();
+    let stats = cache.stats();
+    drop(cache);
+    println!(
+        "reads per vote: {} direct, {} cached ({} hits, {} misses, {} writes flushed)",
+        direct_reads,
+        counting.reads.get() - direct_reads,
+        stats.hits,
+        stats.misses,
+        stats.flushed
+    );
+
+    // MockStorage reads are cheap, unlike the reads of a contract, which go through the host
+    group.bench_function("direct vote", |b| {
+        let mut store = voting_state();
+        b.iter(|| vote(&mut store, "voter1"));
+    });
+
+    group.bench_function("cached vote", |b| {
+        let mut store = voting_state();
+        b.iter(|| {
+            let mut cache = CachedStorage::new(&mut store);
+            vote(&mut cache, "voter1");
+            cache.flush();
+        });
+    });
+
+    group.finish();
+}
+
 fn make_config() -> Criterion {
     Criterion::default()
         .without_plots()
@@ -158,4 +347,19 @@ criterion_group!(
     config = make_config();
     targets = bench_unsigned_int_key
 );
-criterion_main!(signed_int_key, unsigned_int_key);
+criterion_group!(
+    name = value_codecs;
+    config = make_config();
+    targets = bench_value_codecs
+);
+criterion_group!(
+    name = cached_storage;
+    config = make_config();
+    targets = bench_cached_storage
+);
+criterion_main!(
+    signed_int_key,
+    unsigned_int_key,
+    value_codecs,
+    cached_storage
+);
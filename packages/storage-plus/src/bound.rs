@@ -1,6 +1,6 @@
 #![cfg(feature = "iterator")]
 
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Decimal, Timestamp, Uint128, Uint256, Uint64};
 use std::marker::PhantomData;
 
 use crate::de::KeyDeserialize;
@@ -123,6 +123,32 @@ impl<
     }
 }
 
+macro_rules! tuple_bound {
+    ($A:ident, $($T:ident),+; $Y:ident, $Z:ident) => {
+        impl<
+                'a,
+                $A: PrimaryKey<'a> + Prefixer<'a> + Clone,
+                $($T: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize + Clone,)+
+                $Y: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize + Clone,
+                $Z: PrimaryKey<'a> + KeyDeserialize + Clone,
+            > Bounder<'a> for ($A, $($T,)+ $Y, $Z)
+        {
+            fn inclusive_bound(self) -> Option<Bound<'a, Self>> {
+                Some(Bound::inclusive(self))
+            }
+            fn exclusive_bound(self) -> Option<Bound<'a, Self>> {
+                Some(Bound::exclusive(self))
+            }
+        }
+    };
+}
+
+tuple_bound!(T, U; V, W);
+tuple_bound!(T, U, V; W, X);
+tuple_bound!(T, U, V, W; X, Y);
+tuple_bound!(T, U, V, W, X; Y, Z);
+tuple_bound!(T, U, V, W, X, Y; Z, A);
+
 impl<'a> Bounder<'a> for &'a str {
     fn inclusive_bound(self) -> Option<Bound<'a, Self>> {
         Some(Bound::inclusive(self))
@@ -182,3 +208,4 @@ macro_rules! integer_bound {
 }
 
 integer_bound!(for i8, u8, i16, u16, i32, u32, i64, u64);
+integer_bound!(for Uint64, Uint128, Uint256, Decimal, Timestamp, bool, char);
@@ -0,0 +1,418 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+#[cfg(feature = "iterator")]
+use std::cmp::Ordering;
+#[cfg(feature = "iterator")]
+use std::iter::{self, Peekable};
+#[cfg(feature = "iterator")]
+use std::ops::{Bound, RangeBounds};
+
+use cosmwasm_std::Storage;
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, Record};
+
+/// Hit and miss counters of a `CachedStorage`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Reads served from the cache, or from the pending writes
+    pub hits: u64,
+    /// Reads forwarded to the wrapped storage
+    pub misses: u64,
+    /// Writes (sets and removes) buffered since the creation
+    pub writes: u64,
+    /// Writes applied to the wrapped storage. Overwritten pending writes are only flushed once.
+    pub flushed: u64,
+}
+
+/// A pending write: its sequence number, to flush in order, and the new value (`None` on remove)
+type Pending = (u64, Option<Vec<u8>>);
+
+/// Storage wrapper memoizing the reads, and buffering the writes until `flush`.
+///
+/// Repeated `Item::load` / `Map::load` of the same entries during an execution only read the
+/// wrapped storage once. Note the cache holds the raw bytes, so the values are still deserialized
+/// on every load.
+///
+/// Reads and ranges see the pending writes. They are flushed in the order they were made, with
+/// only the last write of every key applied. Writes still pending when the cache is dropped are
+/// discarded, so returning early with an error doesn't write a partial state.
+///
+/// ## Example:
+///
+/// ```rust
+/// use cosmwasm_std::testing::MockStorage;
+/// use cosmwasm_std::StdResult;
+/// use cw_storage_plus::{CachedStorage, Item};
+///
+/// const COUNTER: Item<u64> = Item::new("counter");
+///
+/// let mut store = MockStorage::new();
+/// COUNTER.save(&mut store, &0).unwrap();
+///
+/// let mut cache = CachedStorage::new(&mut store);
+/// for _ in 0..3 {
+///     COUNTER.update(&mut cache, |c| -> StdResult<_> { Ok(c + 1) }).unwrap();
+/// }
+/// // only the first load reads the wrapped storage
+/// assert_eq!(cache.stats().misses, 1);
+/// assert_eq!(cache.stats().hits, 2);
+///
+/// // nothing is written until flushed
+/// cache.flush();
+/// drop(cache);
+/// assert_eq!(COUNTER.load(&store).unwrap(), 3);
+/// ```
+pub struct CachedStorage<'a> {
+    storage: &'a mut dyn Storage,
+    /// Values read from the wrapped storage, `None` if missing
+    reads: RefCell<BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
+    writes: BTreeMap<Vec<u8>, Pending>,
+    stats: Cell<CacheStats>,
+}
+
+impl<'a> CachedStorage<'a> {
+    pub fn new(storage: &'a mut dyn Storage) -> Self {
+        CachedStorage {
+            storage,
+            reads: RefCell::new(BTreeMap::new()),
+            writes: BTreeMap::new(),
+            stats: Cell::new(CacheStats::default()),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats.get()
+    }
+
+    /// Number of writes waiting to be flushed
+    pub fn pending_writes(&self) -> usize {
+        self.writes.len()
+    }
+
+    /// Applies the pending writes to the wrapped storage, in order. They stay cached for reads.
+    pub fn flush(&mut self) {
+        let mut writes: Vec<_> = std::mem::take(&mut self.writes).into_iter().collect();
+        writes.sort_by_key(|(_, (seq, _))| *seq);
+
+        let mut reads = self.reads.borrow_mut();
+        let mut stats = self.stats.get();
+        for (key, (_, value)) in writes {
+            match &value {
+                Some(value) => self.storage.set(&key, value),
+                None => self.storage.remove(&key),
+            }
+            reads.insert(key, value);
+            stats.flushed += 1;
+        }
+        self.stats.set(stats);
+    }
+
+    fn write(&mut self, key: &[u8], value: Option<Vec<u8>>) {
+        let mut stats = self.stats.get();
+        self.writes.insert(key.to_vec(), (stats.writes, value));
+        stats.writes += 1;
+        self.stats.set(stats);
+    }
+
+    fn count(&self, hit: bool) {
+        let mut stats = self.stats.get();
+        if hit {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+        self.stats.set(stats);
+    }
+}
+
+impl<'a> Storage for CachedStorage<'a> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        if let Some((_, value)) = self.writes.get(key) {
+            self.count(true);
+            return value.clone();
+        }
+        if let Some(value) = self.reads.borrow().get(key) {
+            self.count(true);
+            return value.clone();
+        }
+        self.count(false);
+        let value = self.storage.get(key);
+        self.reads.borrow_mut().insert(key.to_vec(), value.clone());
+        value
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.write(key, Some(value.to_vec()));
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.write(key, None);
+    }
+
+    #[cfg(feature = "iterator")]
+    /// Ranges over the wrapped storage, overlaid with the pending writes.
+    /// Ranges are not cached.
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'b> {
+        let bounds = (
+            start.map_or(Bound::Unbounded, |x| Bound::Included(x.to_vec())),
+            end.map_or(Bound::Unbounded, |x| Bound::Excluded(x.to_vec())),
+        );
+
+        // BTreeMap.range panics if start > end, which is just an empty range here
+        let pending: Box<dyn Iterator<Item = (&Vec<u8>, &Pending)>> =
+            match (bounds.start_bound(), bounds.end_bound()) {
+                (Bound::Included(start), Bound::Excluded(end)) if start > end => {
+                    Box::new(iter::empty())
+                }
+                _ => {
+                    let pending = self.writes.range(bounds);
+                    match order {
+                        Order::Ascending => Box::new(pending),
+                        Order::Descending => Box::new(pending.rev()),
+                    }
+                }
+            };
+
+        let base = self.storage.range(start, end, order);
+        Box::new(Overlay {
+            pending: pending.peekable(),
+            base: base.peekable(),
+            order,
+        })
+    }
+}
+
+/// Merges the pending writes into the records of the wrapped storage
+#[cfg(feature = "iterator")]
+struct Overlay<'b, P, B>
+where
+    P: Iterator<Item = (&'b Vec<u8>, &'b Pending)>,
+    B: Iterator<Item = Record>,
+{
+    pending: Peekable<P>,
+    base: Peekable<B>,
+    order: Order,
+}
+
+#[cfg(feature = "iterator")]
+impl<'b, P, B> Iterator for Overlay<'b, P, B>
+where
+    P: Iterator<Item = (&'b Vec<u8>, &'b Pending)>,
+    B: Iterator<Item = Record>,
+{
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Ordering::Less => the pending write comes first
+            let next = match (self.pending.peek(), self.base.peek()) {
+                (Some((pkey, _)), Some((bkey, _))) => match self.order {
+                    Order::Ascending => pkey.as_slice().cmp(bkey),
+                    Order::Descending => bkey.as_slice().cmp(pkey),
+                },
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => return self.base.next(),
+                (None, None) => return None,
+            };
+            if let Ordering::Greater = next {
+                return self.base.next();
+            }
+            if let Ordering::Equal = next {
+                // overwritten or removed
+                self.base.next();
+            }
+            match self.pending.next() {
+                Some((key, (_, Some(value)))) => return Some((key.clone(), value.clone())),
+                // removed, skip it
+                _ => continue,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Item, Map};
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::StdResult;
+
+    const CONFIG: Item<u32> = Item::new("config");
+    const BALANCES: Map<&str, u64> = Map::new("balances");
+
+    #[test]
+    fn memoizes_reads() {
+        let mut store = MockStorage::new();
+        CONFIG.save(&mut store, &7).unwrap();
+
+        let cache = CachedStorage::new(&mut store);
+        for _ in 0..3 {
+            assert_eq!(CONFIG.load(&cache).unwrap(), 7);
+            assert_eq!(BALANCES.may_load(&cache, "john").unwrap(), None);
+        }
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 4,
+                misses: 2,
+                writes: 0,
+                flushed: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn buffers_writes_until_flush() {
+        let mut store = MockStorage::new();
+        CONFIG.save(&mut store, &7).unwrap();
+
+        let mut cache = CachedStorage::new(&mut store);
+        CONFIG.save(&mut cache, &8).unwrap();
+        CONFIG
+            .update(&mut cache, |c| -> StdResult<_> { Ok(c + 1) })
+            .unwrap();
+        BALANCES.save(&mut cache, "john", &100).unwrap();
+        BALANCES.save(&mut cache, "jim", &50).unwrap();
+        BALANCES.remove(&mut cache, "jim");
+
+        // reads see the pending writes, without reading the wrapped storage
+        assert_eq!(CONFIG.load(&cache).unwrap(), 9);
+        assert_eq!(BALANCES.may_load(&cache, "jim").unwrap(), None);
+        assert_eq!(cache.stats().misses, 0);
+        assert_eq!(cache.pending_writes(), 3);
+
+        cache.flush();
+        assert_eq!(cache.pending_writes(), 0);
+        let stats = cache.stats();
+        assert_eq!((stats.writes, stats.flushed), (5, 3));
+        // still cached after flush
+        assert_eq!(CONFIG.load(&cache).unwrap(), 9);
+        assert_eq!(cache.stats().misses, 0);
+
+        drop(cache);
+        assert_eq!(CONFIG.load(&store).unwrap(), 9);
+        assert_eq!(BALANCES.load(&store, "john").unwrap(), 100);
+        assert_eq!(BALANCES.may_load(&store, "jim").unwrap(), None);
+    }
+
+    #[test]
+    fn discards_pending_writes_on_drop() {
+        let mut store = MockStorage::new();
+        CONFIG.save(&mut store, &1).unwrap();
+        {
+            let mut cache = CachedStorage::new(&mut store);
+            CONFIG.save(&mut cache, &2).unwrap();
+        }
+        assert_eq!(CONFIG.load(&store).unwrap(), 1);
+    }
+
+    /// Records the writes applied to it
+    #[derive(Default)]
+    struct WriteLog {
+        store: MockStorage,
+        log: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    }
+
+    impl Storage for WriteLog {
+        fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+            self.store.get(key)
+        }
+
+        #[cfg(feature = "iterator")]
+        fn range<'b>(
+            &'b self,
+            start: Option<&[u8]>,
+            end: Option<&[u8]>,
+            order: Order,
+        ) -> Box<dyn Iterator<Item = Record> + 'b> {
+            self.store.range(start, end, order)
+        }
+
+        fn set(&mut self, key: &[u8], value: &[u8]) {
+            self.log.push((key.to_vec(), Some(value.to_vec())));
+            self.store.set(key, value)
+        }
+
+        fn remove(&mut self, key: &[u8]) {
+            self.log.push((key.to_vec(), None));
+            self.store.remove(key)
+        }
+    }
+
+    #[test]
+    fn flushes_in_order() {
+        let mut store = WriteLog::default();
+        let mut cache = CachedStorage::new(&mut store);
+        cache.set(b"c", b"1");
+        cache.set(b"a", b"2");
+        cache.remove(b"b");
+        cache.set(b"c", b"3");
+        cache.flush();
+        drop(cache);
+
+        assert_eq!(
+            store.log,
+            vec![
+                (b"a".to_vec(), Some(b"2".to_vec())),
+                (b"b".to_vec(), None),
+                (b"c".to_vec(), Some(b"3".to_vec())),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_sees_pending_writes() {
+        let mut store = MockStorage::new();
+        BALANCES.save(&mut store, "a", &1).unwrap();
+        BALANCES.save(&mut store, "b", &2).unwrap();
+        BALANCES.save(&mut store, "d", &4).unwrap();
+
+        let mut cache = CachedStorage::new(&mut store);
+        BALANCES.save(&mut cache, "b", &20).unwrap();
+        BALANCES.save(&mut cache, "c", &30).unwrap();
+        BALANCES.remove(&mut cache, "d");
+        BALANCES.save(&mut cache, "e", &50).unwrap();
+
+        let all: StdResult<Vec<_>> = BALANCES
+            .range(&cache, None, None, Order::Ascending)
+            .collect();
+        let expected = vec![
+            ("a".to_string(), 1),
+            ("b".to_string(), 20),
+            ("c".to_string(), 30),
+            ("e".to_string(), 50),
+        ];
+        assert_eq!(all.unwrap(), expected);
+
+        let all: StdResult<Vec<_>> = BALANCES
+            .range(&cache, None, None, Order::Descending)
+            .collect();
+        let reversed: Vec<_> = expected.into_iter().rev().collect();
+        assert_eq!(all.unwrap(), reversed);
+
+        // bounded, and empty ranges
+        assert_eq!(
+            cache
+                .range(Some(b"b"), Some(b"a"), Order::Ascending)
+                .count(),
+            0
+        );
+        let keys: Vec<_> = BALANCES
+            .keys(
+                &cache,
+                Some(crate::Bound::inclusive("b")),
+                Some(crate::Bound::exclusive("e")),
+                Order::Descending,
+            )
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(keys, vec!["c".to_string(), "b".to_string()]);
+    }
+}
@@ -0,0 +1,104 @@
+//! Value serialization formats of the storage types.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use cosmwasm_std::{from_slice, to_vec, StdResult};
+
+/// Serializes values to and deserializes them from their stored bytes.
+/// Implement it to plug in a custom format.
+///
+/// `Item` and `Map` store their values as JSON by default. Heavy values (proposals holding many
+/// messages, big configs) can use a compact binary format instead, given as last type parameter
+/// and created with `with_codec`:
+///
+/// ```rust
+/// # #[cfg(feature = "bincode")]
+/// # {
+/// use cw_storage_plus::{Bincode, Map};
+///
+/// const PROPOSALS: Map<u64, Vec<String>, Bincode> = Map::with_codec("proposals");
+/// # }
+/// ```
+///
+/// Note that the codec is part of the storage layout: changing it requires migrating the
+/// stored values, and other contracts reading them with raw queries must use the same codec.
+/// Only `Item` and `Map` take a codec, the indexed and snapshot maps always store JSON.
+pub trait Codec {
+    fn encode<T: Serialize>(value: &T) -> StdResult<Vec<u8>>;
+
+    fn decode<T: DeserializeOwned>(data: &[u8]) -> StdResult<T>;
+}
+
+/// JSON, as done by `cosmwasm_std::to_vec` and `from_slice`. The default codec.
+#[derive(Debug, Clone, Copy)]
+pub struct Json;
+
+impl Codec for Json {
+    fn encode<T: Serialize>(value: &T) -> StdResult<Vec<u8>> {
+        to_vec(value)
+    }
+
+    fn decode<T: DeserializeOwned>(data: &[u8]) -> StdResult<T> {
+        from_slice(data)
+    }
+}
+
+/// Compact binary encoding of [bincode](https://docs.rs/bincode), with fixed size integers.
+///
+/// It is not self-describing, so it doesn't support types using `#[serde(untagged)]`,
+/// `#[serde(flatten)]` or `#[serde(skip_serializing_if = "...")]`, and adding a field
+/// to a stored struct requires a migration.
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy)]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl Codec for Bincode {
+    fn encode<T: Serialize>(value: &T) -> StdResult<Vec<u8>> {
+        bincode::serialize(value)
+            .map_err(|err| cosmwasm_std::StdError::serialize_err(std::any::type_name::<T>(), err))
+    }
+
+    fn decode<T: DeserializeOwned>(data: &[u8]) -> StdResult<T> {
+        bincode::deserialize(data)
+            .map_err(|err| cosmwasm_std::StdError::parse_err(std::any::type_name::<T>(), err))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Data {
+        pub name: String,
+        pub age: u32,
+    }
+
+    fn data() -> Data {
+        Data {
+            name: "John".to_string(),
+            age: 32,
+        }
+    }
+
+    #[test]
+    fn json_matches_cosmwasm_std() {
+        let encoded = Json::encode(&data()).unwrap();
+        assert_eq!(encoded, to_vec(&data()).unwrap());
+        assert_eq!(Json::decode::<Data>(&encoded).unwrap(), data());
+        assert!(Json::decode::<Data>(b"{").is_err());
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn bincode_round_trips() {
+        let encoded = Bincode::encode(&data()).unwrap();
+        // length of the name (u64), name, age (u32)
+        assert_eq!(encoded.len(), 8 + 4 + 4);
+        assert_eq!(Bincode::decode::<Data>(&encoded).unwrap(), data());
+        assert!(Bincode::decode::<Data>(&encoded[..10]).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 use std::array::TryFromSliceError;
 use std::convert::TryInto;
 
-use cosmwasm_std::{Addr, StdError, StdResult};
+use cosmwasm_std::{Addr, Decimal, StdError, StdResult, Timestamp, Uint128, Uint256, Uint64};
 
 use crate::int_key::IntKey;
 
@@ -112,6 +112,37 @@ macro_rules! integer_de {
 
 integer_de!(for i8, u8, i16, u16, i32, u32, i64, u64, i128, u128);
 
+macro_rules! fixed_size_de {
+    (for $($t:ty, $n:literal, $from_bytes:expr),+) => {
+        $(impl KeyDeserialize for $t {
+            type Output = $t;
+
+            #[inline(always)]
+            fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
+                let bytes: [u8; $n] = value.as_slice().try_into()
+                    .map_err(|err: TryFromSliceError| StdError::generic_err(err.to_string()))?;
+                let from_bytes: fn([u8; $n]) -> StdResult<$t> = $from_bytes;
+                from_bytes(bytes)
+            }
+        })*
+    }
+}
+
+fixed_size_de!(
+    for Uint64, 8, |b| Ok(Uint64::new(u64::from_be_bytes(b))),
+    Uint128, 16, |b| Ok(Uint128::new(u128::from_be_bytes(b))),
+    Uint256, 32, |b| Ok(Uint256::from_be_bytes(b)),
+    Decimal, 16, |b| Ok(Decimal::raw(u128::from_be_bytes(b))),
+    Timestamp, 8, |b| Ok(Timestamp::from_nanos(u64::from_be_bytes(b))),
+    bool, 1, |b| match b {
+        [0] => Ok(false),
+        [1] => Ok(true),
+        _ => Err(StdError::generic_err("Invalid bool key")),
+    },
+    char, 4, |b| char::from_u32(u32::from_be_bytes(b))
+        .ok_or_else(|| StdError::generic_err("Invalid char key"))
+);
+
 fn parse_length(value: &[u8]) -> StdResult<usize> {
     Ok(u16::from_be_bytes(
         value
@@ -151,6 +182,40 @@ impl<T: KeyDeserialize, U: KeyDeserialize, V: KeyDeserialize> KeyDeserialize for
     }
 }
 
+/// Splits the first length-prefixed element off a composite key
+fn split_first_key(value: &[u8]) -> StdResult<(&[u8], &[u8])> {
+    if value.len() < 2 {
+        return Err(StdError::generic_err("Could not read 2 byte length"));
+    }
+    let (len, rest) = value.split_at(2);
+    let len = parse_length(len)?;
+    if rest.len() < len {
+        return Err(StdError::generic_err("Composite key element is truncated"));
+    }
+    Ok(rest.split_at(len))
+}
+
+macro_rules! tuple_de {
+    ($($T:ident $t:ident),+; $Z:ident) => {
+        impl<$($T: KeyDeserialize,)+ $Z: KeyDeserialize> KeyDeserialize for ($($T,)+ $Z) {
+            type Output = ($($T::Output,)+ $Z::Output);
+
+            #[inline(always)]
+            fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
+                let rest = value.as_slice();
+                $(let ($t, rest) = split_first_key(rest)?;)+
+                Ok(($($T::from_slice($t)?,)+ $Z::from_slice(rest)?))
+            }
+        }
+    };
+}
+
+tuple_de!(T t, U u, V v; W);
+tuple_de!(T t, U u, V v, W w; X);
+tuple_de!(T t, U u, V v, W w, X x; Y);
+tuple_de!(T t, U u, V v, W w, X x, Y y; Z);
+tuple_de!(T t, U u, V v, W w, X x, Y y, Z z; A);
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -249,6 +314,41 @@ mod test {
         );
     }
 
+    #[test]
+    fn deserialize_std_keys_works() {
+        assert_eq!(
+            Uint64::from_slice(&Uint64::new(4242).joined_key()).unwrap(),
+            Uint64::new(4242)
+        );
+        assert_eq!(
+            Uint128::from_slice(&Uint128::new(4242).joined_key()).unwrap(),
+            Uint128::new(4242)
+        );
+        assert_eq!(
+            Uint256::from_slice(&Uint256::from(4242u128).joined_key()).unwrap(),
+            Uint256::from(4242u128)
+        );
+        assert_eq!(
+            Decimal::from_slice(&Decimal::percent(150).joined_key()).unwrap(),
+            Decimal::percent(150)
+        );
+        assert_eq!(
+            Timestamp::from_slice(&Timestamp::from_nanos(4242).joined_key()).unwrap(),
+            Timestamp::from_nanos(4242)
+        );
+        assert!(bool::from_slice(&[1]).unwrap());
+        assert!(!bool::from_slice(&[0]).unwrap());
+        assert_eq!(char::from_slice(&'\u{e9}'.joined_key()).unwrap(), '\u{e9}');
+    }
+
+    #[test]
+    fn deserialize_invalid_std_keys_fails() {
+        assert!(Uint128::from_slice(&[1, 2, 3]).is_err());
+        assert!(bool::from_slice(&[2]).is_err());
+        // a surrogate code point
+        assert!(char::from_slice(&0xd800u32.to_be_bytes()).is_err());
+    }
+
     #[test]
     fn deserialize_tuple_works() {
         assert_eq!(
@@ -265,4 +365,89 @@ mod test {
             (BYTES.to_vec(), 1234, STRING.to_string())
         );
     }
+
+    #[test]
+    fn deserialize_larger_tuples_works() {
+        assert_eq!(
+            <(&str, u8, &[u8], u32)>::from_slice(
+                ("a", 1u8, BYTES, 1234u32).joined_key().as_slice()
+            )
+            .unwrap(),
+            ("a".to_string(), 1, BYTES.to_vec(), 1234)
+        );
+        assert_eq!(
+            <(&str, u8, &[u8], u32, &str)>::from_slice(
+                ("a", 1u8, BYTES, 1234u32, STRING).joined_key().as_slice()
+            )
+            .unwrap(),
+            ("a".to_string(), 1, BYTES.to_vec(), 1234, STRING.to_string())
+        );
+        assert_eq!(
+            <(&str, u8, &[u8], u32, &str, i64)>::from_slice(
+                ("a", 1u8, BYTES, 1234u32, STRING, -5i64)
+                    .joined_key()
+                    .as_slice()
+            )
+            .unwrap(),
+            (
+                "a".to_string(),
+                1,
+                BYTES.to_vec(),
+                1234,
+                STRING.to_string(),
+                -5
+            )
+        );
+        assert_eq!(
+            <(&str, u8, &[u8], u32, &str, i64, String)>::from_slice(
+                ("a", 1u8, BYTES, 1234u32, STRING, -5i64, "b".to_string())
+                    .joined_key()
+                    .as_slice()
+            )
+            .unwrap(),
+            (
+                "a".to_string(),
+                1,
+                BYTES.to_vec(),
+                1234,
+                STRING.to_string(),
+                -5,
+                "b".to_string()
+            )
+        );
+        assert_eq!(
+            <(&str, u8, &[u8], u32, &str, i64, String, Vec<u8>)>::from_slice(
+                (
+                    "a",
+                    1u8,
+                    BYTES,
+                    1234u32,
+                    STRING,
+                    -5i64,
+                    "b".to_string(),
+                    vec![]
+                )
+                    .joined_key()
+                    .as_slice()
+            )
+            .unwrap(),
+            (
+                "a".to_string(),
+                1,
+                BYTES.to_vec(),
+                1234,
+                STRING.to_string(),
+                -5,
+                "b".to_string(),
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    fn deserialize_truncated_tuple_fails() {
+        let key = ("a", "b", "c", "d").joined_key();
+        assert!(<(&str, &str, &str, &str)>::from_slice(&key[..4]).is_err());
+        assert!(<(&str, &str, &str, &str)>::from_slice(&key[..7]).is_err());
+    }
 }
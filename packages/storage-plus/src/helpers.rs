@@ -7,11 +7,12 @@
 use serde::de::DeserializeOwned;
 use std::any::type_name;
 
+use crate::codec::{Codec, Json};
 use crate::keys::Key;
 
 use cosmwasm_std::{
-    from_slice, to_vec, Addr, Binary, ContractResult, CustomQuery, QuerierWrapper, QueryRequest,
-    StdError, StdResult, SystemResult, WasmQuery,
+    to_vec, Addr, Binary, ContractResult, CustomQuery, QuerierWrapper, QueryRequest, StdError,
+    StdResult, SystemResult, WasmQuery,
 };
 
 /// may_deserialize parses json bytes from storage (Option), returning Ok(None) if no data present
@@ -20,17 +21,25 @@ use cosmwasm_std::{
 /// and value.map(|s| s.as_slice()) seems trickier than &value
 pub(crate) fn may_deserialize<T: DeserializeOwned>(
     value: &Option<Vec<u8>>,
+) -> StdResult<Option<T>> {
+    may_decode::<Json, T>(value)
+}
+
+/// Like may_deserialize, for bytes encoded with the given codec
+pub(crate) fn may_decode<C: Codec, T: DeserializeOwned>(
+    value: &Option<Vec<u8>>,
 ) -> StdResult<Option<T>> {
     match value {
-        Some(vec) => Ok(Some(from_slice(vec)?)),
+        Some(vec) => Ok(Some(C::decode(vec)?)),
         None => Ok(None),
     }
 }
 
-/// must_deserialize parses json bytes from storage (Option), returning NotFound error if no data present
-pub(crate) fn must_deserialize<T: DeserializeOwned>(value: &Option<Vec<u8>>) -> StdResult<T> {
+/// must_decode parses bytes encoded with the given codec from storage (Option), returning
+/// NotFound error if no data present
+pub(crate) fn must_decode<C: Codec, T: DeserializeOwned>(value: &Option<Vec<u8>>) -> StdResult<T> {
     match value {
-        Some(vec) => from_slice(vec),
+        Some(vec) => C::decode(vec),
         None => Err(StdError::not_found(type_name::<T>())),
     }
 }
@@ -169,7 +178,7 @@ mod test {
     }
 
     #[test]
-    fn must_deserialize_handles_some() {
+    fn must_decode_handles_some() {
         let person = Person {
             name: "Maria".to_string(),
             age: 42,
@@ -177,13 +186,13 @@ mod test {
         let value = to_vec(&person).unwrap();
         let loaded = Some(value);
 
-        let parsed: Person = must_deserialize(&loaded).unwrap();
+        let parsed: Person = must_decode::<Json, _>(&loaded).unwrap();
         assert_eq!(parsed, person);
     }
 
     #[test]
-    fn must_deserialize_handles_none() {
-        let parsed = must_deserialize::<Person>(&None);
+    fn must_decode_handles_none() {
+        let parsed = must_decode::<Json, Person>(&None);
         match parsed.unwrap_err() {
             StdError::NotFound { kind, .. } => {
                 assert_eq!(kind, "cw_storage_plus::helpers::test::Person")
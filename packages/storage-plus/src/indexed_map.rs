@@ -2,17 +2,20 @@
 #![cfg(feature = "iterator")]
 
 use crate::PrefixBound;
-use cosmwasm_std::{StdError, StdResult, Storage};
+use cosmwasm_std::{from_slice, Binary, Order, StdError, StdResult, Storage};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::de::KeyDeserialize;
+use crate::helpers::nested_namespaces_with_key;
 use crate::indexes::Index;
-use crate::iter_helpers::{deserialize_kv, deserialize_v};
-use crate::keys::{Prefixer, PrimaryKey};
+use crate::iter_helpers::{concat, deserialize_kv, deserialize_v};
+use crate::keys::{Key, Prefixer, PrimaryKey};
 use crate::map::Map;
-use crate::prefix::{namespaced_prefix_range, Prefix};
-use crate::{Bound, Path};
+use crate::paginate::{Page, Paginator};
+use crate::prefix::{default_deserializer_v, namespaced_prefix_range, range_with_prefix, Prefix};
+use crate::range_query::RangeQuery;
+use crate::{Bound, Item, Path};
 
 pub trait IndexList<T> {
     fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<T>> + '_>;
@@ -28,6 +31,8 @@ where
 {
     pk_namespace: &'a [u8],
     primary: Map<'a, K, T>,
+    /// Optional total number of entries, see `with_counter`
+    counter: Option<Item<'a, u64>>,
     /// This is meant to be read directly to get the proper types, like:
     /// map.idx.owner.items(...)
     pub idx: I,
@@ -44,10 +49,21 @@ where
         IndexedMap {
             pk_namespace: pk_namespace.as_bytes(),
             primary: Map::new(pk_namespace),
+            counter: None,
             idx: indexes,
         }
     }
 
+    /// Keeps the total number of entries under the given key, updated on every write, so `len`
+    /// is O(1) rather than iterating over the whole map.
+    ///
+    /// A counter enabled on a map that already has entries must be set up once with
+    /// `rebuild_counter`, for example in `migrate`.
+    pub fn with_counter(mut self, counter_key: &'a str) -> Self {
+        self.counter = Some(Item::new(counter_key));
+        self
+    }
+
     pub fn key(&self, k: K) -> Path<T> {
         self.primary.key(k)
     }
@@ -84,19 +100,35 @@ where
     ) -> StdResult<()> {
         // this is the key *relative* to the primary map namespace
         let pk = key.joined_key();
-        if let Some(old) = old_data {
-            for index in self.idx.get_indexes() {
-                index.remove(store, &pk, old)?;
+        for index in self.idx.get_indexes() {
+            match (old_data, data) {
+                (Some(old), Some(updated)) => index.update(store, &pk, old, updated)?,
+                (Some(old), None) => index.remove(store, &pk, old)?,
+                (None, Some(updated)) => index.save(store, &pk, updated)?,
+                (None, None) => {}
             }
         }
         if let Some(updated) = data {
-            for index in self.idx.get_indexes() {
-                index.save(store, &pk, updated)?;
-            }
             self.primary.save(store, key, updated)?;
         } else {
             self.primary.remove(store, key);
         }
+        match (old_data, data) {
+            (None, Some(_)) => self.update_counter(store, |count| count + 1),
+            (Some(_), None) => self.update_counter(store, |count| count.saturating_sub(1)),
+            _ => Ok(()),
+        }
+    }
+
+    fn update_counter(
+        &self,
+        store: &mut dyn Storage,
+        action: impl FnOnce(u64) -> u64,
+    ) -> StdResult<()> {
+        if let Some(counter) = &self.counter {
+            let count = counter.may_load(store)?.unwrap_or_default();
+            counter.save(store, &action(count))?;
+        }
         Ok(())
     }
 
@@ -184,6 +216,88 @@ where
     pub fn prefix(&self, p: K::Prefix) -> Prefix<K::Suffix, T, K::Suffix> {
         Prefix::new(self.pk_namespace, &p.prefix())
     }
+
+    /// Returns `true` if the map contains no entries
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.no_prefix_raw().is_empty(store)
+    }
+
+    /// Returns the number of entries. This is O(1) when the map was created `with_counter`,
+    /// otherwise it iterates over all the entries.
+    pub fn len(&self, store: &dyn Storage) -> StdResult<u64> {
+        match &self.counter {
+            Some(counter) => Ok(counter.may_load(store)?.unwrap_or_default()),
+            None => Ok(self
+                .no_prefix_raw()
+                .keys_raw(store, None, None, Order::Ascending)
+                .count() as u64),
+        }
+    }
+
+    /// Recomputes the counter from the entries, see `with_counter`. This iterates over the
+    /// whole map, so it's meant to be called once, when enabling the counter.
+    pub fn rebuild_counter(&self, store: &mut dyn Storage) -> StdResult<()> {
+        let counter = match &self.counter {
+            Some(counter) => counter,
+            None => return Err(StdError::generic_err("Counter is not enabled")),
+        };
+        let count = self
+            .no_prefix_raw()
+            .keys_raw(store, None, None, Order::Ascending)
+            .count() as u64;
+        counter.save(store, &count)
+    }
+
+    /// Removes all the entries of the map, together with their index entries
+    pub fn clear(&self, store: &mut dyn Storage) -> StdResult<()> {
+        self.clear_with_prefix(store, &[], None)
+    }
+
+    /// Removes the entries under the given prefix in ascending key order, together with their
+    /// index entries. If `limit` is given, at most that many are removed.
+    ///
+    /// Note that `prefix(p).clear(..)` would leave dangling index entries behind.
+    pub fn clear_prefix(
+        &self,
+        store: &mut dyn Storage,
+        p: K::Prefix,
+        limit: Option<usize>,
+    ) -> StdResult<()> {
+        self.clear_with_prefix(store, &p.prefix(), limit)
+    }
+
+    fn clear_with_prefix(
+        &self,
+        store: &mut dyn Storage,
+        prefix: &[Key],
+        limit: Option<usize>,
+    ) -> StdResult<()> {
+        // remove in batches, so we don't load the whole map into memory
+        const TAKE: usize = 10;
+        let namespace = nested_namespaces_with_key(&[self.pk_namespace], prefix, b"");
+        // indexes refer to the primary key relative to the (length-prefixed) map namespace
+        let pk_offset = self.pk_namespace.len() + 2;
+        let mut left_to_clear = limit.unwrap_or(usize::MAX);
+        while left_to_clear > 0 {
+            let batch = range_with_prefix(store, &namespace, None, None, Order::Ascending)
+                .take(TAKE.min(left_to_clear))
+                .map(|(k, v)| Ok((concat(&namespace, &k), from_slice::<T>(&v)?)))
+                .collect::<StdResult<Vec<_>>>()?;
+            if batch.is_empty() {
+                break;
+            }
+            left_to_clear -= batch.len();
+            let removed = batch.len() as u64;
+            self.update_counter(store, |count| count.saturating_sub(removed))?;
+            for (key, old) in batch {
+                for index in self.idx.get_indexes() {
+                    index.remove(store, &key[pk_offset..], &old)?;
+                }
+                store.remove(&key);
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(feature = "iterator")]
@@ -268,6 +382,45 @@ where
         self.no_prefix().keys(store, min, max, order)
     }
 
+    /// Returns the entry with the lowest primary key, if any
+    pub fn first(&self, store: &dyn Storage) -> StdResult<Option<(K::Output, T)>>
+    where
+        K::Output: 'static,
+    {
+        self.range(store, None, None, Order::Ascending)
+            .next()
+            .transpose()
+    }
+
+    /// Returns the entry with the highest primary key, if any
+    pub fn last(&self, store: &dyn Storage) -> StdResult<Option<(K::Output, T)>>
+    where
+        K::Output: 'static,
+    {
+        self.range(store, None, None, Order::Descending)
+            .next()
+            .transpose()
+    }
+
+    /// Returns the page of entries after the `cursor` of the previous page, by primary key,
+    /// see `Paginator`
+    pub fn paginate(
+        &self,
+        store: &dyn Storage,
+        paginator: &Paginator,
+        cursor: Option<Binary>,
+        limit: Option<u32>,
+        order: Order,
+    ) -> StdResult<Page<(K::Output, T)>> {
+        self.no_prefix()
+            .paginate(store, paginator, cursor, limit, order)
+    }
+
+    /// Starts building a range over the primary keys, see `RangeQuery`
+    pub fn range_query(&self) -> RangeQuery<K, T> {
+        RangeQuery::new(self.pk_namespace, &[], default_deserializer_v::<T>)
+    }
+
     fn no_prefix(&self) -> Prefix<K, T, K> {
         Prefix::new(self.pk_namespace, &[])
     }
@@ -278,9 +431,9 @@ mod test {
     use super::*;
 
     use crate::indexes::test::{index_string_tuple, index_tuple};
-    use crate::{MultiIndex, UniqueIndex};
+    use crate::{IndexPk, MultiIndex, MultiValueIndex, Page, Paginator, PrefixBound, UniqueIndex};
     use cosmwasm_std::testing::MockStorage;
-    use cosmwasm_std::{MemoryStorage, Order};
+    use cosmwasm_std::{Binary, MemoryStorage, Order, StdResult};
     use serde::{Deserialize, Serialize};
 
     #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -707,6 +860,495 @@ mod test {
         assert_eq!(data3, marias[1].1);
     }
 
+    #[test]
+    fn clear_maintains_indexes() {
+        let mut store = MockStorage::new();
+        let map = build_map();
+        assert!(map.is_empty(&store));
+        assert_eq!(map.first(&store).unwrap(), None);
+
+        let (pks, datas) = save_data(&mut store, &map);
+        assert!(!map.is_empty(&store));
+        assert_eq!(
+            map.first(&store).unwrap(),
+            Some((pks[0].to_string(), datas[0].clone()))
+        );
+        assert_eq!(
+            map.last(&store).unwrap(),
+            Some((pks[4].to_string(), datas[4].clone()))
+        );
+
+        map.clear(&mut store).unwrap();
+        assert!(map.is_empty(&store));
+        // no dangling index entries are left behind
+        assert_eq!(store.range(None, None, Order::Ascending).count(), 0);
+
+        // so unique values can be reused
+        map.save(&mut store, "6", &datas[0]).unwrap();
+        let count = map
+            .idx
+            .name
+            .prefix("Maria".to_string())
+            .keys_raw(&store, None, None, Order::Ascending)
+            .count();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn counted_map_and_index() {
+        let mut store = MockStorage::new();
+        let indexes = DataIndexes {
+            name: MultiIndex::new(|d: &Data| d.name.clone(), "data", "data__name")
+                .with_counters("data__name__count"),
+            age: UniqueIndex::new(|d| d.age, "data__age"),
+            name_lastname: UniqueIndex::new(
+                |d| index_string_tuple(&d.name, &d.last_name),
+                "data__name_lastname",
+            ),
+        };
+        let map = IndexedMap::new("data", indexes).with_counter("data__count");
+        assert_eq!(map.len(&store).unwrap(), 0);
+
+        let (pks, datas) = save_data(&mut store, &map);
+        assert_eq!(map.len(&store).unwrap(), 5);
+        let count = |store: &MockStorage, name: &str| {
+            map.idx.name.count_prefix(store, name.to_string()).unwrap()
+        };
+        assert_eq!(count(&store, "Maria"), 2);
+        assert_eq!(count(&store, "John"), 1);
+        assert_eq!(count(&store, "Paul"), 0);
+
+        // updating the other fields keeps the counts
+        let mut data = datas[0].clone();
+        data.age = 50;
+        map.save(&mut store, pks[0], &data).unwrap();
+        assert_eq!(map.len(&store).unwrap(), 5);
+        assert_eq!(count(&store, "Maria"), 2);
+
+        // overwriting keeps the total, but moves the entry between index values
+        let mut data = datas[0].clone();
+        data.name = "Paul".to_string();
+        map.save(&mut store, pks[0], &data).unwrap();
+        assert_eq!(map.len(&store).unwrap(), 5);
+        assert_eq!(count(&store, "Maria"), 1);
+        assert_eq!(count(&store, "Paul"), 1);
+
+        map.remove(&mut store, pks[0]).unwrap();
+        // removing a missing entry changes nothing
+        map.remove(&mut store, pks[0]).unwrap();
+        assert_eq!(map.len(&store).unwrap(), 4);
+        assert_eq!(count(&store, "Paul"), 0);
+
+        map.clear_prefix(&mut store, (), Some(2)).unwrap();
+        assert_eq!(map.len(&store).unwrap(), 2);
+        assert_eq!(count(&store, "Maria"), 0);
+        assert_eq!(count(&store, "John"), 0);
+
+        map.clear(&mut store).unwrap();
+        assert_eq!(map.len(&store).unwrap(), 0);
+        assert_eq!(count(&store, "Marta"), 0);
+
+        // uncounted maps and indexes iterate instead
+        let map = build_map();
+        save_data(&mut store, &map);
+        assert_eq!(map.len(&store).unwrap(), 5);
+        assert_eq!(
+            map.idx
+                .name
+                .count_prefix(&store, "Maria".to_string())
+                .unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn counted_composite_index_and_rebuild() {
+        let mut store = MockStorage::new();
+        let indexes = DataCompositeMultiIndex {
+            name_age: MultiIndex::new(|d| index_tuple(&d.name, d.age), "data", "data__name_age"),
+        };
+        let map: IndexedMap<&str, _, _> = IndexedMap::new("data", indexes);
+        for (pk, name, age) in [("1", "Maria", 42), ("2", "Maria", 42), ("3", "Maria", 23)] {
+            let data = Data {
+                name: name.to_string(),
+                last_name: "Doe".to_string(),
+                age,
+            };
+            map.save(&mut store, pk, &data).unwrap();
+        }
+
+        // counters enabled on existing entries are set up by the rebuild
+        let indexes = DataCompositeMultiIndex {
+            name_age: MultiIndex::new(
+                |d: &Data| index_tuple(&d.name, d.age),
+                "data",
+                "data__name_age",
+            )
+            .with_counters("data__name_age__count"),
+        };
+        let map = IndexedMap::new("data", indexes).with_counter("data__count");
+        assert_eq!(map.len(&store).unwrap(), 0);
+        map.rebuild_counter(&mut store).unwrap();
+        map.idx.name_age.rebuild_counters(&mut store).unwrap();
+        assert_eq!(map.len(&store).unwrap(), 3);
+
+        // counts the entries of the whole index value, not of its leading name
+        let count = |store: &MockStorage, name: &str, age| {
+            map.idx
+                .name_age
+                .count_prefix(store, index_tuple(name, age))
+                .unwrap()
+        };
+        assert_eq!(count(&store, "Maria", 42), 2);
+        assert_eq!(count(&store, "Maria", 23), 1);
+        assert_eq!(count(&store, "Maria", 0), 0);
+
+        map.remove(&mut store, "2").unwrap();
+        assert_eq!(count(&store, "Maria", 42), 1);
+        assert_eq!(map.len(&store).unwrap(), 2);
+
+        // the rebuild agrees with the counters kept on writes
+        map.idx.name_age.rebuild_counters(&mut store).unwrap();
+        assert_eq!(count(&store, "Maria", 42), 1);
+        assert_eq!(count(&store, "Maria", 23), 1);
+
+        // rebuilding needs the counters enabled
+        build_map()
+            .idx
+            .name
+            .rebuild_counters(&mut store)
+            .unwrap_err();
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    struct Post {
+        pub title: String,
+        pub tags: Vec<String>,
+    }
+
+    struct PostIndexes<'a> {
+        pub tags: MultiValueIndex<'a, String, Post, String>,
+    }
+
+    impl<'a> IndexList<Post> for PostIndexes<'a> {
+        fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Post>> + '_> {
+            let v: Vec<&dyn Index<Post>> = vec![&self.tags];
+            Box::new(v.into_iter())
+        }
+    }
+
+    /// Counts the writes to the wrapped storage
+    #[derive(Default)]
+    struct WriteCounter {
+        storage: MockStorage,
+        writes: usize,
+    }
+
+    impl Storage for WriteCounter {
+        fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+            self.storage.get(key)
+        }
+
+        fn range<'a>(
+            &'a self,
+            start: Option<&[u8]>,
+            end: Option<&[u8]>,
+            order: Order,
+        ) -> Box<dyn Iterator<Item = cosmwasm_std::Record> + 'a> {
+            self.storage.range(start, end, order)
+        }
+
+        fn set(&mut self, key: &[u8], value: &[u8]) {
+            self.writes += 1;
+            self.storage.set(key, value)
+        }
+
+        fn remove(&mut self, key: &[u8]) {
+            self.writes += 1;
+            self.storage.remove(key)
+        }
+    }
+
+    fn post(title: &str, tags: &[&str]) -> Post {
+        Post {
+            title: title.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn multi_value_index() {
+        let mut store = WriteCounter::default();
+        let indexes = PostIndexes {
+            tags: MultiValueIndex::new(|p: &Post| p.tags.clone(), "posts", "posts__tags")
+                .with_counters("posts__tags__count"),
+        };
+        let map = IndexedMap::new("posts", indexes);
+
+        map.save(&mut store, "1", &post("rust", &["code", "rust"]))
+            .unwrap();
+        map.save(&mut store, "2", &post("wasm", &["code", "wasm", "wasm"]))
+            .unwrap();
+        map.save(&mut store, "3", &post("untagged", &[])).unwrap();
+
+        let tagged = |store: &dyn Storage, tag: &str| -> Vec<String> {
+            map.idx
+                .tags
+                .prefix(tag.to_string())
+                .keys(store, None, None, Order::Ascending)
+                .collect::<StdResult<_>>()
+                .unwrap()
+        };
+        assert_eq!(tagged(&store, "code"), vec!["1", "2"]);
+        assert_eq!(tagged(&store, "rust"), vec!["1"]);
+        assert_eq!(tagged(&store, "wasm"), vec!["2"]);
+        // repeated values are indexed once
+        assert_eq!(map.idx.tags.count_prefix(&store, "wasm".into()).unwrap(), 1);
+        assert_eq!(map.idx.tags.count_prefix(&store, "code".into()).unwrap(), 2);
+
+        // every (tag, pk) pair, ordered by tag
+        let all: Vec<_> = map
+            .idx
+            .tags
+            .keys(&store, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(all, vec!["1", "2", "1", "2"]);
+
+        // updating only touches the changed values: the primary entry, and the index entry and
+        // counter of "code" and "std"
+        store.writes = 0;
+        map.save(&mut store, "1", &post("rust", &["rust", "std"]))
+            .unwrap();
+        assert_eq!(store.writes, 5);
+        store.writes = 0;
+        map.save(&mut store, "1", &post("rust", &["std", "rust"]))
+            .unwrap();
+        // reordered values only rewrite the primary entry
+        assert_eq!(store.writes, 1);
+        assert_eq!(tagged(&store, "code"), vec!["2"]);
+        assert_eq!(tagged(&store, "std"), vec!["1"]);
+        assert_eq!(map.idx.tags.count_prefix(&store, "code".into()).unwrap(), 1);
+
+        map.remove(&mut store, "2").unwrap();
+        assert!(tagged(&store, "code").is_empty());
+        assert!(tagged(&store, "wasm").is_empty());
+        assert_eq!(map.idx.tags.count_prefix(&store, "wasm".into()).unwrap(), 0);
+
+        // index values are length-prefixed, so "std" sorts before "rust"
+        let titles: Vec<_> = map
+            .idx
+            .tags
+            .prefix_range_raw(
+                &store,
+                Some(PrefixBound::inclusive("std")),
+                None,
+                Order::Ascending,
+            )
+            .map(|r| r.map(|(_, p)| p.title))
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(titles, vec!["rust", "rust"]);
+    }
+
+    #[test]
+    fn range_query_over_multi_index() {
+        let mut store = MockStorage::new();
+        let map = build_map();
+        save_data(&mut store, &map);
+
+        // the index keys are (name, pk), "Maria Luisa" sorts last as it is longer
+        let pks = |query: RangeQuery<(String, String), Data, IndexPk<String>>| -> Vec<String> {
+            query.keys(&store).collect::<StdResult<_>>().unwrap()
+        };
+        assert_eq!(
+            pks(map.idx.name.range_query()),
+            vec!["3", "1", "2", "5", "4"]
+        );
+
+        // an exclusive prefix bound skips all the primary keys of the index value
+        assert_eq!(
+            pks(map
+                .idx
+                .name
+                .range_query()
+                .from_prefix(PrefixBound::exclusive("Maria".to_string()))),
+            vec!["5", "4"]
+        );
+        // an inclusive one includes them all
+        assert_eq!(
+            pks(map
+                .idx
+                .name
+                .range_query()
+                .to_prefix(PrefixBound::inclusive("Maria".to_string()))
+                .rev()),
+            vec!["2", "1", "3"]
+        );
+        // a bound on the index value and primary key
+        assert_eq!(
+            pks(map
+                .idx
+                .name
+                .range_query()
+                .from(Bound::exclusive(("Maria".to_string(), "1".to_string())))
+                .limit(2)),
+            vec!["2", "5"]
+        );
+
+        let entries: Vec<_> = map
+            .idx
+            .name
+            .range_query()
+            .prefix("Maria".to_string())
+            .from(Bound::exclusive("1"))
+            .range(&store)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, "2");
+        assert_eq!(entries[0].1.last_name, "Williams");
+
+        // the primary keys are returned under any prefix
+        assert_eq!(
+            pks(map.idx.name.range_query().sub_prefix(()).rev().limit(2)),
+            vec!["4", "5"]
+        );
+        let pks_of_maria = |query: RangeQuery<String, Data, IndexPk<String>>| -> Vec<String> {
+            query.keys(&store).collect::<StdResult<_>>().unwrap()
+        };
+        assert_eq!(
+            pks_of_maria(map.idx.name.range_query().prefix("Maria".to_string())),
+            vec!["1", "2"]
+        );
+
+        // the primary map
+        let pks: Vec<_> = map
+            .range_query()
+            .from(Bound::inclusive("2"))
+            .rev()
+            .keys(&store)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(pks, vec!["5", "4", "3", "2"]);
+    }
+
+    #[test]
+    fn paginate_by_primary_key_and_indexes() {
+        const PAGINATOR: Paginator = Paginator::new(2, 3);
+        let mut store = MockStorage::new();
+        let map = build_map();
+        save_data(&mut store, &map);
+
+        // pages the keys of a paginated query until the end, returns the pages
+        fn pages<F>(mut query: F) -> Vec<Vec<String>>
+        where
+            F: FnMut(Option<Binary>) -> StdResult<Page<(String, Data)>>,
+        {
+            let mut pages = vec![];
+            let mut cursor = None;
+            loop {
+                let (entries, next) = query(cursor).unwrap();
+                pages.push(entries.into_iter().map(|(pk, _)| pk).collect());
+                match next {
+                    Some(next) => cursor = Some(next),
+                    None => return pages,
+                }
+            }
+        }
+
+        let by_pk = pages(|c| map.paginate(&store, &PAGINATOR, c, None, Order::Ascending));
+        assert_eq!(by_pk, vec![vec!["1", "2"], vec!["3", "4"], vec!["5"]]);
+
+        // the cursor holds the index value and the primary key, so entries with the same index
+        // value are split correctly between pages
+        let by_name = pages(|c| {
+            map.idx
+                .name
+                .paginate(&store, &PAGINATOR, c, None, Order::Ascending)
+        });
+        assert_eq!(by_name, vec![vec!["3", "1"], vec!["2", "5"], vec!["4"]]);
+
+        let by_name = pages(|c| {
+            map.idx.name.prefix("Maria".to_string()).paginate(
+                &store,
+                &PAGINATOR,
+                c,
+                Some(1),
+                Order::Descending,
+            )
+        });
+        assert_eq!(by_name, vec![vec!["2"], vec!["1"]]);
+
+        // the limit is capped
+        let by_age = pages(|c| {
+            map.idx
+                .age
+                .paginate(&store, &PAGINATOR, c, Some(10), Order::Descending)
+        });
+        assert_eq!(by_age, vec![vec!["5", "1", "3"], vec!["2", "4"]]);
+
+        // a zero limit still returns an entry, and moves the cursor forward
+        let (entries, next) = map
+            .paginate(&store, &PAGINATOR, None, Some(0), Order::Ascending)
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(next.is_some());
+
+        // the entry after the page is not loaded: corrupting the primary data of "2", which
+        // follows the first page by name, only breaks the next page
+        Map::<&str, String>::new("data")
+            .save(&mut store, "2", &"corrupted".to_string())
+            .unwrap();
+        let (entries, next) = map
+            .idx
+            .name
+            .paginate(&store, &PAGINATOR, None, None, Order::Ascending)
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        map.idx
+            .name
+            .paginate(&store, &PAGINATOR, next, None, Order::Ascending)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn clear_prefix_with_limit() {
+        let mut store = MockStorage::new();
+
+        let indexes = DataCompositeMultiIndex {
+            name_age: MultiIndex::new(|d| index_tuple(&d.name, d.age), "data", "data__name_age"),
+        };
+        let map: IndexedMap<(&str, &str), _, _> = IndexedMap::new("data", indexes);
+
+        for (owner, id, age) in [("alice", "1", 10), ("alice", "2", 20), ("bob", "1", 30)] {
+            let data = Data {
+                name: "Maria".to_string(),
+                last_name: owner.to_string(),
+                age,
+            };
+            map.save(&mut store, (owner, id), &data).unwrap();
+        }
+        let maria_count = |store: &MockStorage| {
+            map.idx
+                .name_age
+                .sub_prefix(b"Maria".to_vec())
+                .keys_raw(store, None, None, Order::Ascending)
+                .count()
+        };
+        assert_eq!(maria_count(&store), 3);
+
+        map.clear_prefix(&mut store, "alice", Some(1)).unwrap();
+        assert!(!map.has(&store, ("alice", "1")));
+        assert!(map.has(&store, ("alice", "2")));
+        assert_eq!(maria_count(&store), 2);
+
+        map.clear_prefix(&mut store, "alice", None).unwrap();
+        assert!(map.prefix("alice").is_empty(&store));
+        assert!(map.has(&store, ("bob", "1")));
+        assert_eq!(maria_count(&store), 1);
+    }
+
     #[test]
     fn range_composite_key_by_multi_index() {
         let mut store = MockStorage::new();
@@ -1,28 +1,32 @@
 // this module requires iterator to be useful at all
 #![cfg(feature = "iterator")]
 
-use cosmwasm_std::{StdError, StdResult, Storage};
+use cosmwasm_std::{Record, StdError, StdResult, Storage, Timestamp};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use std::time::Duration;
 
 use crate::de::KeyDeserialize;
 use crate::iter_helpers::deserialize_kv;
 use crate::keys::{Prefixer, PrimaryKey};
 use crate::prefix::{namespaced_prefix_range, Prefix};
-use crate::snapshot::{ChangeSet, SnapshotMap};
+use crate::snapshot::{ChangeSet, CheckpointDimension, HistoricalRange, SnapshotMap};
 use crate::PrefixBound;
 use crate::{Bound, IndexList, Map, Path, Strategy};
 
 /// `IndexedSnapshotMap` works like a `SnapshotMap` but has a secondary index
-pub struct IndexedSnapshotMap<'a, K, T, I> {
+///
+/// Only the primary data is historical: the `*_at_height` queries read the primary map as it was
+/// at the given height, while the indexes always reflect the current state.
+pub struct IndexedSnapshotMap<'a, K, T, I, D = u64> {
     pk_namespace: &'a [u8],
-    primary: SnapshotMap<'a, K, T>,
+    primary: SnapshotMap<'a, K, T, D>,
     /// This is meant to be read directly to get the proper types, like:
     /// map.idx.owner.items(...)
     pub idx: I,
 }
 
-impl<'a, K, T, I> IndexedSnapshotMap<'a, K, T, I> {
+impl<'a, K, T, I, D> IndexedSnapshotMap<'a, K, T, I, D> {
     /// Examples:
     ///
     /// ```rust
@@ -58,35 +62,69 @@ impl<'a, K, T, I> IndexedSnapshotMap<'a, K, T, I> {
         }
     }
 
+    /// Enables pruning, see `SnapshotMap::with_pruning`
+    pub fn with_pruning(self, pruned_key: &'a str) -> Self {
+        IndexedSnapshotMap {
+            primary: self.primary.with_pruning(pruned_key),
+            ..self
+        }
+    }
+
     pub fn changelog(&self) -> &Map<'a, (K, u64), ChangeSet<T>> {
         self.primary.changelog()
     }
 }
 
-impl<'a, K, T, I> IndexedSnapshotMap<'a, K, T, I>
+impl<'a, K, T, I> IndexedSnapshotMap<'a, K, T, I> {
+    /// Keeps only the last `blocks` blocks of history, see `SnapshotMap::with_retention`
+    pub fn with_retention(self, pruned_key: &'a str, blocks: u64) -> Self {
+        IndexedSnapshotMap {
+            primary: self.primary.with_retention(pruned_key, blocks),
+            ..self
+        }
+    }
+}
+
+impl<'a, K, T, I> IndexedSnapshotMap<'a, K, T, I, Timestamp> {
+    /// Keeps only the history of the last `period` of block time,
+    /// see `SnapshotMap::with_time_retention`
+    pub fn with_time_retention(self, pruned_key: &'a str, period: Duration) -> Self {
+        IndexedSnapshotMap {
+            primary: self.primary.with_time_retention(pruned_key, period),
+            ..self
+        }
+    }
+}
+
+impl<'a, K, T, I, D> IndexedSnapshotMap<'a, K, T, I, D>
 where
+    D: CheckpointDimension,
     T: Serialize + DeserializeOwned + Clone,
     K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
     I: IndexList<T>,
 {
-    pub fn add_checkpoint(&self, store: &mut dyn Storage, height: u64) -> StdResult<()> {
+    pub fn add_checkpoint(&self, store: &mut dyn Storage, height: D) -> StdResult<()> {
         self.primary.add_checkpoint(store, height)
     }
 
-    pub fn remove_checkpoint(&self, store: &mut dyn Storage, height: u64) -> StdResult<()> {
+    pub fn remove_checkpoint(&self, store: &mut dyn Storage, height: D) -> StdResult<()> {
         self.primary.remove_checkpoint(store, height)
     }
 
-    pub fn may_load_at_height(
+    pub fn prune(
         &self,
-        store: &dyn Storage,
-        k: K,
-        height: u64,
-    ) -> StdResult<Option<T>> {
+        store: &mut dyn Storage,
+        before_height: D,
+        limit: Option<usize>,
+    ) -> StdResult<usize> {
+        self.primary.prune(store, before_height, limit)
+    }
+
+    pub fn may_load_at_height(&self, store: &dyn Storage, k: K, height: D) -> StdResult<Option<T>> {
         self.primary.may_load_at_height(store, k, height)
     }
 
-    pub fn assert_checkpointed(&self, store: &dyn Storage, height: u64) -> StdResult<()> {
+    pub fn assert_checkpointed(&self, store: &dyn Storage, height: D) -> StdResult<()> {
         self.primary.assert_checkpointed(store, height)
     }
 
@@ -95,8 +133,40 @@ where
     }
 }
 
-impl<'a, K, T, I> IndexedSnapshotMap<'a, K, T, I>
+impl<'a, K, T, I> IndexedSnapshotMap<'a, K, T, I, Timestamp>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+    I: IndexList<T>,
+{
+    pub fn may_load_at_time(
+        &self,
+        store: &dyn Storage,
+        k: K,
+        time: Timestamp,
+    ) -> StdResult<Option<T>> {
+        self.primary.may_load_at_time(store, k, time)
+    }
+
+    pub fn range_at_time<'c>(
+        &self,
+        store: &'c dyn Storage,
+        time: Timestamp,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: cosmwasm_std::Order,
+    ) -> HistoricalRange<'c, (K::Output, T)>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.primary.range_at_time(store, time, min, max, order)
+    }
+}
+
+impl<'a, K, T, I, D> IndexedSnapshotMap<'a, K, T, I, D>
 where
+    D: CheckpointDimension,
     K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
     T: Serialize + DeserializeOwned + Clone,
     I: IndexList<T>,
@@ -104,12 +174,12 @@ where
     /// save will serialize the model and store, returns an error on serialization issues.
     /// this must load the old value to update the indexes properly
     /// if you loaded the old value earlier in the same function, use replace to avoid needless db reads
-    pub fn save(&self, store: &mut dyn Storage, key: K, data: &T, height: u64) -> StdResult<()> {
+    pub fn save(&self, store: &mut dyn Storage, key: K, data: &T, height: D) -> StdResult<()> {
         let old_data = self.may_load(store, key.clone())?;
         self.replace(store, key, Some(data), old_data.as_ref(), height)
     }
 
-    pub fn remove(&self, store: &mut dyn Storage, key: K, height: u64) -> StdResult<()> {
+    pub fn remove(&self, store: &mut dyn Storage, key: K, height: D) -> StdResult<()> {
         let old_data = self.may_load(store, key.clone())?;
         self.replace(store, key, None, old_data.as_ref(), height)
     }
@@ -123,19 +193,19 @@ where
         key: K,
         data: Option<&T>,
         old_data: Option<&T>,
-        height: u64,
+        height: D,
     ) -> StdResult<()> {
         // this is the key *relative* to the primary map namespace
         let pk = key.joined_key();
-        if let Some(old) = old_data {
-            for index in self.idx.get_indexes() {
-                index.remove(store, &pk, old)?;
+        for index in self.idx.get_indexes() {
+            match (old_data, data) {
+                (Some(old), Some(updated)) => index.update(store, &pk, old, updated)?,
+                (Some(old), None) => index.remove(store, &pk, old)?,
+                (None, Some(updated)) => index.save(store, &pk, updated)?,
+                (None, None) => {}
             }
         }
         if let Some(updated) = data {
-            for index in self.idx.get_indexes() {
-                index.save(store, &pk, updated)?;
-            }
             self.primary.save(store, key, updated, height)?;
         } else {
             self.primary.remove(store, key, height)?;
@@ -151,7 +221,7 @@ where
         &self,
         store: &mut dyn Storage,
         key: K,
-        height: u64,
+        height: D,
         action: A,
     ) -> Result<T, E>
     where
@@ -186,8 +256,9 @@ where
 }
 
 // short-cut for simple keys, rather than .prefix(()).range_raw(...)
-impl<'a, K, T, I> IndexedSnapshotMap<'a, K, T, I>
+impl<'a, K, T, I, D> IndexedSnapshotMap<'a, K, T, I, D>
 where
+    D: CheckpointDimension,
     K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
     T: Serialize + DeserializeOwned + Clone,
     I: IndexList<T>,
@@ -219,8 +290,9 @@ where
 }
 
 #[cfg(feature = "iterator")]
-impl<'a, K, T, I> IndexedSnapshotMap<'a, K, T, I>
+impl<'a, K, T, I, D> IndexedSnapshotMap<'a, K, T, I, D>
 where
+    D: CheckpointDimension,
     T: Serialize + DeserializeOwned + Clone,
     K: PrimaryKey<'a>,
     I: IndexList<T>,
@@ -235,8 +307,9 @@ where
 }
 
 #[cfg(feature = "iterator")]
-impl<'a, K, T, I> IndexedSnapshotMap<'a, K, T, I>
+impl<'a, K, T, I, D> IndexedSnapshotMap<'a, K, T, I, D>
 where
+    D: CheckpointDimension,
     T: Serialize + DeserializeOwned + Clone,
     K: PrimaryKey<'a> + KeyDeserialize,
     I: IndexList<T>,
@@ -298,6 +371,76 @@ where
     }
 }
 
+// historical queries over the primary data; indexes only reflect the current state
+impl<'a, K, T, I, D> IndexedSnapshotMap<'a, K, T, I, D>
+where
+    D: CheckpointDimension,
+    T: Serialize + DeserializeOwned + Clone,
+    K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+    I: IndexList<T>,
+{
+    pub fn range_raw_at_height<'c>(
+        &self,
+        store: &'c dyn Storage,
+        height: D,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: cosmwasm_std::Order,
+    ) -> HistoricalRange<'c, Record<T>>
+    where
+        T: 'c,
+    {
+        self.primary
+            .range_raw_at_height(store, height, min, max, order)
+    }
+
+    pub fn range_at_height<'c>(
+        &self,
+        store: &'c dyn Storage,
+        height: D,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: cosmwasm_std::Order,
+    ) -> HistoricalRange<'c, (K::Output, T)>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.primary.range_at_height(store, height, min, max, order)
+    }
+
+    pub fn keys_at_height<'c>(
+        &self,
+        store: &'c dyn Storage,
+        height: D,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: cosmwasm_std::Order,
+    ) -> HistoricalRange<'c, K::Output>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.primary.keys_at_height(store, height, min, max, order)
+    }
+
+    pub fn prefix_range_at_height<'c>(
+        &self,
+        store: &'c dyn Storage,
+        height: D,
+        min: Option<PrefixBound<'a, K::Prefix>>,
+        max: Option<PrefixBound<'a, K::Prefix>>,
+        order: cosmwasm_std::Order,
+    ) -> HistoricalRange<'c, (K::Output, T)>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.primary
+            .prefix_range_at_height(store, height, min, max, order)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
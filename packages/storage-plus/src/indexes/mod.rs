@@ -1,9 +1,11 @@
 // this module requires iterator to be useful at all
 #![cfg(feature = "iterator")]
 mod multi;
+mod multi_value;
 mod unique;
 
 pub use multi::MultiIndex;
+pub use multi_value::MultiValueIndex;
 pub use unique::UniqueIndex;
 
 use serde::de::DeserializeOwned;
@@ -19,6 +21,13 @@ where
 {
     fn save(&self, store: &mut dyn Storage, pk: &[u8], data: &T) -> StdResult<()>;
     fn remove(&self, store: &mut dyn Storage, pk: &[u8], old_data: &T) -> StdResult<()>;
+
+    /// Replaces the index entries of `old_data` with the ones of `data`, for the same primary
+    /// key. Indexes can override it to only write the entries that changed.
+    fn update(&self, store: &mut dyn Storage, pk: &[u8], old_data: &T, data: &T) -> StdResult<()> {
+        self.remove(store, pk, old_data)?;
+        self.save(store, pk, data)
+    }
 }
 
 #[cfg(test)]
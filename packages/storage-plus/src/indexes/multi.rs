@@ -4,15 +4,18 @@
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use cosmwasm_std::{from_slice, Order, Record, StdError, StdResult, Storage};
+use cosmwasm_std::{from_slice, Binary, Order, Record, StdError, StdResult, Storage};
 
 use crate::bound::PrefixBound;
 use crate::de::KeyDeserialize;
 use crate::helpers::namespaces_with_key;
 use crate::iter_helpers::deserialize_kv;
 use crate::map::Map;
+use crate::paginate::{Page, Paginator};
 use crate::prefix::namespaced_prefix_range;
+use crate::range_query::{IndexPk, RangeQuery};
 use crate::{Bound, Index, Prefix, Prefixer, PrimaryKey};
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
 
 /// MultiIndex stores (namespace, index_name, idx_value, pk) -> b"pk_len".
@@ -28,10 +31,14 @@ use std::marker::PhantomData;
 /// This type must match the encompassing `IndexedMap` primary key type,
 /// or its owned variant.
 pub struct MultiIndex<'a, IK, T, PK> {
-    index: fn(&T) -> IK,
+    // `None` when the index entries are written by another index, through `save_key` and
+    // `remove_key`
+    index: Option<fn(&T) -> IK>,
     idx_namespace: &'a [u8],
     // note, we collapse the ik - combining everything under the namespace - and concatenating the pk
     idx_map: Map<'a, Vec<u8>, u32>,
+    // optional number of entries per index value, see `with_counters`
+    counters: Option<Map<'a, Vec<u8>, u64>>,
     pk_namespace: &'a [u8],
     phantom: PhantomData<PK>,
 }
@@ -67,13 +74,33 @@ where
     /// ```
     pub fn new(idx_fn: fn(&T) -> IK, pk_namespace: &'a str, idx_namespace: &'a str) -> Self {
         MultiIndex {
-            index: idx_fn,
+            index: Some(idx_fn),
+            ..Self::without_index_fn(pk_namespace, idx_namespace)
+        }
+    }
+
+    /// The storage and queries of a `MultiIndex`, whose entries are written with `save_key` and
+    /// `remove_key`
+    pub(crate) fn without_index_fn(pk_namespace: &'a str, idx_namespace: &'a str) -> Self {
+        MultiIndex {
+            index: None,
             idx_namespace: idx_namespace.as_bytes(),
             idx_map: Map::new(idx_namespace),
+            counters: None,
             pk_namespace: pk_namespace.as_bytes(),
             phantom: PhantomData,
         }
     }
+
+    /// Keeps the number of entries per index value under the given namespace, updated on every
+    /// write, so `count_prefix` is O(1) rather than iterating over the entries of the value.
+    ///
+    /// Counters enabled on an index that already has entries must be set up once with
+    /// `rebuild_counters`, for example in `migrate`.
+    pub fn with_counters(mut self, counters_namespace: &'a str) -> Self {
+        self.counters = Some(Map::new(counters_namespace));
+        self
+    }
 }
 
 fn deserialize_multi_v<T: DeserializeOwned>(
@@ -131,15 +158,84 @@ where
     IK: PrimaryKey<'a>,
 {
     fn save(&self, store: &mut dyn Storage, pk: &[u8], data: &T) -> StdResult<()> {
-        let idx = (self.index)(data).joined_extra_key(pk);
-        self.idx_map.save(store, idx, &(pk.len() as u32))
+        match self.index {
+            Some(index) => self.save_key(store, pk, index(data)),
+            None => Ok(()),
+        }
     }
 
     fn remove(&self, store: &mut dyn Storage, pk: &[u8], old_data: &T) -> StdResult<()> {
-        let idx = (self.index)(old_data).joined_extra_key(pk);
+        match self.index {
+            Some(index) => self.remove_key(store, pk, index(old_data)),
+            None => Ok(()),
+        }
+    }
+
+    fn update(&self, store: &mut dyn Storage, pk: &[u8], old_data: &T, data: &T) -> StdResult<()> {
+        let index = match self.index {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        let (old, new) = (index(old_data), index(data));
+        // the index entry only depends on the index value and primary key
+        if old.joined_key() != new.joined_key() {
+            self.remove_key(store, pk, old)?;
+            self.save_key(store, pk, new)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, IK, T, PK> MultiIndex<'a, IK, T, PK>
+where
+    IK: PrimaryKey<'a>,
+{
+    /// Adds the primary key under the given index value
+    pub(crate) fn save_key(&self, store: &mut dyn Storage, pk: &[u8], ik: IK) -> StdResult<()> {
+        if let Some(counters) = &self.counters {
+            counters.update::<_, StdError>(store, ik.joined_extra_key(b""), |count| {
+                Ok(count.unwrap_or_default() + 1)
+            })?;
+        }
+        let idx = ik.joined_extra_key(pk);
+        self.idx_map.save(store, idx, &(pk.len() as u32))
+    }
+
+    /// Removes the primary key from under the given index value
+    pub(crate) fn remove_key(&self, store: &mut dyn Storage, pk: &[u8], ik: IK) -> StdResult<()> {
+        if let Some(counters) = &self.counters {
+            let key = ik.joined_extra_key(b"");
+            match counters.may_load(store, key.clone())?.unwrap_or_default() {
+                // drop the counter of values no longer in use
+                0 | 1 => counters.remove(store, key),
+                count => counters.save(store, key, &(count - 1))?,
+            }
+        }
+        let idx = ik.joined_extra_key(pk);
         self.idx_map.remove(store, idx);
         Ok(())
     }
+
+    /// Recomputes the counters from the index entries, see `with_counters`. This iterates over
+    /// the whole index, so it's meant to be called once, when enabling the counters.
+    pub fn rebuild_counters(&self, store: &mut dyn Storage) -> StdResult<()> {
+        let counters = match &self.counters {
+            Some(counters) => counters,
+            None => return Err(StdError::generic_err("Counters are not enabled")),
+        };
+        // index keys are the index value followed by the primary key, of the stored length
+        let mut counts = BTreeMap::<_, u64>::new();
+        for entry in self.idx_map.range_raw(store, None, None, Order::Ascending) {
+            let (key, pk_len) = entry?;
+            let value = key[..key.len() - pk_len as usize].to_vec();
+            *counts.entry(value).or_default() += 1;
+        }
+        counters.clear(store);
+        for (value, count) in counts {
+            counters.save(store, value, &count)?;
+        }
+        Ok(())
+    }
 }
 
 impl<'a, IK, T, PK> MultiIndex<'a, IK, T, PK>
@@ -168,6 +264,22 @@ where
         k.joined_extra_key(b"")
     }
 
+    /// Returns the number of entries `prefix(value)` iterates over, i.e. the entries with
+    /// exactly the given index value. The leading elements of a composite value are not
+    /// counted on their own. This is O(1) when the index was created `with_counters`,
+    /// otherwise it iterates over the entries.
+    pub fn count_prefix(&self, store: &dyn Storage, value: IK) -> StdResult<u64> {
+        match &self.counters {
+            Some(counters) => Ok(counters
+                .may_load(store, value.joined_extra_key(b""))?
+                .unwrap_or_default()),
+            None => Ok(self
+                .prefix(value)
+                .keys_raw(store, None, None, Order::Ascending)
+                .count() as u64),
+        }
+    }
+
     #[cfg(test)]
     pub fn count(&self, store: &dyn Storage, p: IK) -> usize {
         let prefix = self.prefix(p);
@@ -244,6 +356,16 @@ where
             .map(move |kv| (deserialize_multi_v)(store, self.pk_namespace, kv));
         Box::new(mapped)
     }
+
+    /// Starts building a range over the index values and primary keys, returning the primary
+    /// keys, see `RangeQuery`. Prefix bounds apply to all the primary keys of an index value.
+    pub fn range_query(&self) -> RangeQuery<(IK, PK), T, IndexPk<PK>> {
+        RangeQuery::new(
+            self.idx_namespace,
+            self.pk_namespace,
+            deserialize_multi_v::<T>,
+        )
+    }
 }
 
 #[cfg(feature = "iterator")]
@@ -334,6 +456,20 @@ where
         self.no_prefix().keys(store, min, max, order)
     }
 
+    /// Returns the page of entries after the `cursor` of the previous page, ordered by index
+    /// value and then primary key. The cursor holds both, see `Paginator`
+    pub fn paginate(
+        &self,
+        store: &dyn Storage,
+        paginator: &Paginator,
+        cursor: Option<Binary>,
+        limit: Option<u32>,
+        order: Order,
+    ) -> StdResult<Page<(PK::Output, T)>> {
+        self.no_prefix()
+            .paginate(store, paginator, cursor, limit, order)
+    }
+
     fn no_prefix(&self) -> Prefix<PK, T, (IK, PK)> {
         Prefix::with_deserialization_functions(
             self.idx_namespace,
@@ -0,0 +1,247 @@
+// this module requires iterator to be useful at all
+#![cfg(feature = "iterator")]
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use cosmwasm_std::{Binary, Order, Record, StdResult, Storage};
+
+use crate::bound::PrefixBound;
+use crate::de::KeyDeserialize;
+use crate::paginate::{Page, Paginator};
+use crate::{Bound, Index, MultiIndex, Prefix, Prefixer, PrimaryKey};
+use std::collections::BTreeMap;
+
+/// MultiValueIndex works like a `MultiIndex`, but the index function returns any number of
+/// index values per entry (tags, owners, holders), and the entry is indexed under each of them.
+///
+/// It uses the same storage layout as `MultiIndex`:
+/// (namespace, index_name, idx_value, pk) -> b"pk_len", and offers the same queries.
+pub struct MultiValueIndex<'a, IK, T, PK> {
+    index: fn(&T) -> Vec<IK>,
+    keys: MultiIndex<'a, IK, T, PK>,
+}
+
+impl<'a, IK, T, PK> MultiValueIndex<'a, IK, T, PK>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    /// Create a new MultiValueIndex
+    ///
+    /// idx_fn - lambda creating the index keys from value
+    /// pk_namespace - prefix for the primary key
+    /// idx_namespace - prefix for the index value
+    ///
+    /// ## Example:
+    ///
+    /// ```rust
+    /// use cw_storage_plus::MultiValueIndex;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Deserialize, Serialize, Clone)]
+    /// struct Post {
+    ///     pub title: String,
+    ///     pub tags: Vec<String>,
+    /// }
+    ///
+    /// let index: MultiValueIndex<_, _, String> = MultiValueIndex::new(
+    ///     |p: &Post| p.tags.clone(),
+    ///     "posts",
+    ///     "posts__tags",
+    /// );
+    /// ```
+    pub fn new(idx_fn: fn(&T) -> Vec<IK>, pk_namespace: &'a str, idx_namespace: &'a str) -> Self {
+        MultiValueIndex {
+            index: idx_fn,
+            // only stores and queries the index values given by `index`
+            keys: MultiIndex::without_index_fn(pk_namespace, idx_namespace),
+        }
+    }
+
+    /// Keeps the number of entries per index value, see `MultiIndex::with_counters`
+    pub fn with_counters(self, counters_namespace: &'a str) -> Self {
+        MultiValueIndex {
+            index: self.index,
+            keys: self.keys.with_counters(counters_namespace),
+        }
+    }
+}
+
+impl<'a, IK, T, PK> MultiValueIndex<'a, IK, T, PK>
+where
+    IK: PrimaryKey<'a>,
+{
+    /// The distinct index values of the entry, by key, so repeated values are indexed only once
+    fn index_values(&self, data: &T) -> BTreeMap<Vec<u8>, IK> {
+        (self.index)(data)
+            .into_iter()
+            .map(|ik| (ik.joined_key(), ik))
+            .collect()
+    }
+}
+
+impl<'a, IK, T, PK> Index<T> for MultiValueIndex<'a, IK, T, PK>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    IK: PrimaryKey<'a>,
+{
+    fn save(&self, store: &mut dyn Storage, pk: &[u8], data: &T) -> StdResult<()> {
+        for ik in self.index_values(data).into_values() {
+            self.keys.save_key(store, pk, ik)?;
+        }
+        Ok(())
+    }
+
+    fn remove(&self, store: &mut dyn Storage, pk: &[u8], old_data: &T) -> StdResult<()> {
+        for ik in self.index_values(old_data).into_values() {
+            self.keys.remove_key(store, pk, ik)?;
+        }
+        Ok(())
+    }
+
+    /// Only removes the values no longer in the entry, and adds the new ones
+    fn update(&self, store: &mut dyn Storage, pk: &[u8], old_data: &T, data: &T) -> StdResult<()> {
+        let mut old = self.index_values(old_data);
+        let mut new = self.index_values(data);
+        old.retain(|key, _| new.remove(key).is_none());
+        for ik in old.into_values() {
+            self.keys.remove_key(store, pk, ik)?;
+        }
+        for ik in new.into_values() {
+            self.keys.save_key(store, pk, ik)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, IK, T, PK> MultiValueIndex<'a, IK, T, PK>
+where
+    PK: PrimaryKey<'a> + KeyDeserialize,
+    T: Serialize + DeserializeOwned + Clone,
+    IK: PrimaryKey<'a> + Prefixer<'a>,
+{
+    pub fn index_key(&self, k: IK) -> Vec<u8> {
+        self.keys.index_key(k)
+    }
+
+    /// Returns the number of entries `prefix(value)` iterates over, see `MultiIndex::count_prefix`
+    pub fn count_prefix(&self, store: &dyn Storage, value: IK) -> StdResult<u64> {
+        self.keys.count_prefix(store, value)
+    }
+
+    /// Recomputes the counters from the index entries, see `MultiIndex::rebuild_counters`
+    pub fn rebuild_counters(&self, store: &mut dyn Storage) -> StdResult<()> {
+        self.keys.rebuild_counters(store)
+    }
+
+    pub fn prefix(&self, p: IK) -> Prefix<PK, T, PK> {
+        self.keys.prefix(p)
+    }
+
+    pub fn sub_prefix(&self, p: IK::Prefix) -> Prefix<PK, T, (IK::Suffix, PK)> {
+        self.keys.sub_prefix(p)
+    }
+}
+
+// short-cut for simple keys, rather than .prefix(()).range_raw(...)
+impl<'a, IK, T, PK> MultiValueIndex<'a, IK, T, PK>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    IK: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+    PK: PrimaryKey<'a> + KeyDeserialize,
+{
+    pub fn range_raw<'c>(
+        &'c self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, (IK, PK)>>,
+        max: Option<Bound<'a, (IK, PK)>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<Record<T>>> + 'c>
+    where
+        T: 'c,
+    {
+        self.keys.range_raw(store, min, max, order)
+    }
+
+    pub fn keys_raw<'c>(
+        &'c self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, (IK, PK)>>,
+        max: Option<Bound<'a, (IK, PK)>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + 'c> {
+        self.keys.keys_raw(store, min, max, order)
+    }
+
+    pub fn prefix_range_raw<'c>(
+        &'c self,
+        store: &'c dyn Storage,
+        min: Option<PrefixBound<'a, IK>>,
+        max: Option<PrefixBound<'a, IK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<Record<T>>> + 'c>
+    where
+        T: 'c,
+        'a: 'c,
+    {
+        self.keys.prefix_range_raw(store, min, max, order)
+    }
+
+    pub fn prefix_range<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<PrefixBound<'a, IK>>,
+        max: Option<PrefixBound<'a, IK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(PK::Output, T)>> + 'c>
+    where
+        T: 'c,
+        'a: 'c,
+        IK: 'c,
+        PK: 'c,
+        PK::Output: 'static,
+    {
+        self.keys.prefix_range(store, min, max, order)
+    }
+
+    pub fn range<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, (IK, PK)>>,
+        max: Option<Bound<'a, (IK, PK)>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(PK::Output, T)>> + 'c>
+    where
+        T: 'c,
+        PK::Output: 'static,
+    {
+        self.keys.range(store, min, max, order)
+    }
+
+    pub fn keys<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, (IK, PK)>>,
+        max: Option<Bound<'a, (IK, PK)>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<PK::Output>> + 'c>
+    where
+        T: 'c,
+        PK::Output: 'static,
+    {
+        self.keys.keys(store, min, max, order)
+    }
+
+    /// Returns the page of entries after the `cursor` of the previous page, see
+    /// `MultiIndex::paginate`
+    pub fn paginate(
+        &self,
+        store: &dyn Storage,
+        paginator: &Paginator,
+        cursor: Option<Binary>,
+        limit: Option<u32>,
+        order: Order,
+    ) -> StdResult<Page<(PK::Output, T)>> {
+        self.keys.paginate(store, paginator, cursor, limit, order)
+    }
+}
@@ -12,6 +12,7 @@ use crate::bound::PrefixBound;
 use crate::de::KeyDeserialize;
 use crate::iter_helpers::deserialize_kv;
 use crate::map::Map;
+use crate::paginate::{Page, Paginator};
 use crate::prefix::namespaced_prefix_range;
 use crate::{Bound, Index, Prefix, Prefixer, PrimaryKey};
 
@@ -245,6 +246,20 @@ where
         )
     }
 
+    /// Returns the page of entries after the `cursor` of the previous page, ordered by index
+    /// value, see `Paginator`
+    pub fn paginate(
+        &self,
+        store: &dyn Storage,
+        paginator: &Paginator,
+        cursor: Option<Binary>,
+        limit: Option<u32>,
+        order: Order,
+    ) -> StdResult<Page<(PK::Output, T)>> {
+        self.no_prefix()
+            .paginate(store, paginator, cursor, limit, order)
+    }
+
     fn no_prefix(&self) -> Prefix<PK, T, IK> {
         Prefix::with_deserialization_functions(
             self.idx_namespace,
@@ -0,0 +1,283 @@
+//! Decoding of raw contract state, as returned by `WasmQuery::Raw` or exported state dumps.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::type_name;
+
+use cosmwasm_std::{from_slice, to_vec, StdError, StdResult};
+
+use crate::de::KeyDeserialize;
+use crate::helpers::namespaces_with_key;
+
+/// A raw state entry, decoded with the storage types registered in a `StateDecoder`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedEntry {
+    /// Storage key of the item, or namespace of the map
+    pub namespace: String,
+    /// The map key components, as JSON. `None` for an item
+    pub key: Option<String>,
+    /// The value, as JSON
+    pub value: String,
+}
+
+/// Parses a raw key (without the namespace) and value into their JSON
+type DecodeFn = fn(&[u8], &[u8]) -> StdResult<(Option<String>, String)>;
+
+struct Registered {
+    namespace: String,
+    /// `None` for an item, the length-prefixed namespace of a map otherwise
+    map_prefix: Option<Vec<u8>>,
+    decode: DecodeFn,
+}
+
+/// A registry of the storage items and maps declared by a contract, with their key and value
+/// types, to decode its raw state.
+///
+/// ## Example:
+///
+/// ```rust
+/// use cosmwasm_std::{testing::MockStorage, Addr, Uint128};
+/// use cw_storage_plus::{Item, Map, StateDecoder};
+///
+/// const OWNER: Item<Addr> = Item::new("owner");
+/// const BALANCES: Map<&Addr, Uint128> = Map::new("balance");
+///
+/// let mut store = MockStorage::new();
+/// let owner = Addr::unchecked("owner");
+/// OWNER.save(&mut store, &owner).unwrap();
+/// BALANCES.save(&mut store, &owner, &Uint128::new(100)).unwrap();
+///
+/// let decoder = StateDecoder::new()
+///     .item::<Addr>("owner")
+///     .map::<&Addr, Uint128>("balance");
+///
+/// let raw_key = BALANCES.key(&owner).to_vec();
+/// let entry = decoder.decode(&raw_key, br#""100""#).unwrap();
+/// assert_eq!(entry.namespace, "balance");
+/// assert_eq!(entry.key.unwrap(), r#""owner""#);
+/// assert_eq!(entry.value, r#""100""#);
+/// ```
+#[derive(Default)]
+pub struct StateDecoder {
+    entries: Vec<Registered>,
+}
+
+impl StateDecoder {
+    pub fn new() -> Self {
+        StateDecoder::default()
+    }
+
+    /// Registers an `Item` stored under the given key
+    pub fn item<T>(mut self, storage_key: &str) -> Self
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        self.entries.push(Registered {
+            namespace: storage_key.to_string(),
+            map_prefix: None,
+            decode: decode_item::<T>,
+        });
+        self
+    }
+
+    /// Registers a `Map` (or the primary map of an `IndexedMap`) stored under the given namespace
+    pub fn map<K, T>(mut self, namespace: &str) -> Self
+    where
+        K: KeyDeserialize,
+        K::Output: Serialize,
+        T: Serialize + DeserializeOwned,
+    {
+        self.entries.push(Registered {
+            namespace: namespace.to_string(),
+            map_prefix: Some(namespaces_with_key(&[namespace.as_bytes()], b"")),
+            decode: decode_map_entry::<K, T>,
+        });
+        self
+    }
+
+    /// Decodes a raw state entry. Fails if the key doesn't belong to any registered storage type,
+    /// or if the key or value don't parse as its types.
+    pub fn decode(&self, key: &[u8], value: &[u8]) -> StdResult<DecodedEntry> {
+        // items are checked first, as their keys are not length-prefixed
+        let item = self
+            .entries
+            .iter()
+            .find(|e| e.map_prefix.is_none() && e.namespace.as_bytes() == key)
+            .map(|e| (e, key));
+        let map = || {
+            self.entries.iter().find_map(|e| {
+                let prefix = e.map_prefix.as_ref()?;
+                key.strip_prefix(prefix.as_slice()).map(|k| (e, k))
+            })
+        };
+        let (entry, key) = item.or_else(map).ok_or_else(|| {
+            StdError::generic_err(format!(
+                "No registered storage type for key {}",
+                String::from_utf8_lossy(key)
+            ))
+        })?;
+        let (key, value) = (entry.decode)(key, value)?;
+        Ok(DecodedEntry {
+            namespace: entry.namespace.clone(),
+            key,
+            value,
+        })
+    }
+
+    /// Decodes all the entries of a raw state dump, like the one of `App::dump_wasm_raw`
+    pub fn decode_all<'r, I>(&self, records: I) -> StdResult<Vec<DecodedEntry>>
+    where
+        I: IntoIterator<Item = &'r (Vec<u8>, Vec<u8>)>,
+    {
+        records
+            .into_iter()
+            .map(|(key, value)| self.decode(key, value))
+            .collect()
+    }
+}
+
+fn to_json<T: Serialize>(data: &T) -> StdResult<String> {
+    String::from_utf8(to_vec(data)?).map_err(StdError::from)
+}
+
+fn decode_item<T>(_key: &[u8], value: &[u8]) -> StdResult<(Option<String>, String)>
+where
+    T: Serialize + DeserializeOwned,
+{
+    Ok((None, to_json(&from_slice::<T>(value)?)?))
+}
+
+fn decode_map_entry<K, T>(key: &[u8], value: &[u8]) -> StdResult<(Option<String>, String)>
+where
+    K: KeyDeserialize,
+    K::Output: Serialize,
+    T: Serialize + DeserializeOwned,
+{
+    let key = K::from_slice(key)
+        .map_err(|err| StdError::parse_err(type_name::<K>(), format!("invalid key: {}", err)))?;
+    Ok((Some(to_json(&key)?), to_json(&from_slice::<T>(value)?)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Item, Map};
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::{Addr, Storage, Uint128};
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Config {
+        pub owner: Addr,
+        pub max_tokens: u32,
+    }
+
+    const CONFIG: Item<Config> = Item::new("config");
+    const BALANCES: Map<&Addr, Uint128> = Map::new("balance");
+    const ALLOWANCES: Map<(&Addr, &Addr), Uint128> = Map::new("allowance");
+    const COUNTERS: Map<u64, u32> = Map::new("c");
+
+    fn decoder() -> StateDecoder {
+        StateDecoder::new()
+            .item::<Config>("config")
+            .map::<&Addr, Uint128>("balance")
+            .map::<(&Addr, &Addr), Uint128>("allowance")
+            .map::<u64, u32>("c")
+    }
+
+    #[test]
+    fn decodes_items_and_maps() {
+        let mut store = MockStorage::new();
+        let (john, jim) = (Addr::unchecked("john"), Addr::unchecked("jim"));
+        CONFIG
+            .save(
+                &mut store,
+                &Config {
+                    owner: john.clone(),
+                    max_tokens: 3,
+                },
+            )
+            .unwrap();
+        BALANCES
+            .save(&mut store, &john, &Uint128::new(100))
+            .unwrap();
+        ALLOWANCES
+            .save(&mut store, (&john, &jim), &Uint128::new(20))
+            .unwrap();
+        COUNTERS.save(&mut store, 1234, &7).unwrap();
+
+        let decoder = decoder();
+        let raw = store.get(CONFIG.as_slice()).unwrap();
+        assert_eq!(
+            decoder.decode(CONFIG.as_slice(), &raw).unwrap(),
+            DecodedEntry {
+                namespace: "config".to_string(),
+                key: None,
+                value: r#"{"owner":"john","max_tokens":3}"#.to_string(),
+            }
+        );
+
+        let key = ALLOWANCES.key((&john, &jim));
+        let raw = store.get(&key).unwrap();
+        assert_eq!(
+            decoder.decode(&key, &raw).unwrap(),
+            DecodedEntry {
+                namespace: "allowance".to_string(),
+                key: Some(r#"["john","jim"]"#.to_string()),
+                value: r#""20""#.to_string(),
+            }
+        );
+
+        let key = COUNTERS.key(1234);
+        let raw = store.get(&key).unwrap();
+        let entry = decoder.decode(&key, &raw).unwrap();
+        assert_eq!(entry.namespace, "c");
+        assert_eq!(entry.key.unwrap(), "1234");
+        assert_eq!(entry.value, "7");
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn decodes_state_dump() {
+        use cosmwasm_std::Order;
+
+        let mut store = MockStorage::new();
+        let john = Addr::unchecked("john");
+        BALANCES
+            .save(&mut store, &john, &Uint128::new(100))
+            .unwrap();
+        COUNTERS.save(&mut store, 1, &7).unwrap();
+        COUNTERS.save(&mut store, 2, &8).unwrap();
+
+        let dump: Vec<_> = store.range(None, None, Order::Ascending).collect();
+        let entries = decoder().decode_all(&dump).unwrap();
+        let keys: Vec<_> = entries
+            .iter()
+            .map(|e| (e.namespace.as_str(), e.key.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("c", "1".to_string()),
+                ("c", "2".to_string()),
+                ("balance", r#""john""#.to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_fails_on_unknown_or_invalid_entries() {
+        let decoder = decoder();
+        let err = decoder.decode(b"unknown", b"{}").unwrap_err();
+        assert!(matches!(err, StdError::GenericErr { .. }));
+
+        // wrong value type
+        let key = BALANCES.key(&Addr::unchecked("john"));
+        assert!(decoder.decode(&key, b"[1]").is_err());
+        // wrong key length
+        let mut key = COUNTERS.key(1).to_vec();
+        key.pop();
+        let err = decoder.decode(&key, b"7").unwrap_err();
+        assert!(matches!(err, StdError::ParseErr { .. }));
+    }
+}
@@ -2,34 +2,44 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::marker::PhantomData;
 
-use cosmwasm_std::{
-    to_vec, Addr, CustomQuery, QuerierWrapper, StdError, StdResult, Storage, WasmQuery,
-};
+use cosmwasm_std::{Addr, CustomQuery, QuerierWrapper, StdError, StdResult, Storage};
 
-use crate::helpers::{may_deserialize, must_deserialize};
+use crate::codec::{Codec, Json};
+use crate::helpers::{may_decode, must_decode, query_raw};
 
 /// Item stores one typed item at the given key.
 /// This is an analog of Singleton.
 /// It functions the same way as Path does but doesn't use a Vec and thus has a const fn constructor.
-pub struct Item<'a, T> {
+#[derive(Debug, Clone)]
+pub struct Item<'a, T, C = Json> {
     // this is full key - no need to length-prefix it, we only store one item
     storage_key: &'a [u8],
     // see https://doc.rust-lang.org/std/marker/struct.PhantomData.html#unused-type-parameters for why this is needed
     data_type: PhantomData<T>,
+    codec: PhantomData<C>,
 }
 
 impl<'a, T> Item<'a, T> {
     pub const fn new(storage_key: &'a str) -> Self {
+        Item::with_codec(storage_key)
+    }
+}
+
+impl<'a, T, C> Item<'a, T, C> {
+    /// Like `new`, for an item stored with the given `Codec`
+    pub const fn with_codec(storage_key: &'a str) -> Self {
         Item {
             storage_key: storage_key.as_bytes(),
             data_type: PhantomData,
+            codec: PhantomData,
         }
     }
 }
 
-impl<'a, T> Item<'a, T>
+impl<'a, T, C> Item<'a, T, C>
 where
     T: Serialize + DeserializeOwned,
+    C: Codec,
 {
     // this gets the path of the data to use elsewhere
     pub fn as_slice(&self) -> &[u8] {
@@ -38,7 +48,7 @@ where
 
     /// save will serialize the model and store, returns an error on serialization issues
     pub fn save(&self, store: &mut dyn Storage, data: &T) -> StdResult<()> {
-        store.set(self.storage_key, &to_vec(data)?);
+        store.set(self.storage_key, &C::encode(data)?);
         Ok(())
     }
 
@@ -49,14 +59,14 @@ where
     /// load will return an error if no data is set at the given key, or on parse error
     pub fn load(&self, store: &dyn Storage) -> StdResult<T> {
         let value = store.get(self.storage_key);
-        must_deserialize(&value)
+        must_decode::<C, T>(&value)
     }
 
     /// may_load will parse the data stored at the key if present, returns `Ok(None)` if no data there.
     /// returns an error on issues parsing
     pub fn may_load(&self, store: &dyn Storage) -> StdResult<Option<T>> {
         let value = store.get(self.storage_key);
-        may_deserialize(&value)
+        may_decode::<C, T>(&value)
     }
 
     /// Loads the data, perform the specified action, and store the result
@@ -84,11 +94,8 @@ where
         querier: &QuerierWrapper<Q>,
         remote_contract: Addr,
     ) -> StdResult<T> {
-        let request = WasmQuery::Raw {
-            contract_addr: remote_contract.into(),
-            key: self.storage_key.into(),
-        };
-        querier.query(&request.into())
+        let result = query_raw(querier, remote_contract, self.storage_key.into())?;
+        C::decode(&result)
     }
 }
 
@@ -98,7 +105,7 @@ mod test {
     use cosmwasm_std::testing::MockStorage;
     use serde::{Deserialize, Serialize};
 
-    use cosmwasm_std::{OverflowError, OverflowOperation, StdError};
+    use cosmwasm_std::{to_vec, OverflowError, OverflowOperation, StdError};
 
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
     struct Config {
@@ -125,6 +132,31 @@ mod test {
         assert_eq!(cfg, CONFIG.load(&store).unwrap());
     }
 
+    #[test]
+    #[cfg(feature = "bincode")]
+    fn save_and_load_bincode() {
+        use crate::codec::Bincode;
+        const BINARY_CONFIG: Item<Config, Bincode> = Item::with_codec("binary_config");
+        let mut store = MockStorage::new();
+
+        assert!(BINARY_CONFIG.load(&store).is_err());
+        assert_eq!(BINARY_CONFIG.may_load(&store).unwrap(), None);
+
+        let cfg = Config {
+            owner: "admin".to_string(),
+            max_tokens: 1234,
+        };
+        BINARY_CONFIG.save(&mut store, &cfg).unwrap();
+        assert_eq!(cfg, BINARY_CONFIG.load(&store).unwrap());
+
+        // the owner length, the owner and max_tokens
+        let raw = store.get(BINARY_CONFIG.as_slice()).unwrap();
+        assert_eq!(raw.len(), 8 + 5 + 4);
+        // and it can't be read as json
+        const JSON_CONFIG: Item<Config> = Item::new("binary_config");
+        assert!(JSON_CONFIG.load(&store).is_err());
+    }
+
     #[test]
     fn remove_works() {
         let mut store = MockStorage::new();
@@ -3,24 +3,37 @@
 use serde::de::DeserializeOwned;
 
 use cosmwasm_std::Record;
-use cosmwasm_std::{from_slice, StdResult};
+use cosmwasm_std::StdResult;
 
+use crate::codec::{Codec, Json};
 use crate::de::KeyDeserialize;
 use crate::helpers::encode_length;
 
 #[allow(dead_code)]
 pub(crate) fn deserialize_v<T: DeserializeOwned>(kv: Record) -> StdResult<Record<T>> {
+    decode_v::<T, Json>(kv)
+}
+
+pub(crate) fn deserialize_kv<K: KeyDeserialize, T: DeserializeOwned>(
+    kv: Record,
+) -> StdResult<(K::Output, T)> {
+    decode_kv::<K, T, Json>(kv)
+}
+
+/// Like deserialize_v, for values encoded with the given codec
+pub(crate) fn decode_v<T: DeserializeOwned, C: Codec>(kv: Record) -> StdResult<Record<T>> {
     let (k, v) = kv;
-    let t = from_slice::<T>(&v)?;
+    let t = C::decode::<T>(&v)?;
     Ok((k, t))
 }
 
-pub(crate) fn deserialize_kv<K: KeyDeserialize, T: DeserializeOwned>(
+/// Like deserialize_kv, for values encoded with the given codec
+pub(crate) fn decode_kv<K: KeyDeserialize, T: DeserializeOwned, C: Codec>(
     kv: Record,
 ) -> StdResult<(K::Output, T)> {
     let (k, v) = kv;
     let kt = K::from_vec(k)?;
-    let vt = from_slice::<T>(&v)?;
+    let vt = C::decode::<T>(&v)?;
     Ok((kt, vt))
 }
 
@@ -1,4 +1,4 @@
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Decimal, Timestamp, Uint128, Uint256, Uint64};
 
 use crate::de::KeyDeserialize;
 use crate::helpers::namespaces_with_key;
@@ -12,6 +12,7 @@ pub enum Key<'a> {
     Val32([u8; 4]),
     Val64([u8; 8]),
     Val128([u8; 16]),
+    Val256([u8; 32]),
 }
 
 impl<'a> AsRef<[u8]> for Key<'a> {
@@ -23,6 +24,7 @@ impl<'a> AsRef<[u8]> for Key<'a> {
             Key::Val32(v) => v,
             Key::Val64(v) => v,
             Key::Val128(v) => v,
+            Key::Val256(v) => v,
         }
     }
 }
@@ -50,8 +52,15 @@ impl<'a> PartialEq<&[u8]> for Key<'a> {
 /// `()`: Sub-prefix.
 /// `(T, U)`: Super-suffix.
 ///
-/// `SubPrefix` and `SuperSuffix` only make real sense in the case of triples. Still, they need to be
-/// consistently defined for all types.
+/// `SubPrefix` and `SuperSuffix` only make real sense in the case of triples and larger tuples.
+/// Still, they need to be consistently defined for all types.
+///
+/// Tuples of up to 8 elements follow the triple. For a 4-tuple `(T, U, V, W)`:
+///
+/// `(T, U, V)`: Prefix.
+/// `W`: Suffix.
+/// `(T, U)`: Sub-prefix.
+/// `(V, W)`: Super-suffix.
 pub trait PrimaryKey<'a>: Clone {
     /// These associated types need to implement `Prefixer`, so that they can be useful arguments
     /// for `prefix()`, `sub_prefix()`, and their key-deserializable variants.
@@ -155,6 +164,42 @@ impl<
     }
 }
 
+// Larger tuples follow the triple: the prefix is all but the last element, the sub-prefix all but the
+// last two, and the super-suffix the last two.
+macro_rules! tuple_key {
+    ($A:ident $a:ident, $($T:ident $t:ident),+; $Y:ident $y:ident, $Z:ident $z:ident;
+     prefix ($($P:ident),+); sub_prefix ($($S:ident),+)) => {
+        impl<
+                'a,
+                $A: PrimaryKey<'a> + Prefixer<'a>,
+                $($T: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,)+
+                $Y: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+                $Z: PrimaryKey<'a> + KeyDeserialize,
+            > PrimaryKey<'a> for ($A, $($T,)+ $Y, $Z)
+        {
+            type Prefix = ($($P,)+);
+            type SubPrefix = ($($S,)+);
+            type Suffix = $Z;
+            type SuperSuffix = ($Y, $Z);
+
+            fn key(&self) -> Vec<Key<'_>> {
+                let ($a, $($t,)+ $y, $z) = self;
+                let mut keys = $a.key();
+                $(keys.extend($t.key());)+
+                keys.extend($y.key());
+                keys.extend($z.key());
+                keys
+            }
+        }
+    };
+}
+
+tuple_key!(T t, U u; V v, W w; prefix (T, U, V); sub_prefix (T, U));
+tuple_key!(T t, U u, V v; W w, X x; prefix (T, U, V, W); sub_prefix (T, U, V));
+tuple_key!(T t, U u, V v, W w; X x, Y y; prefix (T, U, V, W, X); sub_prefix (T, U, V, W));
+tuple_key!(T t, U u, V v, W w, X x; Y y, Z z; prefix (T, U, V, W, X, Y); sub_prefix (T, U, V, W, X));
+tuple_key!(T t, U u, V v, W w, X x, Y y; Z z, A a; prefix (T, U, V, W, X, Y, Z); sub_prefix (T, U, V, W, X, Y));
+
 pub trait Prefixer<'a> {
     /// returns 0 or more namespaces that should be length-prefixed and concatenated for range searches
     fn prefix(&self) -> Vec<Key>;
@@ -194,6 +239,25 @@ impl<'a, T: Prefixer<'a>, U: Prefixer<'a>, V: Prefixer<'a>> Prefixer<'a> for (T,
     }
 }
 
+macro_rules! tuple_prefix {
+    ($($T:ident $t:ident),+) => {
+        impl<'a, $($T: Prefixer<'a>),+> Prefixer<'a> for ($($T,)+) {
+            fn prefix(&self) -> Vec<Key<'_>> {
+                let ($($t,)+) = self;
+                let mut res = vec![];
+                $(res.extend($t.prefix().into_iter());)+
+                res
+            }
+        }
+    };
+}
+
+tuple_prefix!(T t, U u, V v, W w);
+tuple_prefix!(T t, U u, V v, W w, X x);
+tuple_prefix!(T t, U u, V v, W w, X x, Y y);
+tuple_prefix!(T t, U u, V v, W w, X x, Y y, Z z);
+tuple_prefix!(T t, U u, V v, W w, X x, Y y, Z z, A a);
+
 // Provide a string version of this to raw encode strings
 impl<'a> Prefixer<'a> for &'a str {
     fn prefix(&self) -> Vec<Key> {
@@ -302,6 +366,42 @@ macro_rules! integer_prefix {
 
 integer_prefix!(for i8, Val8, u8, Val8, i16, Val16, u16, Val16, i32, Val32, u32, Val32, i64, Val64, u64, Val64, i128, Val128, u128, Val128);
 
+// The remaining fixed size keys are big endian encoded, so they sort by value
+
+macro_rules! fixed_size_key {
+    (for $($t:ty, $v:tt, $bytes:expr),+) => {
+        $(impl<'a> PrimaryKey<'a> for $t {
+            type Prefix = ();
+            type SubPrefix = ();
+            type Suffix = Self;
+            type SuperSuffix = Self;
+
+            fn key(&self) -> Vec<Key<'_>> {
+                let to_bytes: fn(&$t) -> _ = $bytes;
+                vec![Key::$v(to_bytes(self))]
+            }
+        }
+
+        impl<'a> Prefixer<'a> for $t {
+            fn prefix(&self) -> Vec<Key<'_>> {
+                let to_bytes: fn(&$t) -> _ = $bytes;
+                vec![Key::$v(to_bytes(self))]
+            }
+        })*
+    }
+}
+
+fixed_size_key!(
+    for Uint64, Val64, |v| v.to_be_bytes(),
+    Uint128, Val128, |v| v.to_be_bytes(),
+    Uint256, Val256, |v| v.to_be_bytes(),
+    // the atomics, i.e. the value times 10^18
+    Decimal, Val128, |v| v.atomics().to_be_bytes(),
+    Timestamp, Val64, |v| v.nanos().to_be_bytes(),
+    bool, Val8, |v| [*v as u8],
+    char, Val32, |v| (*v as u32).to_be_bytes()
+);
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -486,6 +586,102 @@ mod test {
         assert_eq!(pair.prefix(), vec![one.as_slice(), two.as_slice()]);
     }
 
+    #[test]
+    fn composite_keys_up_to_eight() {
+        let k = ("a", 1u8, "b", 2u16);
+        assert_eq!(
+            k.key(),
+            vec![
+                b"a".as_slice(),
+                [1u8].as_slice(),
+                b"b".as_slice(),
+                [0u8, 2].as_slice()
+            ]
+        );
+        assert_eq!(k.joined_key(), b"\x00\x01a\x00\x01\x01\x00\x01b\x00\x02");
+
+        let k = ("a", "b", "c", "d", "e", "f", "g", "h");
+        let path = k.key();
+        assert_eq!(8, path.len());
+        assert_eq!(path[7], b"h".as_slice());
+    }
+
+    #[test]
+    fn composite_key_prefixes_up_to_eight() {
+        // the prefix of a 5-tuple is a 4-tuple, its sub-prefix a triple
+        type Key5<'a> = (&'a str, u32, &'a str, u32, &'a str);
+        type Key8<'a> = (
+            &'a str,
+            &'a str,
+            &'a str,
+            &'a str,
+            &'a str,
+            &'a str,
+            &'a str,
+            &'a str,
+        );
+        let prefix: <Key5 as PrimaryKey>::Prefix = ("a", 1, "b", 2);
+        let sub_prefix: <Key5 as PrimaryKey>::SubPrefix = ("a", 1, "b");
+        assert_eq!(
+            prefix.prefix(),
+            vec![
+                b"a".as_slice(),
+                1u32.to_cw_bytes().as_slice(),
+                b"b".as_slice(),
+                2u32.to_cw_bytes().as_slice()
+            ]
+        );
+        assert_eq!(sub_prefix.prefix().len(), 3);
+
+        let k = ("a", "b", "c", "d", "e", "f", "g", "h");
+        let prefix: <Key8 as PrimaryKey>::Prefix = ("a", "b", "c", "d", "e", "f", "g");
+        assert_eq!(prefix.prefix().len(), 7);
+        assert!(k.joined_key().starts_with(&prefix.joined_prefix()));
+    }
+
+    #[test]
+    fn std_keys_are_big_endian() {
+        let k = Uint64::new(4242);
+        assert_eq!(k.key(), vec![4242u64.to_be_bytes().as_slice()]);
+        assert_eq!(k.prefix(), vec![4242u64.to_be_bytes().as_slice()]);
+
+        let k = Uint128::new(4242);
+        assert_eq!(k.key(), vec![4242u128.to_be_bytes().as_slice()]);
+
+        let k = Uint256::from(4242u128);
+        let mut expected = [0u8; 32];
+        expected[16..].copy_from_slice(&4242u128.to_be_bytes());
+        assert_eq!(k.key(), vec![expected.as_slice()]);
+
+        let k = Decimal::percent(150);
+        assert_eq!(
+            k.key(),
+            vec![1_500_000_000_000_000_000u128.to_be_bytes().as_slice()]
+        );
+
+        let k = Timestamp::from_seconds(2);
+        assert_eq!(k.key(), vec![2_000_000_000u64.to_be_bytes().as_slice()]);
+
+        assert_eq!(false.key(), vec![[0u8].as_slice()]);
+        assert_eq!(true.key(), vec![[1u8].as_slice()]);
+        assert_eq!('a'.key(), vec![97u32.to_be_bytes().as_slice()]);
+    }
+
+    #[test]
+    fn std_keys_sort_by_value() {
+        fn joined<'a, K: PrimaryKey<'a>>(k: &'a K) -> Vec<u8> {
+            k.joined_key()
+        }
+        assert!(joined(&Uint128::new(255)) < joined(&Uint128::new(256)));
+        assert!(joined(&Uint256::from(255u128)) < joined(&Uint256::from(1u128 << 64)));
+        assert!(joined(&Decimal::percent(99)) < joined(&Decimal::one()));
+        assert!(
+            joined(&(Timestamp::from_nanos(255), "b")) < joined(&(Timestamp::from_nanos(256), "a"))
+        );
+        assert!(joined(&false) < joined(&true));
+        assert!(joined(&'z') < joined(&'\u{e9}'));
+    }
+
     #[test]
     fn naked_proper_prefixes() {
         let pair: (u32, &[u8]) = (12345, b"random");
@@ -1,22 +1,38 @@
 mod bound;
+mod cached;
+mod codec;
 mod de;
+mod deque;
 mod endian;
 mod helpers;
 mod indexed_map;
 mod indexed_snapshot;
 mod indexes;
 mod int_key;
+mod introspect;
 mod item;
 mod iter_helpers;
 mod keys;
 mod map;
+#[cfg(feature = "merkle")]
+mod merkle;
+mod migrate;
+mod paginate;
 mod path;
 mod prefix;
+mod range_query;
 mod snapshot;
+mod usage;
+mod versioned;
 
 #[cfg(feature = "iterator")]
 pub use bound::{Bound, Bounder, PrefixBound, RawBound};
+pub use cached::{CacheStats, CachedStorage};
+#[cfg(feature = "bincode")]
+pub use codec::Bincode;
+pub use codec::{Codec, Json};
 pub use de::KeyDeserialize;
+pub use deque::{Deque, DequeIter};
 pub use endian::Endian;
 #[cfg(feature = "iterator")]
 pub use indexed_map::{IndexList, IndexedMap};
@@ -27,16 +43,31 @@ pub use indexes::Index;
 #[cfg(feature = "iterator")]
 pub use indexes::MultiIndex;
 #[cfg(feature = "iterator")]
+pub use indexes::MultiValueIndex;
+#[cfg(feature = "iterator")]
 pub use indexes::UniqueIndex;
 pub use int_key::IntKey;
+pub use introspect::{DecodedEntry, StateDecoder};
 pub use item::Item;
 pub use keys::{Key, Prefixer, PrimaryKey};
 pub use map::Map;
+#[cfg(feature = "merkle")]
+pub use merkle::{verify_merkle_proof, MerkleLeaf, MerkleMap, MerkleProof};
+#[cfg(feature = "iterator")]
+pub use migrate::{addr_key, same_entry, transform_key, MigrationProgress};
+#[cfg(feature = "iterator")]
+pub use paginate::{Page, Paginator};
 pub use path::Path;
 #[cfg(feature = "iterator")]
 pub use prefix::{range_with_prefix, Prefix};
 #[cfg(feature = "iterator")]
-pub use snapshot::{SnapshotItem, SnapshotMap, Strategy};
+pub use range_query::{IndexPk, RangeQuery};
+#[cfg(feature = "iterator")]
+pub use snapshot::{CheckpointDimension, SnapshotItem, SnapshotMap, Strategy};
+#[cfg(feature = "iterator")]
+pub use usage::all_storage_usage;
+pub use usage::{key_prefix_usage, storage_usage, MeteredStorage, StorageUsage};
+pub use versioned::{upgrade, Upgrade, VersionedItem, VersionedMap};
 
 #[cfg(all(feature = "iterator", feature = "macro"))]
 #[macro_use]
@@ -2,35 +2,58 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::marker::PhantomData;
 
+#[cfg(feature = "iterator")]
+use std::collections::BTreeSet;
+
 #[cfg(feature = "iterator")]
 use crate::bound::{Bound, PrefixBound};
+use crate::codec::{Codec, Json};
 #[cfg(feature = "iterator")]
 use crate::de::KeyDeserialize;
+#[cfg(feature = "iterator")]
+use crate::helpers::namespaces_with_key;
 use crate::helpers::query_raw;
 #[cfg(feature = "iterator")]
-use crate::iter_helpers::{deserialize_kv, deserialize_v};
+use crate::iter_helpers::{decode_kv, decode_v};
 #[cfg(feature = "iterator")]
 use crate::keys::Prefixer;
 use crate::keys::{Key, PrimaryKey};
+#[cfg(feature = "iterator")]
+use crate::migrate::MigrationProgress;
+#[cfg(feature = "iterator")]
+use crate::paginate::{Page, Paginator};
 use crate::path::Path;
 #[cfg(feature = "iterator")]
-use crate::prefix::{namespaced_prefix_range, Prefix};
-use cosmwasm_std::{from_slice, Addr, CustomQuery, QuerierWrapper, StdError, StdResult, Storage};
+use crate::prefix::{codec_deserializer_v, namespaced_prefix_range, Prefix};
+#[cfg(feature = "iterator")]
+use crate::range_query::RangeQuery;
+use cosmwasm_std::{Addr, CustomQuery, QuerierWrapper, StdError, StdResult, Storage};
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Binary, Record};
 
 #[derive(Debug, Clone)]
-pub struct Map<'a, K, T> {
+pub struct Map<'a, K, T, C = Json> {
     namespace: &'a [u8],
     // see https://doc.rust-lang.org/std/marker/struct.PhantomData.html#unused-type-parameters for why this is needed
     key_type: PhantomData<K>,
     data_type: PhantomData<T>,
+    codec: PhantomData<C>,
 }
 
 impl<'a, K, T> Map<'a, K, T> {
     pub const fn new(namespace: &'a str) -> Self {
+        Map::with_codec(namespace)
+    }
+}
+
+impl<'a, K, T, C> Map<'a, K, T, C> {
+    /// Like `new`, for a map storing its values with the given `Codec`
+    pub const fn with_codec(namespace: &'a str) -> Self {
         Map {
             namespace: namespace.as_bytes(),
             data_type: PhantomData,
             key_type: PhantomData,
+            codec: PhantomData,
         }
     }
 
@@ -39,12 +62,13 @@ impl<'a, K, T> Map<'a, K, T> {
     }
 }
 
-impl<'a, K, T> Map<'a, K, T>
+impl<'a, K, T, C> Map<'a, K, T, C>
 where
     T: Serialize + DeserializeOwned,
+    C: Codec,
     K: PrimaryKey<'a>,
 {
-    pub fn key(&self, k: K) -> Path<T> {
+    pub fn key(&self, k: K) -> Path<T, C> {
         Path::new(
             self.namespace,
             &k.key().iter().map(Key::as_ref).collect::<Vec<_>>(),
@@ -53,7 +77,7 @@ where
 
     #[cfg(feature = "iterator")]
     pub(crate) fn no_prefix_raw(&self) -> Prefix<Vec<u8>, T, K> {
-        Prefix::new(self.namespace, &[])
+        Prefix::with_codec::<C>(self.namespace, &[])
     }
 
     pub fn save(&self, store: &mut dyn Storage, k: K, data: &T) -> StdResult<()> {
@@ -106,31 +130,33 @@ where
         if result.is_empty() {
             Ok(None)
         } else {
-            from_slice(&result).map(Some)
+            C::decode(&result).map(Some)
         }
     }
 }
 
 #[cfg(feature = "iterator")]
-impl<'a, K, T> Map<'a, K, T>
+impl<'a, K, T, C> Map<'a, K, T, C>
 where
     T: Serialize + DeserializeOwned,
+    C: Codec,
     K: PrimaryKey<'a>,
 {
     pub fn sub_prefix(&self, p: K::SubPrefix) -> Prefix<K::SuperSuffix, T, K::SuperSuffix> {
-        Prefix::new(self.namespace, &p.prefix())
+        Prefix::with_codec::<C>(self.namespace, &p.prefix())
     }
 
     pub fn prefix(&self, p: K::Prefix) -> Prefix<K::Suffix, T, K::Suffix> {
-        Prefix::new(self.namespace, &p.prefix())
+        Prefix::with_codec::<C>(self.namespace, &p.prefix())
     }
 }
 
 // short-cut for simple keys, rather than .prefix(()).range_raw(...)
 #[cfg(feature = "iterator")]
-impl<'a, K, T> Map<'a, K, T>
+impl<'a, K, T, C> Map<'a, K, T, C>
 where
     T: Serialize + DeserializeOwned,
+    C: Codec,
     // TODO: this should only be when K::Prefix == ()
     // Other cases need to call prefix() first
     K: PrimaryKey<'a>,
@@ -151,16 +177,18 @@ where
         T: 'c,
         'a: 'c,
     {
-        let mapped =
-            namespaced_prefix_range(store, self.namespace, min, max, order).map(deserialize_v);
+        // a fn pointer, so the iterator doesn't borrow the codec type
+        let de_fn: fn(Record) -> StdResult<Record<T>> = decode_v::<T, C>;
+        let mapped = namespaced_prefix_range(store, self.namespace, min, max, order).map(de_fn);
         Box::new(mapped)
     }
 }
 
 #[cfg(feature = "iterator")]
-impl<'a, K, T> Map<'a, K, T>
+impl<'a, K, T, C> Map<'a, K, T, C>
 where
     T: Serialize + DeserializeOwned,
+    C: Codec,
     K: PrimaryKey<'a> + KeyDeserialize,
 {
     /// While `range` over a `prefix` fixes the prefix to one element and iterates over the
@@ -182,20 +210,21 @@ where
         K: 'c,
         K::Output: 'static,
     {
-        let mapped = namespaced_prefix_range(store, self.namespace, min, max, order)
-            .map(deserialize_kv::<K, T>);
+        let de_fn: fn(Record) -> StdResult<(K::Output, T)> = decode_kv::<K, T, C>;
+        let mapped = namespaced_prefix_range(store, self.namespace, min, max, order).map(de_fn);
         Box::new(mapped)
     }
 
     fn no_prefix(&self) -> Prefix<K, T, K> {
-        Prefix::new(self.namespace, &[])
+        Prefix::with_codec::<C>(self.namespace, &[])
     }
 }
 
 #[cfg(feature = "iterator")]
-impl<'a, K, T> Map<'a, K, T>
+impl<'a, K, T, C> Map<'a, K, T, C>
 where
     T: Serialize + DeserializeOwned,
+    C: Codec,
     K: PrimaryKey<'a>,
 {
     pub fn range_raw<'c>(
@@ -223,12 +252,24 @@ where
     {
         self.no_prefix_raw().keys_raw(store, min, max, order)
     }
+
+    /// Returns `true` if the map contains no entries
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.no_prefix_raw().is_empty(store)
+    }
+
+    /// Removes all the entries of the map. Use `prefix(..).clear(store, limit)` to remove a
+    /// bounded number of entries when the map may be too big for one transaction.
+    pub fn clear(&self, store: &mut dyn Storage) {
+        self.no_prefix_raw().clear(store, None)
+    }
 }
 
 #[cfg(feature = "iterator")]
-impl<'a, K, T> Map<'a, K, T>
+impl<'a, K, T, C> Map<'a, K, T, C>
 where
     T: Serialize + DeserializeOwned,
+    C: Codec,
     K: PrimaryKey<'a> + KeyDeserialize,
 {
     pub fn range<'c>(
@@ -258,6 +299,111 @@ where
     {
         self.no_prefix().keys(store, min, max, order)
     }
+
+    /// Returns the entry with the lowest key, if any
+    pub fn first(&self, store: &dyn Storage) -> StdResult<Option<(K::Output, T)>>
+    where
+        K::Output: 'static,
+    {
+        self.range(store, None, None, cosmwasm_std::Order::Ascending)
+            .next()
+            .transpose()
+    }
+
+    /// Returns the entry with the highest key, if any
+    pub fn last(&self, store: &dyn Storage) -> StdResult<Option<(K::Output, T)>>
+    where
+        K::Output: 'static,
+    {
+        self.range(store, None, None, cosmwasm_std::Order::Descending)
+            .next()
+            .transpose()
+    }
+
+    /// Returns the page of entries after the `cursor` of the previous page, see `Paginator`
+    pub fn paginate(
+        &self,
+        store: &dyn Storage,
+        paginator: &Paginator,
+        cursor: Option<Binary>,
+        limit: Option<u32>,
+        order: cosmwasm_std::Order,
+    ) -> StdResult<Page<(K::Output, T)>> {
+        self.no_prefix()
+            .paginate(store, paginator, cursor, limit, order)
+    }
+
+    /// Starts building a range over the map, see `RangeQuery`
+    pub fn range_query(&self) -> RangeQuery<K, T> {
+        RangeQuery::new(self.namespace, &[], codec_deserializer_v::<T, C>)
+    }
+
+    /// Moves up to `limit` entries (all of them if `None`) to `new_map`, in ascending key order,
+    /// changing their keys and values with `transform`. See `same_entry`, `transform_key` and
+    /// `addr_key` for common transforms.
+    ///
+    /// The moved entries are removed from this map, so calling it again in later transactions
+    /// resumes with the remaining ones. `new_map` must use another namespace.
+    ///
+    /// Fails without writing anything if an entry would overwrite one of `new_map`, either
+    /// already stored or moved by the same batch.
+    pub fn migrate_to<'b, K2, T2, C2, F>(
+        &self,
+        store: &mut dyn Storage,
+        new_map: &Map<'b, K2, T2, C2>,
+        mut transform: F,
+        limit: Option<usize>,
+    ) -> StdResult<MigrationProgress>
+    where
+        K2: PrimaryKey<'b> + KeyDeserialize,
+        K2::Output: PrimaryKey<'b>,
+        T2: Serialize + DeserializeOwned,
+        C2: Codec,
+        F: FnMut(K::Output, T) -> StdResult<(K2::Output, T2)>,
+    {
+        if new_map.namespace == self.namespace {
+            return Err(StdError::generic_err(
+                "Cannot migrate a map to its own namespace",
+            ));
+        }
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut batch = self
+            .range_raw(store, None, None, cosmwasm_std::Order::Ascending)
+            .take(limit.saturating_add(1))
+            .collect::<StdResult<Vec<_>>>()?;
+        let more = batch.len() > limit;
+        batch.truncate(limit);
+
+        // transforms, checks and encodes the whole batch before writing, so that a failure
+        // leaves the storage untouched
+        let mut new_keys = BTreeSet::new();
+        let moved = batch
+            .into_iter()
+            .map(|(raw_key, data)| {
+                let (key, data) = transform(K::from_slice(&raw_key)?, data)?;
+                let path: Path<T2, C2> = Path::new(
+                    new_map.namespace,
+                    &key.key().iter().map(Key::as_ref).collect::<Vec<_>>(),
+                );
+                if path.has(store) || !new_keys.insert(path.to_vec()) {
+                    return Err(StdError::generic_err(
+                        "Cannot migrate an entry to a key that is already used in the new map",
+                    ));
+                }
+                Ok((raw_key, path.to_vec(), C2::encode(&data)?))
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let namespace = namespaces_with_key(&[self.namespace], b"");
+        for (raw_key, new_key, value) in &moved {
+            store.remove(&[namespace.as_slice(), raw_key].concat());
+            store.set(new_key, value);
+        }
+        Ok(MigrationProgress {
+            moved: moved.len(),
+            more,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -422,6 +568,70 @@ mod test {
         assert_eq!(1234, same);
     }
 
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn clear_and_is_empty() {
+        let mut store = MockStorage::new();
+        assert!(PEOPLE_ID.is_empty(&store));
+        assert_eq!(PEOPLE_ID.first(&store).unwrap(), None);
+        assert_eq!(PEOPLE_ID.last(&store).unwrap(), None);
+
+        let data = Data {
+            name: "John".to_string(),
+            age: 32,
+        };
+        for id in [56, 12, 1234, 7] {
+            PEOPLE_ID.save(&mut store, id, &data).unwrap();
+        }
+        // another map, which must not be touched
+        PEOPLE_STR.save(&mut store, "jim", &data).unwrap();
+
+        assert!(!PEOPLE_ID.is_empty(&store));
+        assert_eq!(PEOPLE_ID.first(&store).unwrap(), Some((7, data.clone())));
+        assert_eq!(PEOPLE_ID.last(&store).unwrap(), Some((1234, data.clone())));
+
+        PEOPLE_ID.clear(&mut store);
+        assert!(PEOPLE_ID.is_empty(&store));
+        assert_eq!(PEOPLE_ID.first(&store).unwrap(), None);
+        assert_eq!(PEOPLE_STR.load(&store, "jim").unwrap(), data);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn clear_prefix_with_limit() {
+        let mut store = MockStorage::new();
+
+        for i in 0..25u8 {
+            ALLOWANCE
+                .save(&mut store, (b"owner", &[i]), &(i as u64))
+                .unwrap();
+        }
+        ALLOWANCE
+            .save(&mut store, (b"other", b"spender"), &1)
+            .unwrap();
+
+        let prefix = ALLOWANCE.prefix(b"owner");
+        let count = |store: &MockStorage| {
+            ALLOWANCE
+                .prefix(b"owner")
+                .keys_raw(store, None, None, Order::Ascending)
+                .count()
+        };
+
+        // removes the lowest keys first
+        prefix.clear(&mut store, Some(12));
+        assert_eq!(count(&store), 13);
+        assert!(!ALLOWANCE.has(&store, (b"owner", &[11])));
+        assert!(ALLOWANCE.has(&store, (b"owner", &[12])));
+
+        prefix.clear(&mut store, Some(100));
+        assert!(prefix.is_empty(&store));
+        assert!(!ALLOWANCE.is_empty(&store));
+
+        ALLOWANCE.prefix(b"other").clear(&mut store, None);
+        assert!(ALLOWANCE.is_empty(&store));
+    }
+
     #[test]
     #[cfg(feature = "iterator")]
     fn range_raw_simple_key() {
@@ -735,6 +945,121 @@ mod test {
         assert_eq!(all, vec![(1234, data)]);
     }
 
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_timestamp_composite_key() {
+        use cosmwasm_std::{Addr, Timestamp};
+
+        const EXPIRATIONS: Map<(Timestamp, &Addr), bool> = Map::new("expirations");
+
+        let mut store = MockStorage::new();
+        let (john, jim) = (Addr::unchecked("john"), Addr::unchecked("jim"));
+        EXPIRATIONS
+            .save(&mut store, (Timestamp::from_seconds(256), &john), &true)
+            .unwrap();
+        EXPIRATIONS
+            .save(&mut store, (Timestamp::from_seconds(255), &jim), &false)
+            .unwrap();
+        EXPIRATIONS
+            .save(&mut store, (Timestamp::from_seconds(255), &john), &true)
+            .unwrap();
+
+        // sorted by time, then address
+        let all: StdResult<Vec<_>> = EXPIRATIONS
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![
+                ((Timestamp::from_seconds(255), jim.clone()), false),
+                ((Timestamp::from_seconds(255), john.clone()), true),
+                ((Timestamp::from_seconds(256), john.clone()), true),
+            ]
+        );
+
+        // everything expired up to a time
+        let expired: StdResult<Vec<_>> = EXPIRATIONS
+            .prefix_range(
+                &store,
+                None,
+                Some(PrefixBound::inclusive(Timestamp::from_seconds(255))),
+                Order::Ascending,
+            )
+            .collect();
+        assert_eq!(expired.unwrap().len(), 2);
+
+        let later: StdResult<Vec<_>> = EXPIRATIONS
+            .prefix(Timestamp::from_seconds(256))
+            .keys(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(later.unwrap(), vec![john]);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_quadruple_key() {
+        // owner, collection, token id, expiry
+        const TOKENS: Map<(&str, &str, &str, u64), u32> = Map::new("tokens");
+
+        let mut store = MockStorage::new();
+        TOKENS
+            .save(&mut store, ("john", "apes", "1", 100), &1)
+            .unwrap();
+        TOKENS
+            .save(&mut store, ("john", "apes", "1", 200), &2)
+            .unwrap();
+        TOKENS
+            .save(&mut store, ("john", "apes", "2", 100), &3)
+            .unwrap();
+        TOKENS
+            .save(&mut store, ("john", "cats", "1", 100), &4)
+            .unwrap();
+        TOKENS
+            .save(&mut store, ("jim", "apes", "1", 100), &5)
+            .unwrap();
+        assert_eq!(TOKENS.load(&store, ("john", "apes", "2", 100)).unwrap(), 3);
+
+        let all: StdResult<Vec<_>> = TOKENS.range(&store, None, None, Order::Ascending).collect();
+        let all = all.unwrap();
+        assert_eq!(5, all.len());
+        assert_eq!(
+            all[0],
+            (
+                ("jim".to_string(), "apes".to_string(), "1".to_string(), 100),
+                5
+            )
+        );
+
+        // prefix: all but the expiry
+        let expiries: StdResult<Vec<_>> = TOKENS
+            .prefix(("john", "apes", "1"))
+            .range(&store, None, None, Order::Descending)
+            .collect();
+        assert_eq!(expiries.unwrap(), vec![(200, 2), (100, 1)]);
+
+        // sub-prefix: token id and expiry
+        let tokens: StdResult<Vec<_>> = TOKENS
+            .sub_prefix(("john", "apes"))
+            .range(
+                &store,
+                Some(Bound::exclusive(("1", 200))),
+                None,
+                Order::Ascending,
+            )
+            .collect();
+        assert_eq!(tokens.unwrap(), vec![(("2".to_string(), 100), 3)]);
+
+        let bounded: StdResult<Vec<_>> = TOKENS
+            .range(
+                &store,
+                ("john", "apes", "2", 0).inclusive_bound(),
+                None,
+                Order::Ascending,
+            )
+            .collect();
+        assert_eq!(bounded.unwrap().len(), 2);
+    }
+
     #[test]
     #[cfg(feature = "iterator")]
     fn range_simple_signed_integer_key() {
@@ -1511,4 +1836,129 @@ mod test {
         assert_eq!(include.len(), 1);
         assert_eq!(include, vec![456]);
     }
+
+    // stores the values as JSON behind a format version byte
+    #[derive(Debug, Clone)]
+    #[cfg(feature = "iterator")]
+    struct VersionedJson;
+
+    #[cfg(feature = "iterator")]
+    impl Codec for VersionedJson {
+        fn encode<T: Serialize>(value: &T) -> StdResult<Vec<u8>> {
+            let mut data = vec![1];
+            data.extend(cosmwasm_std::to_vec(value)?);
+            Ok(data)
+        }
+
+        fn decode<T: DeserializeOwned>(data: &[u8]) -> StdResult<T> {
+            match data.split_first() {
+                Some((1, json)) => cosmwasm_std::from_slice(json),
+                _ => Err(StdError::parse_err(
+                    std::any::type_name::<T>(),
+                    "unknown format version",
+                )),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn custom_codec() {
+        const VERSIONED: Map<(&str, u32), Data, VersionedJson> = Map::with_codec("versioned");
+        let mut store = MockStorage::new();
+
+        let john = Data {
+            name: "John".to_string(),
+            age: 32,
+        };
+        let jim = Data {
+            name: "Jim".to_string(),
+            age: 44,
+        };
+        VERSIONED.save(&mut store, ("john", 1), &john).unwrap();
+        VERSIONED.save(&mut store, ("jim", 2), &jim).unwrap();
+
+        let raw = store.get(&VERSIONED.key(("john", 1))).unwrap();
+        assert_eq!(raw[0], 1);
+        assert_eq!(VERSIONED.load(&store, ("john", 1)).unwrap(), john);
+
+        // all the deserializers use the codec
+        let all: StdResult<Vec<_>> = VERSIONED
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![
+                (("jim".to_string(), 2), jim.clone()),
+                (("john".to_string(), 1), john.clone())
+            ]
+        );
+        let prefixed: StdResult<Vec<_>> = VERSIONED
+            .prefix("john")
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(prefixed.unwrap(), vec![(1, john.clone())]);
+        let ranged: StdResult<Vec<_>> = VERSIONED
+            .prefix_range_raw(
+                &store,
+                Some(PrefixBound::inclusive("john")),
+                None,
+                Order::Ascending,
+            )
+            .map(|r| r.map(|(_, v)| v))
+            .collect();
+        assert_eq!(ranged.unwrap(), vec![john]);
+
+        // JSON values are rejected
+        const PLAIN: Map<(&str, u32), Data> = Map::new("versioned");
+        PLAIN.save(&mut store, ("ada", 3), &jim).unwrap();
+        assert!(VERSIONED.load(&store, ("ada", 3)).is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "iterator", feature = "bincode"))]
+    fn bincode_codec() {
+        use crate::codec::Bincode;
+        use cosmwasm_std::{coins, BankMsg, CosmosMsg, WasmMsg};
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+        struct Proposal {
+            pub title: String,
+            pub msgs: Vec<CosmosMsg>,
+        }
+
+        const JSON: Map<u64, Proposal> = Map::new("json");
+        const BINCODE: Map<u64, Proposal, Bincode> = Map::with_codec("bincode");
+        let mut store = MockStorage::new();
+
+        let proposal = Proposal {
+            title: "Pay the team".to_string(),
+            msgs: vec![
+                BankMsg::Send {
+                    to_address: "alice".to_string(),
+                    amount: coins(1000, "ucosm"),
+                }
+                .into(),
+                WasmMsg::Execute {
+                    contract_addr: "cw20".to_string(),
+                    msg: to_binary(&"transfer").unwrap(),
+                    funds: vec![],
+                }
+                .into(),
+            ],
+        };
+        JSON.save(&mut store, 1, &proposal).unwrap();
+        BINCODE.save(&mut store, 1, &proposal).unwrap();
+        BINCODE.save(&mut store, 2, &proposal).unwrap();
+
+        assert_eq!(BINCODE.load(&store, 1).unwrap(), proposal);
+        let json_len = store.get(&JSON.key(1)).unwrap().len();
+        let bincode_len = store.get(&BINCODE.key(1)).unwrap().len();
+        assert!(bincode_len < json_len);
+
+        let all: StdResult<Vec<_>> = BINCODE
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(all.unwrap(), vec![(1, proposal.clone()), (2, proposal)]);
+    }
 }
@@ -0,0 +1,621 @@
+//! A map committing to its entries with a sparse Merkle tree, to prove them to other chains.
+
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use cosmwasm_std::{to_vec, Binary, StdError, StdResult, Storage};
+
+#[cfg(feature = "iterator")]
+use crate::de::KeyDeserialize;
+use crate::keys::PrimaryKey;
+use crate::map::Map;
+use crate::path::Path;
+#[cfg(feature = "iterator")]
+use crate::{Bound, Prefix};
+
+type Hash = [u8; 32];
+
+/// The hash of an empty subtree
+const EMPTY: Hash = [0; 32];
+/// Length of the key hashes, in bits, and so maximum depth of the tree
+const MAX_DEPTH: usize = 256;
+
+/// A proof that a key is (inclusion) or is not (exclusion) set in a `MerkleMap` with a given
+/// root. See `verify_merkle_proof`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MerkleProof {
+    /// Hashes of the siblings of the nodes on the key path, from the root down
+    pub siblings: Vec<Binary>,
+    /// The leaf at the end of the key path. For an exclusion proof, it is either empty or the
+    /// leaf of another key sharing that path
+    pub leaf: Option<MerkleLeaf>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MerkleLeaf {
+    pub key_hash: Binary,
+    pub value_hash: Binary,
+}
+
+/// A node of the tree. A subtree holding a single entry is collapsed into its leaf, so the tree
+/// only depends on the set entries, and not on the order they were written in.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+enum Node {
+    Leaf {
+        key_hash: Binary,
+        value_hash: Binary,
+    },
+    Internal {
+        left: Binary,
+        right: Binary,
+    },
+}
+
+impl Node {
+    fn leaf(key_hash: &Hash, value_hash: &Hash) -> Self {
+        Node::Leaf {
+            key_hash: Binary::from(&key_hash[..]),
+            value_hash: Binary::from(&value_hash[..]),
+        }
+    }
+
+    fn internal(left: &Hash, right: &Hash) -> Self {
+        Node::Internal {
+            left: Binary::from(&left[..]),
+            right: Binary::from(&right[..]),
+        }
+    }
+
+    fn hash(&self) -> StdResult<Hash> {
+        Ok(match self {
+            Node::Leaf {
+                key_hash,
+                value_hash,
+            } => leaf_hash(&key_hash.to_array()?, &value_hash.to_array()?),
+            Node::Internal { left, right } => internal_hash(&left.to_array()?, &right.to_array()?),
+        })
+    }
+
+    fn children(&self) -> StdResult<Option<(Hash, Hash)>> {
+        match self {
+            Node::Leaf { .. } => Ok(None),
+            Node::Internal { left, right } => Ok(Some((left.to_array()?, right.to_array()?))),
+        }
+    }
+}
+
+fn sha256(data: &[u8]) -> Hash {
+    Sha256::digest(data).into()
+}
+
+fn leaf_hash(key_hash: &Hash, value_hash: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0]);
+    hasher.update(key_hash);
+    hasher.update(value_hash);
+    hasher.finalize().into()
+}
+
+fn internal_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([1]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Whether the key path goes right at the given depth
+fn bit(key_hash: &Hash, depth: usize) -> bool {
+    key_hash[depth / 8] & (0x80 >> (depth % 8)) != 0
+}
+
+/// The storage key of the node at the given depth on the key path: the depth, followed by the
+/// path bits up to it
+fn node_key(key_hash: &Hash, depth: usize) -> Vec<u8> {
+    let mut key = (depth as u16).to_be_bytes().to_vec();
+    let (bytes, bits) = (depth / 8, depth % 8);
+    key.extend_from_slice(&key_hash[..bytes]);
+    if bits > 0 {
+        key.push(key_hash[bytes] & (0xff << (8 - bits)));
+    }
+    key
+}
+
+/// The key hash of the sibling of the node at the given depth on the key path
+fn flip(key_hash: &Hash, depth: usize) -> Hash {
+    let mut flipped = *key_hash;
+    flipped[depth / 8] ^= 0x80 >> (depth % 8);
+    flipped
+}
+
+/// A `Map` that keeps a sparse Merkle tree of its entries up to date, to prove them with
+/// `prove` against its `root`, for example to another chain through a light-client contract.
+///
+/// The tree is keyed by the SHA-256 hash of the key bytes (`PrimaryKey::joined_key`), and its
+/// leaves commit to the SHA-256 hash of the stored (JSON) value bytes. Only writes through
+/// `save`, `update` and `remove` update the tree: the entries must not be written through a
+/// `Map` with the same namespace.
+///
+/// ## Example:
+///
+/// ```rust
+/// use cosmwasm_std::testing::MockStorage;
+/// use cosmwasm_std::{to_vec, Addr};
+/// use cw_storage_plus::{verify_merkle_proof, MerkleMap, PrimaryKey};
+///
+/// const BALANCES: MerkleMap<&Addr, u64> = MerkleMap::new("balances", "balances__tree");
+///
+/// let mut store = MockStorage::new();
+/// let john = Addr::unchecked("john");
+/// BALANCES.save(&mut store, &john, &1000).unwrap();
+///
+/// let root = BALANCES.root(&store).unwrap();
+/// let proof = BALANCES.prove(&store, &john).unwrap();
+/// let value = to_vec(&1000u64).unwrap();
+/// assert!(verify_merkle_proof(&root, &john.joined_key(), Some(&value), &proof));
+///
+/// // and jim has no balance
+/// let jim = Addr::unchecked("jim");
+/// let proof = BALANCES.prove(&store, &jim).unwrap();
+/// assert!(verify_merkle_proof(&root, &jim.joined_key(), None, &proof));
+/// ```
+pub struct MerkleMap<'a, K, T> {
+    primary: Map<'a, K, T>,
+    nodes: Map<'a, Vec<u8>, Node>,
+}
+
+impl<'a, K, T> MerkleMap<'a, K, T> {
+    pub const fn new(pk: &'a str, tree: &'a str) -> Self {
+        MerkleMap {
+            primary: Map::new(pk),
+            nodes: Map::new(tree),
+        }
+    }
+}
+
+impl<'a, K, T> MerkleMap<'a, K, T>
+where
+    T: Serialize + DeserializeOwned,
+    K: PrimaryKey<'a>,
+{
+    pub fn key(&self, k: K) -> Path<T> {
+        self.primary.key(k)
+    }
+
+    pub fn save(&self, store: &mut dyn Storage, k: K, data: &T) -> StdResult<()> {
+        let key_hash = sha256(&k.joined_key());
+        let value_hash = sha256(&to_vec(data)?);
+        self.primary.save(store, k, data)?;
+        self.update_tree(store, &key_hash, Some(&value_hash))
+    }
+
+    pub fn remove(&self, store: &mut dyn Storage, k: K) -> StdResult<()> {
+        if !self.primary.has(store, k.clone()) {
+            return Ok(());
+        }
+        let key_hash = sha256(&k.joined_key());
+        self.primary.remove(store, k);
+        self.update_tree(store, &key_hash, None)
+    }
+
+    /// load will return an error if no data is set at the given key, or on parse error
+    pub fn load(&self, store: &dyn Storage, k: K) -> StdResult<T> {
+        self.primary.load(store, k)
+    }
+
+    /// may_load will parse the data stored at the key if present, returns Ok(None) if no data there.
+    /// returns an error on issues parsing
+    pub fn may_load(&self, store: &dyn Storage, k: K) -> StdResult<Option<T>> {
+        self.primary.may_load(store, k)
+    }
+
+    pub fn has(&self, store: &dyn Storage, k: K) -> bool {
+        self.primary.has(store, k)
+    }
+
+    /// Loads the data, perform the specified action, and store the result
+    /// in the database. This is shorthand for some common sequences, which may be useful.
+    ///
+    /// If the data exists, `action(Some(value))` is called. Otherwise `action(None)` is called.
+    pub fn update<A, E>(&self, store: &mut dyn Storage, k: K, action: A) -> Result<T, E>
+    where
+        A: FnOnce(Option<T>) -> Result<T, E>,
+        E: From<StdError>,
+    {
+        let input = self.may_load(store, k.clone())?;
+        let output = action(input)?;
+        self.save(store, k, &output)?;
+        Ok(output)
+    }
+
+    /// The root hash of the tree, committing to all the entries
+    pub fn root(&self, store: &dyn Storage) -> StdResult<Binary> {
+        let root = match self.nodes.may_load(store, node_key(&EMPTY, 0))? {
+            Some(node) => node.hash()?,
+            None => EMPTY,
+        };
+        Ok(Binary::from(&root[..]))
+    }
+
+    /// Proves that the key is set to its current value, or that it is not set, against the
+    /// current `root`
+    pub fn prove(&self, store: &dyn Storage, k: K) -> StdResult<MerkleProof> {
+        let key_hash = sha256(&k.joined_key());
+        let mut siblings = vec![];
+        for depth in 0..=MAX_DEPTH {
+            let node = match self.nodes.may_load(store, node_key(&key_hash, depth))? {
+                Some(node) => node,
+                None => {
+                    return Ok(MerkleProof {
+                        siblings,
+                        leaf: None,
+                    })
+                }
+            };
+            match node {
+                Node::Leaf {
+                    key_hash,
+                    value_hash,
+                } => {
+                    let leaf = MerkleLeaf {
+                        key_hash,
+                        value_hash,
+                    };
+                    return Ok(MerkleProof {
+                        siblings,
+                        leaf: Some(leaf),
+                    });
+                }
+                Node::Internal { left, right } => {
+                    siblings.push(if bit(&key_hash, depth) { left } else { right })
+                }
+            }
+        }
+        Err(StdError::generic_err("Merkle tree deeper than its keys"))
+    }
+
+    fn update_tree(
+        &self,
+        store: &mut dyn Storage,
+        key_hash: &Hash,
+        value_hash: Option<&Hash>,
+    ) -> StdResult<()> {
+        let root = self.update_node(store, 0, key_hash, value_hash)?;
+        self.write_node(store, key_hash, 0, root.as_ref())
+    }
+
+    fn write_node(
+        &self,
+        store: &mut dyn Storage,
+        key_hash: &Hash,
+        depth: usize,
+        node: Option<&Node>,
+    ) -> StdResult<()> {
+        match node {
+            Some(node) => self.nodes.save(store, node_key(key_hash, depth), node),
+            None => {
+                self.nodes.remove(store, node_key(key_hash, depth));
+                Ok(())
+            }
+        }
+    }
+
+    /// Sets (or removes, for `None`) the leaf of the key in the subtree at the given depth on
+    /// its path, returning the new root node of that subtree. The caller writes that node.
+    fn update_node(
+        &self,
+        store: &mut dyn Storage,
+        depth: usize,
+        key_hash: &Hash,
+        value_hash: Option<&Hash>,
+    ) -> StdResult<Option<Node>> {
+        let node = match self.nodes.may_load(store, node_key(key_hash, depth))? {
+            Some(node) => node,
+            None => return Ok(value_hash.map(|v| Node::leaf(key_hash, v))),
+        };
+        let (left, right) = match node.children()? {
+            Some(children) => children,
+            None => {
+                let existing = match &node {
+                    Node::Leaf { key_hash, .. } => key_hash.to_array::<32>()?,
+                    Node::Internal { .. } => unreachable!(),
+                };
+                if &existing == key_hash {
+                    return Ok(value_hash.map(|v| Node::leaf(key_hash, v)));
+                }
+                if value_hash.is_none() {
+                    // the key is not set
+                    return Ok(Some(node));
+                }
+                // pushes the other leaf one level down, next to the one being set
+                let hash = node.hash()?;
+                self.write_node(store, &existing, depth + 1, Some(&node))?;
+                if bit(&existing, depth) {
+                    (EMPTY, hash)
+                } else {
+                    (hash, EMPTY)
+                }
+            }
+        };
+
+        let child = self.update_node(store, depth + 1, key_hash, value_hash)?;
+        let child_hash = child.as_ref().map(Node::hash).transpose()?.unwrap_or(EMPTY);
+        let (left, right, sibling) = if bit(key_hash, depth) {
+            (left, child_hash, left)
+        } else {
+            (child_hash, right, right)
+        };
+
+        // collapses the subtrees left with a single leaf
+        match child {
+            Some(Node::Leaf { .. }) if sibling == EMPTY => {
+                self.write_node(store, key_hash, depth + 1, None)?;
+                Ok(child)
+            }
+            None => {
+                self.write_node(store, key_hash, depth + 1, None)?;
+                if sibling == EMPTY {
+                    return Ok(None);
+                }
+                let sibling_key = flip(key_hash, depth);
+                let sibling = self.nodes.load(store, node_key(&sibling_key, depth + 1))?;
+                if let Node::Leaf { .. } = sibling {
+                    self.write_node(store, &sibling_key, depth + 1, None)?;
+                    Ok(Some(sibling))
+                } else {
+                    Ok(Some(Node::internal(&left, &right)))
+                }
+            }
+            Some(child) => {
+                self.write_node(store, key_hash, depth + 1, Some(&child))?;
+                Ok(Some(Node::internal(&left, &right)))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, K, T> MerkleMap<'a, K, T>
+where
+    T: Serialize + DeserializeOwned,
+    K: PrimaryKey<'a> + KeyDeserialize,
+{
+    pub fn prefix(&self, p: K::Prefix) -> Prefix<K::Suffix, T, K::Suffix> {
+        self.primary.prefix(p)
+    }
+
+    pub fn range<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: cosmwasm_std::Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'c>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.primary.range(store, min, max, order)
+    }
+
+    pub fn keys<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: cosmwasm_std::Order,
+    ) -> Box<dyn Iterator<Item = StdResult<K::Output>> + 'c>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.primary.keys(store, min, max, order)
+    }
+}
+
+/// Verifies a proof generated by `MerkleMap::prove`, without access to the map storage.
+///
+/// `key` are the key bytes, as returned by `PrimaryKey::joined_key`. `value` are the stored value
+/// bytes (JSON, as returned by `to_vec`, or by a raw query of the entry) to prove inclusion, or
+/// `None` to prove that the key is not set.
+pub fn verify_merkle_proof(
+    root: &[u8],
+    key: &[u8],
+    value: Option<&[u8]>,
+    proof: &MerkleProof,
+) -> bool {
+    let key_hash = sha256(key);
+    let depth = proof.siblings.len();
+    if depth > MAX_DEPTH {
+        return false;
+    }
+    let leaf = match &proof.leaf {
+        Some(leaf) => match (leaf.key_hash.to_array(), leaf.value_hash.to_array()) {
+            (Ok(key_hash), Ok(value_hash)) => Some((key_hash, value_hash)),
+            _ => return false,
+        },
+        None => None,
+    };
+
+    let mut hash = match (value, leaf) {
+        (Some(value), Some((leaf_key, leaf_value))) => {
+            if leaf_key != key_hash || leaf_value != sha256(value) {
+                return false;
+            }
+            leaf_hash(&leaf_key, &leaf_value)
+        }
+        (None, None) => EMPTY,
+        // another key on the same path
+        (None, Some((leaf_key, leaf_value))) => {
+            if leaf_key == key_hash || (0..depth).any(|d| bit(&leaf_key, d) != bit(&key_hash, d)) {
+                return false;
+            }
+            leaf_hash(&leaf_key, &leaf_value)
+        }
+        (Some(_), None) => return false,
+    };
+    for (depth, sibling) in proof.siblings.iter().enumerate().rev() {
+        let sibling = match sibling.to_array() {
+            Ok(sibling) => sibling,
+            Err(_) => return false,
+        };
+        hash = if bit(&key_hash, depth) {
+            internal_hash(&sibling, &hash)
+        } else {
+            internal_hash(&hash, &sibling)
+        };
+    }
+    root == hash
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const BALANCES: MerkleMap<&str, u64> = MerkleMap::new("balances", "balances__tree");
+
+    fn names(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("user{}", i)).collect()
+    }
+
+    fn verify(store: &dyn Storage, name: &str, value: Option<u64>) -> bool {
+        let root = BALANCES.root(store).unwrap();
+        let proof = BALANCES.prove(store, name).unwrap();
+        let value = value.map(|v| to_vec(&v).unwrap());
+        verify_merkle_proof(&root, name.as_bytes(), value.as_deref(), &proof)
+    }
+
+    #[test]
+    fn root_only_depends_on_entries() {
+        let mut store = MockStorage::new();
+        assert_eq!(BALANCES.root(&store).unwrap(), Binary::from(&EMPTY[..]));
+
+        let names = names(20);
+        for (i, name) in names.iter().enumerate() {
+            BALANCES.save(&mut store, name, &(i as u64)).unwrap();
+        }
+        let root = BALANCES.root(&store).unwrap();
+
+        // same entries, written in another order
+        let mut other = MockStorage::new();
+        for (i, name) in names.iter().enumerate().rev() {
+            BALANCES.save(&mut other, name, &(i as u64)).unwrap();
+        }
+        assert_eq!(BALANCES.root(&other).unwrap(), root);
+
+        // with an extra entry, then removed
+        BALANCES.save(&mut other, "extra", &7).unwrap();
+        assert_ne!(BALANCES.root(&other).unwrap(), root);
+        BALANCES.remove(&mut other, "extra").unwrap();
+        assert_eq!(BALANCES.root(&other).unwrap(), root);
+
+        // changing a value changes the root
+        BALANCES
+            .update(&mut other, "user3", |v| -> StdResult<_> {
+                Ok(v.unwrap() + 1)
+            })
+            .unwrap();
+        assert_ne!(BALANCES.root(&other).unwrap(), root);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn removing_all_entries_clears_the_tree() {
+        use cosmwasm_std::Order;
+
+        let mut store = MockStorage::new();
+        let names = names(20);
+        for name in &names {
+            BALANCES.save(&mut store, name, &1).unwrap();
+        }
+        for name in &names {
+            BALANCES.remove(&mut store, name).unwrap();
+        }
+        assert_eq!(BALANCES.root(&store).unwrap(), Binary::from(&EMPTY[..]));
+        assert_eq!(store.range(None, None, Order::Ascending).count(), 0);
+    }
+
+    #[test]
+    fn proves_inclusion_and_exclusion() {
+        let mut store = MockStorage::new();
+        // empty tree
+        assert!(verify(&store, "john", None));
+
+        let names = names(30);
+        for (i, name) in names.iter().enumerate() {
+            BALANCES.save(&mut store, name, &(i as u64 * 10)).unwrap();
+        }
+        for (i, name) in names.iter().enumerate() {
+            assert!(verify(&store, name, Some(i as u64 * 10)));
+            assert!(!verify(&store, name, Some(i as u64 * 10 + 1)));
+            assert!(!verify(&store, name, None));
+        }
+        for name in ["john", "jim", "user30", "user"] {
+            assert!(verify(&store, name, None));
+            assert!(!verify(&store, name, Some(0)));
+        }
+
+        // removed entries are proven absent
+        BALANCES.remove(&mut store, "user7").unwrap();
+        assert!(verify(&store, "user7", None));
+        assert!(verify(&store, "user8", Some(80)));
+    }
+
+    #[test]
+    fn rejects_forged_proofs() {
+        let mut store = MockStorage::new();
+        for (i, name) in names(10).iter().enumerate() {
+            BALANCES.save(&mut store, name, &(i as u64)).unwrap();
+        }
+        let root = BALANCES.root(&store).unwrap();
+        let value = to_vec(&2u64).unwrap();
+        let proof = BALANCES.prove(&store, "user2").unwrap();
+        assert!(verify_merkle_proof(&root, b"user2", Some(&value), &proof));
+
+        // wrong root
+        let old_root = root.clone();
+        BALANCES.save(&mut store, "user9", &100).unwrap();
+        let root = BALANCES.root(&store).unwrap();
+        assert!(!verify_merkle_proof(&root, b"user2", Some(&value), &proof));
+        assert!(verify_merkle_proof(
+            &old_root,
+            b"user2",
+            Some(&value),
+            &proof
+        ));
+        let proof = BALANCES.prove(&store, "user2").unwrap();
+
+        // another key
+        assert!(!verify_merkle_proof(&root, b"user3", Some(&value), &proof));
+        // tampered sibling
+        let mut forged = proof.clone();
+        let mut sibling = forged.siblings[0].to_vec();
+        sibling[0] ^= 1;
+        forged.siblings[0] = Binary::from(sibling);
+        assert!(!verify_merkle_proof(&root, b"user2", Some(&value), &forged));
+        // truncated path
+        let mut forged = proof.clone();
+        forged.siblings.pop();
+        assert!(!verify_merkle_proof(&root, b"user2", Some(&value), &forged));
+        // the leaf of user2 doesn't prove the absence of a key on another path
+        let missing = (0..)
+            .map(|i| format!("nobody{}", i))
+            .find(|k| bit(&sha256(k.as_bytes()), 0) != bit(&sha256(b"user2"), 0))
+            .unwrap();
+        assert!(!verify_merkle_proof(
+            &root,
+            missing.as_bytes(),
+            None,
+            &proof
+        ));
+        // nor an empty leaf
+        let forged = MerkleProof {
+            siblings: proof.siblings,
+            leaf: None,
+        };
+        assert!(!verify_merkle_proof(&root, b"user2", None, &forged));
+    }
+}
@@ -0,0 +1,231 @@
+#![cfg(feature = "iterator")]
+//! Helpers to move the entries of a map to another one in `migrate`, see `Map::migrate_to`.
+
+use cosmwasm_std::{Addr, Api, StdResult};
+
+/// The outcome of a `Map::migrate_to` call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationProgress {
+    /// Number of entries moved by this call
+    pub moved: usize,
+    /// Whether entries remain to be moved, by calling it again
+    pub more: bool,
+}
+
+/// Keeps the keys and values as they are, to move a map to a new namespace
+///
+/// ## Example:
+///
+/// ```rust
+/// use cosmwasm_std::testing::MockStorage;
+/// use cw_storage_plus::{same_entry, Map};
+///
+/// const OLD_BALANCES: Map<&str, u64> = Map::new("balance");
+/// const BALANCES: Map<&str, u64> = Map::new("balances");
+///
+/// let mut store = MockStorage::new();
+/// OLD_BALANCES.save(&mut store, "john", &1000).unwrap();
+///
+/// let progress = OLD_BALANCES
+///     .migrate_to(&mut store, &BALANCES, same_entry, None)
+///     .unwrap();
+/// assert_eq!((progress.moved, progress.more), (1, false));
+/// assert_eq!(BALANCES.load(&store, "john").unwrap(), 1000);
+/// ```
+pub fn same_entry<K, T>(key: K, data: T) -> StdResult<(K, T)> {
+    Ok((key, data))
+}
+
+/// Changes the keys with `f`, keeping the values
+pub fn transform_key<K, K2, T>(
+    mut f: impl FnMut(K) -> StdResult<K2>,
+) -> impl FnMut(K, T) -> StdResult<(K2, T)> {
+    move |key, data| Ok((f(key)?, data))
+}
+
+/// Validates the string keys as addresses, to move a `Map<&str, _>` to a `Map<&Addr, _>`
+pub fn addr_key<'a, T: 'a>(api: &'a dyn Api) -> impl FnMut(String, T) -> StdResult<(Addr, T)> + 'a {
+    transform_key(move |key: String| api.addr_validate(&key))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Map;
+    use cosmwasm_std::testing::{MockApi, MockStorage};
+    use cosmwasm_std::{Order, StdError};
+
+    const OLD_BALANCES: Map<&str, u64> = Map::new("balance");
+    const BALANCES: Map<&Addr, u64> = Map::new("balances");
+
+    fn old_balances(store: &mut MockStorage, count: u64) {
+        for i in 0..count {
+            OLD_BALANCES
+                .save(store, &format!("user{}", i), &(i * 10))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn migrates_in_batches() {
+        let mut store = MockStorage::new();
+        let api = MockApi::default();
+        old_balances(&mut store, 5);
+
+        let progress = OLD_BALANCES
+            .migrate_to(&mut store, &BALANCES, addr_key(&api), Some(2))
+            .unwrap();
+        assert_eq!(
+            progress,
+            MigrationProgress {
+                moved: 2,
+                more: true
+            }
+        );
+        assert_eq!(
+            OLD_BALANCES
+                .keys(&store, None, None, Order::Ascending)
+                .count(),
+            3
+        );
+
+        // resumes with the remaining entries
+        let progress = OLD_BALANCES
+            .migrate_to(&mut store, &BALANCES, addr_key(&api), Some(3))
+            .unwrap();
+        assert_eq!(
+            progress,
+            MigrationProgress {
+                moved: 3,
+                more: false
+            }
+        );
+        let progress = OLD_BALANCES
+            .migrate_to(&mut store, &BALANCES, addr_key(&api), Some(3))
+            .unwrap();
+        assert_eq!(
+            progress,
+            MigrationProgress {
+                moved: 0,
+                more: false
+            }
+        );
+
+        assert!(OLD_BALANCES.is_empty(&store));
+        let balances = BALANCES
+            .range(&store, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        let expected: Vec<_> = (0..5)
+            .map(|i| (Addr::unchecked(format!("user{}", i)), i * 10))
+            .collect();
+        assert_eq!(balances, expected);
+    }
+
+    #[test]
+    fn migrates_to_composite_keys_and_values() {
+        const ALLOWANCES: Map<(&Addr, &str), String> = Map::new("allowances");
+
+        let mut store = MockStorage::new();
+        old_balances(&mut store, 3);
+
+        let progress = OLD_BALANCES
+            .migrate_to(
+                &mut store,
+                &ALLOWANCES,
+                |owner, amount| {
+                    Ok((
+                        (Addr::unchecked(owner), "ujuno".to_string()),
+                        amount.to_string(),
+                    ))
+                },
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            progress,
+            MigrationProgress {
+                moved: 3,
+                more: false
+            }
+        );
+        let allowance = ALLOWANCES
+            .load(&store, (&Addr::unchecked("user2"), "ujuno"))
+            .unwrap();
+        assert_eq!(allowance, "20");
+    }
+
+    #[test]
+    fn failed_transform_leaves_entries() {
+        let mut store = MockStorage::new();
+        let api = MockApi::default();
+        old_balances(&mut store, 3);
+        // too short for MockApi
+        OLD_BALANCES.save(&mut store, "jo", &1).unwrap();
+
+        let err = OLD_BALANCES
+            .migrate_to(&mut store, &BALANCES, addr_key(&api), None)
+            .unwrap_err();
+        assert!(matches!(err, StdError::GenericErr { .. }));
+        assert_eq!(
+            OLD_BALANCES
+                .keys(&store, None, None, Order::Ascending)
+                .count(),
+            4
+        );
+        assert!(BALANCES.is_empty(&store));
+
+        // can't migrate in place
+        const SAME: Map<&Addr, u64> = Map::new("balance");
+        let err = OLD_BALANCES
+            .migrate_to(&mut store, &SAME, addr_key(&api), None)
+            .unwrap_err();
+        assert!(matches!(err, StdError::GenericErr { .. }));
+    }
+
+    #[test]
+    fn fails_on_key_collisions() {
+        let mut store = MockStorage::new();
+        old_balances(&mut store, 3);
+
+        // two old keys mapping to the same new one
+        let err = OLD_BALANCES
+            .migrate_to(
+                &mut store,
+                &BALANCES,
+                transform_key(|key: String| Ok(Addr::unchecked(&key[..4]))),
+                None,
+            )
+            .unwrap_err();
+        assert!(matches!(err, StdError::GenericErr { .. }));
+
+        // a key already stored in the new map
+        BALANCES
+            .save(&mut store, &Addr::unchecked("user1"), &1)
+            .unwrap();
+        let err = OLD_BALANCES
+            .migrate_to(
+                &mut store,
+                &BALANCES,
+                transform_key(|key: String| Ok(Addr::unchecked(key))),
+                None,
+            )
+            .unwrap_err();
+        assert!(matches!(err, StdError::GenericErr { .. }));
+
+        // the storage is untouched
+        assert_eq!(
+            OLD_BALANCES
+                .keys(&store, None, None, Order::Ascending)
+                .count(),
+            3
+        );
+        assert_eq!(
+            BALANCES
+                .range(&store, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()
+                .unwrap(),
+            vec![(Addr::unchecked("user1"), 1)]
+        );
+    }
+}
@@ -0,0 +1,108 @@
+#![cfg(feature = "iterator")]
+
+use cosmwasm_std::{Binary, Order, Record, StdResult};
+
+use crate::{Bound, PrimaryKey};
+
+/// A page of entries, with the cursor to query the next page from.
+/// The cursor is `None` on the last page.
+pub type Page<V> = (Vec<V>, Option<Binary>);
+
+/// Page size limits, shared by the paginated queries of a contract.
+///
+/// The cursor returned with every page is the raw storage key of its last entry. Clients should
+/// treat it as opaque, and send it back unchanged to get the next page, in the same order.
+///
+/// ## Example:
+///
+/// ```rust
+/// use cosmwasm_std::{Binary, Order, StdResult, Storage};
+/// use cw_storage_plus::{Map, Paginator};
+///
+/// const PAGINATOR: Paginator = Paginator::new(10, 30);
+/// const BALANCES: Map<&str, u128> = Map::new("balances");
+///
+/// fn all_balances(
+///     store: &dyn Storage,
+///     cursor: Option<Binary>,
+///     limit: Option<u32>,
+/// ) -> StdResult<(Vec<(String, u128)>, Option<Binary>)> {
+///     BALANCES.paginate(store, &PAGINATOR, cursor, limit, Order::Ascending)
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Paginator {
+    default_limit: u32,
+    max_limit: u32,
+}
+
+impl Paginator {
+    pub const fn new(default_limit: u32, max_limit: u32) -> Self {
+        Paginator {
+            default_limit,
+            max_limit,
+        }
+    }
+
+    /// The page size for the requested limit: the default one if none, capped to the maximum.
+    /// Pages hold at least one entry, so that they always move the cursor forward.
+    pub fn limit(&self, limit: Option<u32>) -> usize {
+        limit
+            .unwrap_or(self.default_limit)
+            .min(self.max_limit)
+            .max(1) as usize
+    }
+
+    /// Collects a page from the `range` of raw records, built from the bounds after the cursor
+    /// in the given order. Only the records of the page are decoded.
+    pub(crate) fn page<'b, B, V, I, F, D>(
+        &self,
+        cursor: Option<Binary>,
+        limit: Option<u32>,
+        order: Order,
+        range: F,
+        mut decode: D,
+    ) -> StdResult<Page<V>>
+    where
+        B: PrimaryKey<'b>,
+        I: Iterator<Item = Record>,
+        F: FnOnce(Option<Bound<'b, B>>, Option<Bound<'b, B>>, Order) -> I,
+        D: FnMut(Record) -> StdResult<V>,
+    {
+        let after = cursor.map(|c| Bound::ExclusiveRaw(c.0));
+        let mut range = match order {
+            Order::Ascending => range(after, None, order),
+            Order::Descending => range(None, after, order),
+        };
+
+        let limit = self.limit(limit);
+        let mut entries = Vec::with_capacity(limit);
+        let mut last_key = None;
+        for record in range.by_ref().take(limit) {
+            last_key = Some(record.0.clone());
+            entries.push(decode(record)?);
+        }
+        // only point to a next page if there is one
+        let next = match range.next() {
+            Some(_) => last_key.map(Binary),
+            None => None,
+        };
+        Ok((entries, next))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn limit_is_capped() {
+        let paginator = Paginator::new(10, 30);
+        assert_eq!(paginator.limit(None), 10);
+        assert_eq!(paginator.limit(Some(5)), 5);
+        assert_eq!(paginator.limit(Some(30)), 30);
+        assert_eq!(paginator.limit(Some(100)), 30);
+        assert_eq!(paginator.limit(Some(0)), 1);
+        assert_eq!(Paginator::new(0, 30).limit(None), 1);
+    }
+}
@@ -2,25 +2,29 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::marker::PhantomData;
 
-use crate::helpers::{may_deserialize, must_deserialize, nested_namespaces_with_key};
+use crate::codec::{Codec, Json};
+use crate::helpers::{may_decode, must_decode, nested_namespaces_with_key};
 use crate::keys::Key;
-use cosmwasm_std::{to_vec, StdError, StdResult, Storage};
+use cosmwasm_std::{StdError, StdResult, Storage};
 use std::ops::Deref;
 
 #[derive(Debug, Clone)]
-pub struct Path<T>
+pub struct Path<T, C = Json>
 where
     T: Serialize + DeserializeOwned,
+    C: Codec,
 {
     /// all namespaces prefixes and concatenated with the key
     pub(crate) storage_key: Vec<u8>,
     // see https://doc.rust-lang.org/std/marker/struct.PhantomData.html#unused-type-parameters for why this is needed
     data: PhantomData<T>,
+    codec: PhantomData<C>,
 }
 
-impl<T> Deref for Path<T>
+impl<T, C> Deref for Path<T, C>
 where
     T: Serialize + DeserializeOwned,
+    C: Codec,
 {
     type Target = [u8];
 
@@ -29,9 +33,10 @@ where
     }
 }
 
-impl<T> Path<T>
+impl<T, C> Path<T, C>
 where
     T: Serialize + DeserializeOwned,
+    C: Codec,
 {
     pub fn new(namespace: &[u8], keys: &[&[u8]]) -> Self {
         let l = keys.len();
@@ -47,12 +52,13 @@ where
         Path {
             storage_key,
             data: PhantomData,
+            codec: PhantomData,
         }
     }
 
     /// save will serialize the model and store, returns an error on serialization issues
     pub fn save(&self, store: &mut dyn Storage, data: &T) -> StdResult<()> {
-        store.set(&self.storage_key, &to_vec(data)?);
+        store.set(&self.storage_key, &C::encode(data)?);
         Ok(())
     }
 
@@ -63,14 +69,14 @@ where
     /// load will return an error if no data is set at the given key, or on parse error
     pub fn load(&self, store: &dyn Storage) -> StdResult<T> {
         let value = store.get(&self.storage_key);
-        must_deserialize(&value)
+        must_decode::<C, T>(&value)
     }
 
     /// may_load will parse the data stored at the key if present, returns Ok(None) if no data there.
     /// returns an error on issues parsing
     pub fn may_load(&self, store: &dyn Storage) -> StdResult<Option<T>> {
         let value = store.get(&self.storage_key);
-        may_deserialize(&value)
+        may_decode::<C, T>(&value)
     }
 
     /// has returns true or false if any data is at this key, without parsing or interpreting the
@@ -3,17 +3,19 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::marker::PhantomData;
 
-use cosmwasm_std::{Order, Record, StdResult, Storage};
+use cosmwasm_std::{Binary, Order, Record, StdResult, Storage};
 use std::ops::Deref;
 
 use crate::bound::{PrefixBound, RawBound};
+use crate::codec::Codec;
 use crate::de::KeyDeserialize;
 use crate::helpers::{namespaces_with_key, nested_namespaces_with_key};
-use crate::iter_helpers::{concat, deserialize_kv, deserialize_v, trim};
+use crate::iter_helpers::{concat, decode_kv, decode_v, deserialize_kv, deserialize_v, trim};
 use crate::keys::Key;
+use crate::paginate::{Page, Paginator};
 use crate::{Bound, Prefixer, PrimaryKey};
 
-type DeserializeVFn<T> = fn(&dyn Storage, &[u8], Record) -> StdResult<Record<T>>;
+pub(crate) type DeserializeVFn<T> = fn(&dyn Storage, &[u8], Record) -> StdResult<Record<T>>;
 
 type DeserializeKvFn<K, T> =
     fn(&dyn Storage, &[u8], Record) -> StdResult<(<K as KeyDeserialize>::Output, T)>;
@@ -34,6 +36,24 @@ pub fn default_deserializer_kv<K: KeyDeserialize, T: DeserializeOwned>(
     deserialize_kv::<K, T>(raw)
 }
 
+/// Like `default_deserializer_v`, for values encoded with the given codec
+pub fn codec_deserializer_v<T: DeserializeOwned, C: Codec>(
+    _: &dyn Storage,
+    _: &[u8],
+    raw: Record,
+) -> StdResult<Record<T>> {
+    decode_v::<T, C>(raw)
+}
+
+/// Like `default_deserializer_kv`, for values encoded with the given codec
+pub fn codec_deserializer_kv<K: KeyDeserialize, T: DeserializeOwned, C: Codec>(
+    _: &dyn Storage,
+    _: &[u8],
+    raw: Record,
+) -> StdResult<(K::Output, T)> {
+    decode_kv::<K, T, C>(raw)
+}
+
 #[derive(Clone)]
 pub struct Prefix<K, T, B = Vec<u8>>
 where
@@ -76,6 +96,17 @@ where
         )
     }
 
+    /// Like `new`, for values encoded with the given codec
+    pub fn with_codec<C: Codec>(top_name: &[u8], sub_names: &[Key]) -> Self {
+        Prefix::with_deserialization_functions(
+            top_name,
+            sub_names,
+            &[],
+            codec_deserializer_kv::<K, T, C>,
+            codec_deserializer_v::<T, C>,
+        )
+    }
+
     pub fn with_deserialization_functions(
         top_name: &[u8],
         sub_names: &[Key],
@@ -92,6 +123,35 @@ where
             de_fn_v,
         }
     }
+
+    /// Returns `true` if there are no entries under this prefix
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        range_with_prefix(store, &self.storage_prefix, None, None, Order::Ascending)
+            .next()
+            .is_none()
+    }
+
+    /// Removes the entries under this prefix in ascending key order. If `limit` is given, at most
+    /// that many are removed, so big prefixes can be cleared over several transactions.
+    pub fn clear(&self, store: &mut dyn Storage, limit: Option<usize>) {
+        // remove in batches, so we don't load the whole prefix into memory
+        const TAKE: usize = 10;
+        let mut left_to_clear = limit.unwrap_or(usize::MAX);
+        while left_to_clear > 0 {
+            let keys: Vec<_> =
+                range_with_prefix(store, &self.storage_prefix, None, None, Order::Ascending)
+                    .take(TAKE.min(left_to_clear))
+                    .map(|(k, _)| k)
+                    .collect();
+            if keys.is_empty() {
+                break;
+            }
+            left_to_clear -= keys.len();
+            for key in keys {
+                store.remove(&concat(&self.storage_prefix, &key));
+            }
+        }
+    }
 }
 
 impl<'b, K, T, B> Prefix<K, T, B>
@@ -188,6 +248,34 @@ where
         .map(move |kv| (de_fn)(store, &pk_name, kv).map(|(k, _)| k));
         Box::new(mapped)
     }
+
+    /// Returns the page of entries after the `cursor` of the previous page, see `Paginator`
+    pub fn paginate(
+        &self,
+        store: &dyn Storage,
+        paginator: &Paginator,
+        cursor: Option<Binary>,
+        limit: Option<u32>,
+        order: Order,
+    ) -> StdResult<Page<(K::Output, T)>> {
+        let de_fn = self.de_fn_kv;
+        let pk_name = &self.pk_name;
+        paginator.page(
+            cursor,
+            limit,
+            order,
+            |min: Option<Bound<'b, B>>, max, order| {
+                range_with_prefix(
+                    store,
+                    &self.storage_prefix,
+                    min.map(|b| b.to_raw_bound()),
+                    max.map(|b| b.to_raw_bound()),
+                    order,
+                )
+            },
+            |kv| (de_fn)(store, pk_name, kv),
+        )
+    }
 }
 
 pub fn range_with_prefix<'a>(
@@ -279,7 +367,7 @@ fn extend_one_byte(limit: &[u8]) -> Vec<u8> {
 /// Returns a new vec of same length and last byte incremented by one
 /// If last bytes are 255, we handle overflow up the chain.
 /// If all bytes are 255, this returns wrong data - but that is never possible as a namespace
-fn increment_last_byte(input: &[u8]) -> Vec<u8> {
+pub(crate) fn increment_last_byte(input: &[u8]) -> Vec<u8> {
     let mut copy = input.to_vec();
     // zero out all trailing 255, increment first that is not such
     for i in (0..input.len()).rev() {
@@ -0,0 +1,456 @@
+#![cfg(feature = "iterator")]
+
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+
+use cosmwasm_std::{Order, Record, StdResult, Storage};
+
+use crate::bound::{Bound, PrefixBound, RawBound};
+use crate::de::KeyDeserialize;
+use crate::helpers::namespaces_with_key;
+use crate::iter_helpers::{concat, trim};
+use crate::keys::{Prefixer, PrimaryKey};
+use crate::prefix::{increment_last_byte, DeserializeVFn};
+
+/// A range over a map or index, built step by step:
+///
+/// ```rust
+/// use cosmwasm_std::testing::MockStorage;
+/// use cosmwasm_std::StdResult;
+/// use cw_storage_plus::{Bound, Map, PrefixBound};
+///
+/// const ALLOWANCES: Map<(&str, &str, u64), u64> = Map::new("allowances");
+///
+/// let mut store = MockStorage::new();
+/// ALLOWANCES.save(&mut store, ("owner", "spender", 1), &10).unwrap();
+/// ALLOWANCES.save(&mut store, ("owner", "spender", 2), &20).unwrap();
+/// ALLOWANCES.save(&mut store, ("owner", "spender2", 1), &30).unwrap();
+///
+/// // the last entry of "owner", before ("spender2", 1)
+/// let last: StdResult<Vec<_>> = ALLOWANCES
+///     .range_query()
+///     .sub_prefix("owner")
+///     .to(Bound::exclusive(("spender2", 1)))
+///     .rev()
+///     .limit(1)
+///     .range(&store)
+///     .collect();
+/// assert_eq!(last.unwrap(), vec![(("spender".to_string(), 2), 20)]);
+///
+/// // bounds on the prefix and on the whole key can be combined
+/// let count = ALLOWANCES
+///     .range_query()
+///     .from_prefix(PrefixBound::exclusive(("owner", "spender")))
+///     .to(Bound::inclusive(("owner", "spender2", 1)))
+///     .keys_raw(&store)
+///     .count();
+/// assert_eq!(count, 1);
+/// ```
+///
+/// `B` is the type of the keys the bounds are given for, and `K` the type the keys are returned as.
+/// They only differ on a `MultiIndex`, whose bounds are over the index value and the primary key,
+/// and which returns the primary key, see `IndexPk`.
+pub struct RangeQuery<B, T, K = B> {
+    /// the namespace of the map, length-prefixed
+    namespace: Vec<u8>,
+    /// the common part of all the keys in range, after the namespace
+    prefix: Vec<u8>,
+    /// the first key in range, inclusive, after the namespace
+    start: Option<Vec<u8>>,
+    /// the key after the range, exclusive, after the namespace
+    end: Option<Vec<u8>>,
+    order: Order,
+    limit: Option<usize>,
+    pk_name: Vec<u8>,
+    de_fn_v: DeserializeVFn<T>,
+    // see https://doc.rust-lang.org/std/marker/struct.PhantomData.html#unused-type-parameters for why this is needed
+    key_type: PhantomData<(B, K)>,
+}
+
+/// The key type of the ranges over a `MultiIndex`, which return the primary keys whatever the
+/// prefix of the index keys the range is restricted to
+pub struct IndexPk<PK>(PhantomData<PK>);
+
+impl<PK: KeyDeserialize> KeyDeserialize for IndexPk<PK> {
+    type Output = PK::Output;
+
+    fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
+        PK::from_vec(value)
+    }
+}
+
+impl<B, T, K> RangeQuery<B, T, K> {
+    pub(crate) fn new(namespace: &[u8], pk_name: &[u8], de_fn_v: DeserializeVFn<T>) -> Self {
+        RangeQuery {
+            namespace: namespaces_with_key(&[namespace], b""),
+            prefix: vec![],
+            start: None,
+            end: None,
+            order: Order::Ascending,
+            limit: None,
+            pk_name: pk_name.to_vec(),
+            de_fn_v,
+            key_type: PhantomData,
+        }
+    }
+
+    /// Changes the key type, keeping the range
+    fn cast<B2, K2>(self) -> RangeQuery<B2, T, K2> {
+        RangeQuery {
+            namespace: self.namespace,
+            prefix: self.prefix,
+            start: self.start,
+            end: self.end,
+            order: self.order,
+            limit: self.limit,
+            pk_name: self.pk_name,
+            de_fn_v: self.de_fn_v,
+            key_type: PhantomData,
+        }
+    }
+
+    /// Iterates in descending order
+    pub fn rev(mut self) -> Self {
+        self.order = Order::Descending;
+        self
+    }
+
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Returns at most `limit` entries
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Sets the start of the range, as a raw key after the current prefix
+    fn start(mut self, bound: RawBound, past_prefix: bool) -> Self {
+        self.start = Some(match bound {
+            RawBound::Inclusive(key) => concat(&self.prefix, &key),
+            RawBound::Exclusive(key) if past_prefix => {
+                increment_last_byte(&concat(&self.prefix, &key))
+            }
+            RawBound::Exclusive(key) => concat(&concat(&self.prefix, &key), &[0]),
+        });
+        self
+    }
+
+    /// Sets the end of the range, as a raw key after the current prefix
+    fn end(mut self, bound: RawBound, past_prefix: bool) -> Self {
+        self.end = Some(match bound {
+            RawBound::Exclusive(key) => concat(&self.prefix, &key),
+            RawBound::Inclusive(key) if past_prefix => {
+                increment_last_byte(&concat(&self.prefix, &key))
+            }
+            RawBound::Inclusive(key) => concat(&concat(&self.prefix, &key), &[0]),
+        });
+        self
+    }
+
+    fn raw<'a>(&self, store: &'a dyn Storage) -> Box<dyn Iterator<Item = Record> + 'a> {
+        let prefix = concat(&self.namespace, &self.prefix);
+        let mut start = prefix.clone();
+        if let Some(bound) = &self.start {
+            start = start.max(concat(&self.namespace, bound));
+        }
+        let mut end = increment_last_byte(&prefix);
+        if let Some(bound) = &self.end {
+            end = end.min(concat(&self.namespace, bound));
+        }
+        if start >= end {
+            return Box::new(std::iter::empty());
+        }
+
+        let iter = store
+            .range(Some(&start), Some(&end), self.order)
+            .map(move |(k, v)| (trim(&prefix, &k), v));
+        match self.limit {
+            Some(limit) => Box::new(iter.take(limit)),
+            None => Box::new(iter),
+        }
+    }
+}
+
+impl<'b, B, T> RangeQuery<B, T>
+where
+    B: PrimaryKey<'b>,
+{
+    /// Restricts the range to the keys starting with `p`. The bounds are then given for, and the
+    /// keys returned as, the rest of the key.
+    pub fn prefix(mut self, p: B::Prefix) -> RangeQuery<B::Suffix, T>
+    where
+        B::Suffix: PrimaryKey<'b>,
+    {
+        self.prefix.extend(p.joined_prefix());
+        self.cast()
+    }
+
+    /// Like `prefix`, with the sub-prefix of the key
+    pub fn sub_prefix(mut self, p: B::SubPrefix) -> RangeQuery<B::SuperSuffix, T>
+    where
+        B::SuperSuffix: PrimaryKey<'b>,
+    {
+        self.prefix.extend(p.joined_prefix());
+        self.cast()
+    }
+}
+
+impl<'b, B, T, PK> RangeQuery<B, T, IndexPk<PK>>
+where
+    B: PrimaryKey<'b>,
+{
+    /// Restricts the range to the index keys starting with `p`. The bounds are then given for
+    /// the rest of the index key, and the primary keys are still returned.
+    pub fn prefix(mut self, p: B::Prefix) -> RangeQuery<B::Suffix, T, IndexPk<PK>>
+    where
+        B::Suffix: PrimaryKey<'b>,
+    {
+        self.prefix.extend(p.joined_prefix());
+        self.cast()
+    }
+
+    /// Like `prefix`, with the sub-prefix of the index key
+    pub fn sub_prefix(mut self, p: B::SubPrefix) -> RangeQuery<B::SuperSuffix, T, IndexPk<PK>>
+    where
+        B::SuperSuffix: PrimaryKey<'b>,
+    {
+        self.prefix.extend(p.joined_prefix());
+        self.cast()
+    }
+}
+
+impl<'b, B, T, K> RangeQuery<B, T, K>
+where
+    B: PrimaryKey<'b>,
+    T: DeserializeOwned,
+    K: KeyDeserialize,
+{
+    pub fn from(self, bound: Bound<'b, B>) -> Self {
+        self.start(bound.to_raw_bound(), false)
+    }
+
+    pub fn to(self, bound: Bound<'b, B>) -> Self {
+        self.end(bound.to_raw_bound(), false)
+    }
+
+    /// Starts the range at a prefix of the key. An exclusive bound skips all the keys with that
+    /// prefix.
+    pub fn from_prefix(self, bound: PrefixBound<'b, B::Prefix>) -> Self {
+        self.start(bound.to_raw_bound(), true)
+    }
+
+    /// Ends the range at a prefix of the key. An inclusive bound includes all the keys with that
+    /// prefix.
+    pub fn to_prefix(self, bound: PrefixBound<'b, B::Prefix>) -> Self {
+        self.end(bound.to_raw_bound(), true)
+    }
+
+    /// The raw keys after the current prefix, and the values
+    pub fn range_raw<'a>(
+        &self,
+        store: &'a dyn Storage,
+    ) -> Box<dyn Iterator<Item = StdResult<Record<T>>> + 'a>
+    where
+        T: 'a,
+    {
+        let de_fn = self.de_fn_v;
+        let pk_name = self.pk_name.clone();
+        Box::new(self.raw(store).map(move |kv| (de_fn)(store, &pk_name, kv)))
+    }
+
+    /// The raw keys after the current prefix
+    pub fn keys_raw<'a>(&self, store: &'a dyn Storage) -> Box<dyn Iterator<Item = Vec<u8>> + 'a> {
+        Box::new(self.raw(store).map(|(k, _)| k))
+    }
+
+    pub fn range<'a>(
+        &self,
+        store: &'a dyn Storage,
+    ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'a>
+    where
+        T: 'a,
+        K::Output: 'static,
+    {
+        let de_fn = self.de_fn_v;
+        let pk_name = self.pk_name.clone();
+        Box::new(self.raw(store).map(move |kv| {
+            let (k, v) = (de_fn)(store, &pk_name, kv)?;
+            Ok((K::from_vec(k)?, v))
+        }))
+    }
+
+    pub fn keys<'a>(
+        &self,
+        store: &'a dyn Storage,
+    ) -> Box<dyn Iterator<Item = StdResult<K::Output>> + 'a>
+    where
+        T: 'a,
+        K::Output: 'static,
+    {
+        Box::new(self.range(store).map(|kv| kv.map(|(k, _)| k)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Map;
+    use cosmwasm_std::testing::MockStorage;
+
+    const BALANCES: Map<(&str, &str, u32), u64> = Map::new("balances");
+
+    fn store() -> MockStorage {
+        let mut store = MockStorage::new();
+        // names of the same length, so that they sort alphabetically
+        for (owner, denom, id) in [
+            ("ann", "atom", 1),
+            ("ann", "atom", 2),
+            ("ann", "osmo", 1),
+            ("bob", "atom", 1),
+            ("bob", "juno", 3),
+            ("cat", "atom", 1),
+        ] {
+            BALANCES
+                .save(&mut store, (owner, denom, id), &(id as u64))
+                .unwrap();
+        }
+        store
+    }
+
+    fn keys<'b, B, K>(query: RangeQuery<B, u64, K>, store: &MockStorage) -> Vec<K::Output>
+    where
+        B: PrimaryKey<'b>,
+        K: KeyDeserialize,
+        K::Output: 'static,
+    {
+        query.keys(store).collect::<StdResult<_>>().unwrap()
+    }
+
+    #[test]
+    fn full_range() {
+        let store = store();
+        let all: StdResult<Vec<_>> = BALANCES.range_query().range(&store).collect();
+        let all = all.unwrap();
+        assert_eq!(all.len(), 6);
+        assert_eq!(all[0], (("ann".to_string(), "atom".to_string(), 1), 1));
+
+        let last = keys(BALANCES.range_query().rev().limit(2), &store);
+        assert_eq!(
+            last,
+            vec![
+                ("cat".to_string(), "atom".to_string(), 1),
+                ("bob".to_string(), "juno".to_string(), 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn prefixes_with_bounds() {
+        let store = store();
+
+        let ids = keys(
+            BALANCES
+                .range_query()
+                .prefix(("ann", "atom"))
+                .from(Bound::exclusive(1u32)),
+            &store,
+        );
+        assert_eq!(ids, vec![2]);
+
+        let denoms = keys(
+            BALANCES
+                .range_query()
+                .sub_prefix("bob")
+                .to(Bound::inclusive(("juno", 3u32)))
+                .rev(),
+            &store,
+        );
+        assert_eq!(
+            denoms,
+            vec![("juno".to_string(), 3), ("atom".to_string(), 1)]
+        );
+
+        // bounds set before the prefix still apply
+        let ids = keys(
+            BALANCES
+                .range_query()
+                .to(Bound::exclusive(("ann", "atom", 2u32)))
+                .prefix(("ann", "atom")),
+            &store,
+        );
+        assert_eq!(ids, vec![1]);
+    }
+
+    #[test]
+    fn prefix_bounds() {
+        let store = store();
+
+        // exclusive prefix bounds skip all the keys with the prefix
+        let all = keys(
+            BALANCES
+                .range_query()
+                .from_prefix(PrefixBound::exclusive(("ann", "atom")))
+                .to_prefix(PrefixBound::exclusive(("bob", "juno"))),
+            &store,
+        );
+        assert_eq!(
+            all,
+            vec![
+                ("ann".to_string(), "osmo".to_string(), 1),
+                ("bob".to_string(), "atom".to_string(), 1)
+            ]
+        );
+
+        // inclusive ones include them all
+        let all = keys(
+            BALANCES
+                .range_query()
+                .sub_prefix("ann")
+                .to_prefix(PrefixBound::inclusive("atom"))
+                .rev(),
+            &store,
+        );
+        assert_eq!(all, vec![("atom".to_string(), 2), ("atom".to_string(), 1)]);
+
+        // mixed with a bound on the whole key
+        let all = keys(
+            BALANCES
+                .range_query()
+                .from(Bound::inclusive(("ann", "osmo", 1u32)))
+                .to_prefix(PrefixBound::inclusive(("bob", "atom"))),
+            &store,
+        );
+        assert_eq!(all.len(), 2);
+    }
+
+    #[test]
+    fn empty_ranges() {
+        let store = store();
+        let none = BALANCES
+            .range_query()
+            .from(Bound::inclusive(("cat", "atom", 1u32)))
+            .to(Bound::exclusive(("ann", "atom", 1u32)))
+            .keys_raw(&store)
+            .count();
+        assert_eq!(none, 0);
+
+        let none = BALANCES
+            .range_query()
+            .prefix(("dave", "atom"))
+            .keys_raw(&store)
+            .count();
+        assert_eq!(none, 0);
+
+        // a bound out of the prefix
+        let none = BALANCES
+            .range_query()
+            .from(Bound::exclusive(("bob", "juno", 3u32)))
+            .prefix(("ann", "atom"))
+            .keys_raw(&store)
+            .count();
+        assert_eq!(none, 0);
+    }
+}
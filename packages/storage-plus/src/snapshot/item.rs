@@ -1,21 +1,25 @@
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use cosmwasm_std::{StdError, StdResult, Storage};
+use cosmwasm_std::{StdError, StdResult, Storage, Timestamp};
+use std::marker::PhantomData;
+use std::time::Duration;
 
-use crate::snapshot::{ChangeSet, Snapshot};
+use crate::snapshot::{ChangeSet, CheckpointDimension, Snapshot};
 use crate::{Item, Map, Strategy};
 
 /// Item that maintains a snapshot of one or more checkpoints.
 /// We can query historical data as well as current state.
 /// What data is snapshotted depends on the Strategy.
-pub struct SnapshotItem<'a, T> {
+/// Checkpoints are block heights by default, use `Timestamp` as `D` to snapshot by block time.
+pub struct SnapshotItem<'a, T, D = u64> {
     primary: Item<'a, T>,
     changelog_namespace: &'a str,
     snapshots: Snapshot<'a, (), T>,
+    dimension: PhantomData<D>,
 }
 
-impl<'a, T> SnapshotItem<'a, T> {
+impl<'a, T, D> SnapshotItem<'a, T, D> {
     /// Example:
     ///
     /// ```rust
@@ -37,49 +41,112 @@ impl<'a, T> SnapshotItem<'a, T> {
             primary: Item::new(storage_key),
             changelog_namespace: changelog,
             snapshots: Snapshot::new(checkpoints, changelog, strategy),
+            dimension: PhantomData,
         }
     }
 
-    pub fn add_checkpoint(&self, store: &mut dyn Storage, height: u64) -> StdResult<()> {
-        self.snapshots.add_checkpoint(store, height)
+    /// Enables pruning, see `SnapshotMap::with_pruning`
+    pub const fn with_pruning(self, pruned_key: &'a str) -> Self {
+        SnapshotItem {
+            primary: self.primary,
+            changelog_namespace: self.changelog_namespace,
+            snapshots: self.snapshots.with_pruning(pruned_key),
+            dimension: PhantomData,
+        }
     }
 
-    pub fn remove_checkpoint(&self, store: &mut dyn Storage, height: u64) -> StdResult<()> {
-        self.snapshots.remove_checkpoint(store, height)
+    /// Retention period in checkpoint units (blocks, or nanoseconds of block time)
+    const fn with_retention_raw(self, pruned_key: &'a str, period: u64) -> Self {
+        SnapshotItem {
+            primary: self.primary,
+            changelog_namespace: self.changelog_namespace,
+            snapshots: self.snapshots.with_retention(pruned_key, period),
+            dimension: PhantomData,
+        }
     }
 
-    pub fn changelog(&self) -> Map<u64, ChangeSet<T>> {
+    pub fn changelog(&self) -> Map<'a, u64, ChangeSet<T>> {
         // Build and return a compatible Map with the proper key type
         Map::new(self.changelog_namespace)
     }
 }
 
-impl<'a, T> SnapshotItem<'a, T>
+impl<'a, T> SnapshotItem<'a, T> {
+    /// Keeps only the last `blocks` blocks of history, pruning older changes lazily on writes.
+    /// Enables pruning, with the same `pruned_key` as `with_pruning`.
+    pub const fn with_retention(self, pruned_key: &'a str, blocks: u64) -> Self {
+        self.with_retention_raw(pruned_key, blocks)
+    }
+}
+
+impl<'a, T> SnapshotItem<'a, T, Timestamp> {
+    /// Keeps only the history of the last `period` of block time, see `with_retention`
+    pub const fn with_time_retention(self, pruned_key: &'a str, period: Duration) -> Self {
+        self.with_retention_raw(pruned_key, period.as_nanos() as u64)
+    }
+}
+
+impl<'a, T, D> SnapshotItem<'a, T, D>
+where
+    D: CheckpointDimension,
+{
+    pub fn add_checkpoint(&self, store: &mut dyn Storage, height: D) -> StdResult<()> {
+        self.snapshots.add_checkpoint(store, height.to_checkpoint())
+    }
+
+    pub fn remove_checkpoint(&self, store: &mut dyn Storage, height: D) -> StdResult<()> {
+        self.snapshots
+            .remove_checkpoint(store, height.to_checkpoint())
+    }
+
+    /// Removes up to `limit` checkpoints and changelog entries written before `before_height`,
+    /// returning how many were removed. Fails if pruning is not enabled.
+    pub fn prune(
+        &self,
+        store: &mut dyn Storage,
+        before_height: D,
+        limit: Option<usize>,
+    ) -> StdResult<usize> {
+        self.snapshots
+            .prune(store, before_height.to_checkpoint(), limit)
+    }
+}
+
+impl<'a, T, D> SnapshotItem<'a, T, D>
 where
     T: Serialize + DeserializeOwned + Clone,
+    D: CheckpointDimension,
 {
     /// load old value and store changelog
-    fn write_change(&self, store: &mut dyn Storage, height: u64) -> StdResult<()> {
+    fn write_change(&self, store: &mut dyn Storage, height: D) -> StdResult<()> {
         // if there is already data in the changelog for this block, do not write more
-        if self.snapshots.has_changelog(store, (), height)? {
+        if self
+            .snapshots
+            .has_changelog(store, (), height.to_checkpoint())?
+        {
             return Ok(());
         }
         // otherwise, store the previous value
         let old = self.primary.may_load(store)?;
-        self.snapshots.write_changelog(store, (), height, old)
+        self.snapshots
+            .write_changelog(store, (), height.to_checkpoint(), old)
     }
 
-    pub fn save(&self, store: &mut dyn Storage, data: &T, height: u64) -> StdResult<()> {
+    pub fn save(&self, store: &mut dyn Storage, data: &T, height: D) -> StdResult<()> {
         if self.snapshots.should_checkpoint(store, &())? {
             self.write_change(store, height)?;
         }
+        self.snapshots
+            .prune_expired_raw(store, &[], height.to_checkpoint())?;
         self.primary.save(store, data)
     }
 
-    pub fn remove(&self, store: &mut dyn Storage, height: u64) -> StdResult<()> {
+    pub fn remove(&self, store: &mut dyn Storage, height: D) -> StdResult<()> {
         if self.snapshots.should_checkpoint(store, &())? {
             self.write_change(store, height)?;
         }
+        self.snapshots
+            .prune_expired_raw(store, &[], height.to_checkpoint())?;
         self.primary.remove(store);
         Ok(())
     }
@@ -95,8 +162,10 @@ where
         self.primary.may_load(store)
     }
 
-    pub fn may_load_at_height(&self, store: &dyn Storage, height: u64) -> StdResult<Option<T>> {
-        let snapshot = self.snapshots.may_load_at_height(store, (), height)?;
+    pub fn may_load_at_height(&self, store: &dyn Storage, height: D) -> StdResult<Option<T>> {
+        let snapshot = self
+            .snapshots
+            .may_load_at_height(store, (), height.to_checkpoint())?;
 
         if let Some(r) = snapshot {
             Ok(r)
@@ -107,8 +176,9 @@ where
     }
 
     // If there is no checkpoint for that height, then we return StdError::NotFound
-    pub fn assert_checkpointed(&self, store: &dyn Storage, height: u64) -> StdResult<()> {
-        self.snapshots.assert_checkpointed(store, height)
+    pub fn assert_checkpointed(&self, store: &dyn Storage, height: D) -> StdResult<()> {
+        self.snapshots
+            .assert_checkpointed(store, height.to_checkpoint())
     }
 
     /// Loads the data, perform the specified action, and store the result in the database.
@@ -117,7 +187,7 @@ where
     /// If the data exists, `action(Some(value))` is called. Otherwise `action(None)` is called.
     ///
     /// This is a bit more customized than needed to only read "old" value 1 time, not 2 per naive approach
-    pub fn update<A, E>(&self, store: &mut dyn Storage, height: u64, action: A) -> Result<T, E>
+    pub fn update<A, E>(&self, store: &mut dyn Storage, height: D, action: A) -> Result<T, E>
     where
         A: FnOnce(Option<T>) -> Result<T, E>,
         E: From<StdError>,
@@ -129,6 +199,16 @@ where
     }
 }
 
+impl<'a, T> SnapshotItem<'a, T, Timestamp>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    /// Loads the value as it was at the given (checkpointed) block time
+    pub fn may_load_at_time(&self, store: &dyn Storage, time: Timestamp) -> StdResult<Option<T>> {
+        self.may_load_at_height(store, time)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -322,4 +402,40 @@ mod tests {
         assert_eq!(1, all.len());
         assert_eq!(all, vec![(4, ChangeSet { old: Some(8) }),]);
     }
+
+    #[test]
+    fn snapshots_by_time() {
+        const TIMED: SnapshotItem<u64, Timestamp> =
+            SnapshotItem::new("timed", "timed__check", "timed__change", Strategy::Selected);
+
+        let mut store = MockStorage::new();
+        let time = Timestamp::from_seconds(1_000);
+
+        TIMED.save(&mut store, &5, time).unwrap();
+        TIMED
+            .add_checkpoint(&mut store, time.plus_seconds(5))
+            .unwrap();
+        TIMED.save(&mut store, &8, time.plus_seconds(6)).unwrap();
+        TIMED.save(&mut store, &13, time.plus_seconds(7)).unwrap();
+
+        assert_eq!(
+            TIMED
+                .may_load_at_time(&store, time.plus_seconds(5))
+                .unwrap(),
+            Some(5)
+        );
+        assert!(TIMED
+            .may_load_at_time(&store, time.plus_seconds(6))
+            .is_err());
+
+        // the changelog is keyed by nanoseconds
+        let all: StdResult<Vec<_>> = TIMED
+            .changelog()
+            .range(&store, None, None, cosmwasm_std::Order::Ascending)
+            .collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![(time.plus_seconds(6).nanos(), ChangeSet { old: Some(5) })]
+        );
+    }
 }
@@ -1,27 +1,34 @@
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use cosmwasm_std::{StdError, StdResult, Storage};
+use cosmwasm_std::{from_slice, Order, Record, StdError, StdResult, Storage, Timestamp};
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::marker::PhantomData;
+use std::time::Duration;
 
-use crate::bound::PrefixBound;
+use crate::bound::{PrefixBound, RawBound};
 use crate::de::KeyDeserialize;
-use crate::iter_helpers::deserialize_kv;
-use crate::keys::PrimaryKey;
+use crate::helpers::namespaces_with_key;
+use crate::iter_helpers::{deserialize_kv, deserialize_v};
+use crate::keys::{Key, PrimaryKey};
 use crate::map::Map;
 use crate::path::Path;
-use crate::prefix::{namespaced_prefix_range, Prefix};
-use crate::snapshot::{ChangeSet, Snapshot};
+use crate::prefix::{increment_last_byte, namespaced_prefix_range, range_with_prefix, Prefix};
+use crate::snapshot::{ChangeSet, CheckpointDimension, Snapshot};
 use crate::{Bound, Prefixer, Strategy};
 
 /// Map that maintains a snapshots of one or more checkpoints.
 /// We can query historical data as well as current state.
 /// What data is snapshotted depends on the Strategy.
-pub struct SnapshotMap<'a, K, T> {
+/// Checkpoints are block heights by default, use `Timestamp` as `D` to snapshot by block time.
+pub struct SnapshotMap<'a, K, T, D = u64> {
     primary: Map<'a, K, T>,
     snapshots: Snapshot<'a, K, T>,
+    dimension: PhantomData<D>,
 }
 
-impl<'a, K, T> SnapshotMap<'a, K, T> {
+impl<'a, K, T, D> SnapshotMap<'a, K, T, D> {
     /// Example:
     ///
     /// ```rust
@@ -43,6 +50,26 @@ impl<'a, K, T> SnapshotMap<'a, K, T> {
         SnapshotMap {
             primary: Map::new(pk),
             snapshots: Snapshot::new(checkpoints, changelog, strategy),
+            dimension: PhantomData,
+        }
+    }
+
+    /// Enables `prune`, storing the lowest height still available under `pruned_key`, which
+    /// must not be used by anything else.
+    pub const fn with_pruning(self, pruned_key: &'a str) -> Self {
+        SnapshotMap {
+            primary: self.primary,
+            snapshots: self.snapshots.with_pruning(pruned_key),
+            dimension: PhantomData,
+        }
+    }
+
+    /// Retention period in checkpoint units (blocks, or nanoseconds of block time)
+    pub(crate) const fn with_retention_raw(self, pruned_key: &'a str, period: u64) -> Self {
+        SnapshotMap {
+            primary: self.primary,
+            snapshots: self.snapshots.with_retention(pruned_key, period),
+            dimension: PhantomData,
         }
     }
 
@@ -51,22 +78,59 @@ impl<'a, K, T> SnapshotMap<'a, K, T> {
     }
 }
 
-impl<'a, K, T> SnapshotMap<'a, K, T>
+impl<'a, K, T> SnapshotMap<'a, K, T> {
+    /// Keeps only the last `blocks` blocks of history: every write lazily prunes the older
+    /// changelog entries of the written key, and queries before the retention period fail.
+    /// Enables pruning like `with_pruning`, with the same `pruned_key`.
+    pub const fn with_retention(self, pruned_key: &'a str, blocks: u64) -> Self {
+        self.with_retention_raw(pruned_key, blocks)
+    }
+}
+
+impl<'a, K, T> SnapshotMap<'a, K, T, Timestamp> {
+    /// Keeps only the history of the last `period` of block time, see `with_retention`
+    pub const fn with_time_retention(self, pruned_key: &'a str, period: Duration) -> Self {
+        self.with_retention_raw(pruned_key, period.as_nanos() as u64)
+    }
+}
+
+impl<'a, K, T, D> SnapshotMap<'a, K, T, D>
 where
+    D: CheckpointDimension,
     T: Serialize + DeserializeOwned + Clone,
     K: PrimaryKey<'a> + Prefixer<'a>,
 {
-    pub fn add_checkpoint(&self, store: &mut dyn Storage, height: u64) -> StdResult<()> {
-        self.snapshots.add_checkpoint(store, height)
+    pub fn add_checkpoint(&self, store: &mut dyn Storage, height: D) -> StdResult<()> {
+        self.snapshots.add_checkpoint(store, height.to_checkpoint())
+    }
+
+    pub fn remove_checkpoint(&self, store: &mut dyn Storage, height: D) -> StdResult<()> {
+        self.snapshots
+            .remove_checkpoint(store, height.to_checkpoint())
+    }
+
+    /// Removes up to `limit` checkpoints and changelog entries written before `before_height`,
+    /// returning how many were removed. `may_load_at_height` keeps working from `before_height`
+    /// on, and fails for earlier heights. Fails if pruning is not enabled.
+    pub fn prune(
+        &self,
+        store: &mut dyn Storage,
+        before_height: D,
+        limit: Option<usize>,
+    ) -> StdResult<usize> {
+        self.snapshots
+            .prune(store, before_height.to_checkpoint(), limit)
     }
 
-    pub fn remove_checkpoint(&self, store: &mut dyn Storage, height: u64) -> StdResult<()> {
-        self.snapshots.remove_checkpoint(store, height)
+    /// Lowest height that can still be queried, if the history has been pruned
+    pub fn pruned_height(&self, store: &dyn Storage) -> StdResult<Option<D>> {
+        Ok(self.snapshots.pruned_height(store)?.map(D::from_checkpoint))
     }
 }
 
-impl<'a, K, T> SnapshotMap<'a, K, T>
+impl<'a, K, T, D> SnapshotMap<'a, K, T, D>
 where
+    D: CheckpointDimension,
     T: Serialize + DeserializeOwned + Clone,
     K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
 {
@@ -79,27 +143,35 @@ where
     }
 
     /// load old value and store changelog
-    fn write_change(&self, store: &mut dyn Storage, k: K, height: u64) -> StdResult<()> {
+    fn write_change(&self, store: &mut dyn Storage, k: K, height: D) -> StdResult<()> {
         // if there is already data in the changelog for this key and block, do not write more
-        if self.snapshots.has_changelog(store, k.clone(), height)? {
+        if self
+            .snapshots
+            .has_changelog(store, k.clone(), height.to_checkpoint())?
+        {
             return Ok(());
         }
         // otherwise, store the previous value
         let old = self.primary.may_load(store, k.clone())?;
-        self.snapshots.write_changelog(store, k, height, old)
+        self.snapshots
+            .write_changelog(store, k, height.to_checkpoint(), old)
     }
 
-    pub fn save(&self, store: &mut dyn Storage, k: K, data: &T, height: u64) -> StdResult<()> {
+    pub fn save(&self, store: &mut dyn Storage, k: K, data: &T, height: D) -> StdResult<()> {
         if self.snapshots.should_checkpoint(store, &k)? {
             self.write_change(store, k.clone(), height)?;
         }
+        self.snapshots
+            .prune_expired_raw(store, &k.prefix(), height.to_checkpoint())?;
         self.primary.save(store, k, data)
     }
 
-    pub fn remove(&self, store: &mut dyn Storage, k: K, height: u64) -> StdResult<()> {
+    pub fn remove(&self, store: &mut dyn Storage, k: K, height: D) -> StdResult<()> {
         if self.snapshots.should_checkpoint(store, &k)? {
             self.write_change(store, k.clone(), height)?;
         }
+        self.snapshots
+            .prune_expired_raw(store, &k.prefix(), height.to_checkpoint())?;
         self.primary.remove(store, k);
         Ok(())
     }
@@ -115,15 +187,10 @@ where
         self.primary.may_load(store, k)
     }
 
-    pub fn may_load_at_height(
-        &self,
-        store: &dyn Storage,
-        k: K,
-        height: u64,
-    ) -> StdResult<Option<T>> {
-        let snapshot = self
-            .snapshots
-            .may_load_at_height(store, k.clone(), height)?;
+    pub fn may_load_at_height(&self, store: &dyn Storage, k: K, height: D) -> StdResult<Option<T>> {
+        let snapshot =
+            self.snapshots
+                .may_load_at_height(store, k.clone(), height.to_checkpoint())?;
 
         if let Some(r) = snapshot {
             Ok(r)
@@ -133,8 +200,9 @@ where
         }
     }
 
-    pub fn assert_checkpointed(&self, store: &dyn Storage, height: u64) -> StdResult<()> {
-        self.snapshots.assert_checkpointed(store, height)
+    pub fn assert_checkpointed(&self, store: &dyn Storage, height: D) -> StdResult<()> {
+        self.snapshots
+            .assert_checkpointed(store, height.to_checkpoint())
     }
 
     /// Loads the data, perform the specified action, and store the result
@@ -143,13 +211,7 @@ where
     /// If the data exists, `action(Some(value))` is called. Otherwise `action(None)` is called.
     ///
     /// This is a bit more customized than needed to only read "old" value 1 time, not 2 per naive approach
-    pub fn update<A, E>(
-        &self,
-        store: &mut dyn Storage,
-        k: K,
-        height: u64,
-        action: A,
-    ) -> Result<T, E>
+    pub fn update<A, E>(&self, store: &mut dyn Storage, k: K, height: D, action: A) -> Result<T, E>
     where
         A: FnOnce(Option<T>) -> Result<T, E>,
         E: From<StdError>,
@@ -159,11 +221,88 @@ where
         self.save(store, k, &output, height)?;
         Ok(output)
     }
+
+    /// Returns `true` if the map currently contains no entries
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.no_prefix_raw().is_empty(store)
+    }
+
+    /// Removes all the entries of the map at the given height. Like with `remove`, the old values
+    /// are recorded in the changelog, so they can still be loaded at earlier checkpoints.
+    pub fn clear(&self, store: &mut dyn Storage, height: D) -> StdResult<()>
+    where
+        K::Output: Prefixer<'a>,
+    {
+        // remove in batches, so we don't load the whole map into memory
+        const TAKE: usize = 10;
+        loop {
+            let batch = self
+                .no_prefix_raw()
+                .range_raw(store, None, None, Order::Ascending)
+                .take(TAKE)
+                .collect::<StdResult<Vec<_>>>()?;
+            if batch.is_empty() {
+                return Ok(());
+            }
+            for (pk, old) in batch {
+                // the changelog is keyed by the key elements, so rebuild them from the raw key
+                let key = K::from_slice(&pk)?;
+                let key = key.prefix();
+                if self.snapshots.should_checkpoint_raw(store, &key)?
+                    && !self
+                        .snapshots
+                        .has_changelog_raw(store, &key, height.to_checkpoint())?
+                {
+                    self.snapshots.write_changelog_raw(
+                        store,
+                        &key,
+                        height.to_checkpoint(),
+                        Some(old),
+                    )?;
+                }
+                store.remove(&namespaces_with_key(&[self.primary.namespace()], &pk));
+            }
+        }
+    }
+}
+
+impl<'a, K, T> SnapshotMap<'a, K, T, Timestamp>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+{
+    /// Loads the value as it was at the given (checkpointed) block time
+    pub fn may_load_at_time(
+        &self,
+        store: &dyn Storage,
+        k: K,
+        time: Timestamp,
+    ) -> StdResult<Option<T>> {
+        self.may_load_at_height(store, k, time)
+    }
+
+    /// Iterates over the map as it was at the given (checkpointed) block time,
+    /// see `range_at_height`
+    pub fn range_at_time<'c>(
+        &self,
+        store: &'c dyn Storage,
+        time: Timestamp,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: cosmwasm_std::Order,
+    ) -> HistoricalRange<'c, (K::Output, T)>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.range_at_height(store, time, min, max, order)
+    }
 }
 
 // short-cut for simple keys, rather than .prefix(()).range_raw(...)
-impl<'a, K, T> SnapshotMap<'a, K, T>
+impl<'a, K, T, D> SnapshotMap<'a, K, T, D>
 where
+    D: CheckpointDimension,
     T: Serialize + DeserializeOwned + Clone,
     K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
 {
@@ -197,8 +336,9 @@ where
 }
 
 #[cfg(feature = "iterator")]
-impl<'a, K, T> SnapshotMap<'a, K, T>
+impl<'a, K, T, D> SnapshotMap<'a, K, T, D>
 where
+    D: CheckpointDimension,
     T: Serialize + DeserializeOwned,
     K: PrimaryKey<'a> + KeyDeserialize,
 {
@@ -254,6 +394,26 @@ where
         self.no_prefix().keys(store, min, max, order)
     }
 
+    /// Returns the entry with the lowest key, if any
+    pub fn first(&self, store: &dyn Storage) -> StdResult<Option<(K::Output, T)>>
+    where
+        K::Output: 'static,
+    {
+        self.range(store, None, None, Order::Ascending)
+            .next()
+            .transpose()
+    }
+
+    /// Returns the entry with the highest key, if any
+    pub fn last(&self, store: &dyn Storage) -> StdResult<Option<(K::Output, T)>>
+    where
+        K::Output: 'static,
+    {
+        self.range(store, None, None, Order::Descending)
+            .next()
+            .transpose()
+    }
+
     pub fn prefix(&self, p: K::Prefix) -> Prefix<K::Suffix, T, K::Suffix> {
         Prefix::new(self.primary.namespace(), &p.prefix())
     }
@@ -267,9 +427,236 @@ where
     }
 }
 
+/// Result of the `*_at_height` range queries, which fail if the height is not checkpointed
+#[cfg(feature = "iterator")]
+pub(crate) type HistoricalRange<'c, V> = StdResult<Box<dyn Iterator<Item = StdResult<V>> + 'c>>;
+
+#[cfg(feature = "iterator")]
+impl<'a, K, T, D> SnapshotMap<'a, K, T, D>
+where
+    D: CheckpointDimension,
+    T: Serialize + DeserializeOwned + Clone,
+    K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+{
+    /// Iterates over the map as it was at the beginning of the given (checkpointed) height,
+    /// merging the current data with the values recorded in the changelog.
+    /// Bounds and order work like in `range_raw`.
+    ///
+    /// The changes since the height within the bounds are collected up front, so the cost grows
+    /// with the number of keys changed since then (only their leading elements restrict the scan).
+    pub fn range_raw_at_height<'c>(
+        &self,
+        store: &'c dyn Storage,
+        height: D,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: cosmwasm_std::Order,
+    ) -> HistoricalRange<'c, Record<T>>
+    where
+        T: 'c,
+    {
+        // the leading elements of the bounds are length-prefixed in the changelog as well, which
+        // restricts the changes to visit
+        let changes_min = min
+            .as_ref()
+            .and_then(leading_elements)
+            .map(RawBound::Inclusive);
+        let changes_max = max
+            .as_ref()
+            .and_then(leading_elements)
+            .map(|p| RawBound::Exclusive(increment_last_byte(&p)));
+        let min = min.map(|b| b.to_raw_bound());
+        let max = max.map(|b| b.to_raw_bound());
+        self.range_at_height_with_bounds(
+            store,
+            height,
+            (min, max),
+            (changes_min, changes_max),
+            order,
+        )
+    }
+
+    pub fn range_at_height<'c>(
+        &self,
+        store: &'c dyn Storage,
+        height: D,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: cosmwasm_std::Order,
+    ) -> HistoricalRange<'c, (K::Output, T)>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        let mapped = self
+            .range_raw_at_height(store, height, min, max, order)?
+            .map(|kv| kv.and_then(|(k, v)| Ok((K::from_vec(k)?, v))));
+        Ok(Box::new(mapped))
+    }
+
+    pub fn keys_at_height<'c>(
+        &self,
+        store: &'c dyn Storage,
+        height: D,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: cosmwasm_std::Order,
+    ) -> HistoricalRange<'c, K::Output>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        let mapped = self
+            .range_raw_at_height(store, height, min, max, order)?
+            .map(|kv| kv.and_then(|(k, _)| K::from_vec(k)));
+        Ok(Box::new(mapped))
+    }
+
+    /// Like `prefix_range`, over the map as it was at the beginning of the given height
+    pub fn prefix_range_at_height<'c>(
+        &self,
+        store: &'c dyn Storage,
+        height: D,
+        min: Option<PrefixBound<'a, K::Prefix>>,
+        max: Option<PrefixBound<'a, K::Prefix>>,
+        order: cosmwasm_std::Order,
+    ) -> HistoricalRange<'c, (K::Output, T)>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        // a prefix bound covers all the keys starting with it
+        let min = min.map(|b| match b.to_raw_bound() {
+            RawBound::Inclusive(p) => RawBound::Inclusive(p),
+            RawBound::Exclusive(p) => RawBound::Inclusive(increment_last_byte(&p)),
+        });
+        let max = max.map(|b| match b.to_raw_bound() {
+            RawBound::Inclusive(p) => RawBound::Exclusive(increment_last_byte(&p)),
+            RawBound::Exclusive(p) => RawBound::Exclusive(p),
+        });
+        // the prefixes are length-prefixed in the changelog keys as well
+        let bounds = (min, max);
+        let mapped = self
+            .range_at_height_with_bounds(store, height, bounds.clone(), bounds, order)?
+            .map(|kv| kv.and_then(|(k, v)| Ok((K::from_vec(k)?, v))));
+        Ok(Box::new(mapped))
+    }
+
+    /// Merges the current entries within the `bounds` with the changelog ones, visiting the
+    /// changelog keys within `changes_bounds`, see `Snapshot::changes_at_height`
+    fn range_at_height_with_bounds<'c>(
+        &self,
+        store: &'c dyn Storage,
+        height: D,
+        bounds: (Option<RawBound>, Option<RawBound>),
+        changes_bounds: (Option<RawBound>, Option<RawBound>),
+        order: cosmwasm_std::Order,
+    ) -> HistoricalRange<'c, Record<T>>
+    where
+        T: 'c,
+    {
+        let (min, max) = bounds;
+        let (changes_min, changes_max) = changes_bounds;
+        let mut changes: Vec<_> = self
+            .snapshots
+            .changes_at_height(store, height.to_checkpoint(), changes_min, changes_max)?
+            .into_iter()
+            .filter(|(k, _)| in_bounds(k, &min, &max))
+            .collect();
+        if let Order::Descending = order {
+            changes.reverse();
+        }
+        let namespace = namespaces_with_key(&[self.primary.namespace()], b"");
+        let current = range_with_prefix(store, &namespace, min, max, order);
+        Ok(Box::new(AtHeightIter {
+            current: current.peekable(),
+            changes: changes.into_iter().peekable(),
+            order,
+            data_type: PhantomData,
+        }))
+    }
+}
+
+/// The length-prefixed elements of a typed key bound but the last one, which is the only one
+/// encoded differently in the changelog
+#[cfg(feature = "iterator")]
+fn leading_elements<'a, K: PrimaryKey<'a>>(bound: &Bound<'a, K>) -> Option<Vec<u8>> {
+    let key = match bound {
+        Bound::Inclusive((k, _)) | Bound::Exclusive((k, _)) => k.key(),
+        Bound::InclusiveRaw(_) | Bound::ExclusiveRaw(_) => return None,
+    };
+    match key.split_last() {
+        Some((_, leading)) if !leading.is_empty() => Some(namespaces_with_key(
+            &leading.iter().map(Key::as_ref).collect::<Vec<_>>(),
+            b"",
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "iterator")]
+fn in_bounds(key: &[u8], min: &Option<RawBound>, max: &Option<RawBound>) -> bool {
+    let above_min = match min {
+        None => true,
+        Some(RawBound::Inclusive(m)) => key >= m.as_slice(),
+        Some(RawBound::Exclusive(m)) => key > m.as_slice(),
+    };
+    let below_max = match max {
+        None => true,
+        Some(RawBound::Inclusive(m)) => key <= m.as_slice(),
+        Some(RawBound::Exclusive(m)) => key < m.as_slice(),
+    };
+    above_min && below_max
+}
+
+/// Merges the current entries with the changelog overrides (raw `ChangeSet`s), both sorted in
+/// `order`. An override replaces the current value for its key, or hides it if the key didn't
+/// exist. Values are only deserialized when returned.
+#[cfg(feature = "iterator")]
+struct AtHeightIter<'c, T> {
+    current: Peekable<Box<dyn Iterator<Item = Record> + 'c>>,
+    changes: Peekable<std::vec::IntoIter<Record>>,
+    order: Order,
+    data_type: PhantomData<T>,
+}
+
+#[cfg(feature = "iterator")]
+impl<'c, T: DeserializeOwned> Iterator for AtHeightIter<'c, T> {
+    type Item = StdResult<Record<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let next = match (self.current.peek(), self.changes.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((current, _)), Some((changed, _))) => match self.order {
+                    Order::Ascending => current.cmp(changed),
+                    Order::Descending => changed.cmp(current),
+                },
+            };
+            match next {
+                Ordering::Less => return self.current.next().map(deserialize_v),
+                Ordering::Equal | Ordering::Greater => {
+                    if next == Ordering::Equal {
+                        self.current.next();
+                    }
+                    let (k, change) = self.changes.next()?;
+                    match from_slice::<ChangeSet<T>>(&change) {
+                        Ok(ChangeSet { old: Some(v) }) => return Some(Ok((k, v))),
+                        Ok(ChangeSet { old: None }) => {}
+                        Err(err) => return Some(Err(err)),
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Item;
     use cosmwasm_std::testing::MockStorage;
 
     type TestMap = SnapshotMap<'static, &'static str, u64>;
@@ -295,6 +682,20 @@ mod tests {
         "select__change",
         Strategy::Selected,
     );
+    const PRUNED: TestMap = SnapshotMap::new(
+        "pruned",
+        "pruned__check",
+        "pruned__change",
+        Strategy::EveryBlock,
+    )
+    .with_pruning("pruned__pruned");
+    const PRUNED_SELECT: TestMap = SnapshotMap::new(
+        "pruned_select",
+        "pruned_select__check",
+        "pruned_select__change",
+        Strategy::Selected,
+    )
+    .with_pruning("pruned_select__pruned");
 
     // Fills a map &[u8] -> u64 with the following writes:
     // 1: A = 5
@@ -422,6 +823,282 @@ mod tests {
         assert_missing_checkpoint(&NEVER, &storage, 5);
     }
 
+    #[test]
+    fn clear_records_changelog() {
+        let mut storage = MockStorage::new();
+        init_data(&EVERY, &mut storage);
+        assert_eq!(EVERY.first(&storage).unwrap(), Some(("C".to_string(), 13)));
+        assert_eq!(EVERY.last(&storage).unwrap(), Some(("D".to_string(), 22)));
+
+        // a change earlier in the same block is kept in the changelog
+        EVERY.save(&mut storage, "D", &1, 6).unwrap();
+        EVERY.clear(&mut storage, 6).unwrap();
+        assert!(EVERY.is_empty(&storage));
+        assert_eq!(EVERY.first(&storage).unwrap(), None);
+        for k in &["A", "B", "C", "D"] {
+            assert_eq!(EVERY.may_load(&storage, k).unwrap(), None);
+        }
+
+        // historical queries still return the old values
+        assert_values_at_height(&EVERY, &storage, 3, VALUES_START_3);
+        assert_values_at_height(&EVERY, &storage, 5, VALUES_START_5);
+        assert_values_at_height(&EVERY, &storage, 6, FINAL_VALUES);
+        assert_values_at_height(
+            &EVERY,
+            &storage,
+            7,
+            &[("A", None), ("B", None), ("C", None), ("D", None)],
+        );
+
+        let mut storage = MockStorage::new();
+        init_data_composite_key(&EVERY_COMPOSITE_KEY, &mut storage);
+        EVERY_COMPOSITE_KEY.clear(&mut storage, 6).unwrap();
+        assert!(EVERY_COMPOSITE_KEY.is_empty(&storage));
+        assert_eq!(
+            EVERY_COMPOSITE_KEY
+                .may_load_at_height(&storage, ("B", "B"), 6)
+                .unwrap(),
+            Some(13)
+        );
+        assert_eq!(
+            EVERY_COMPOSITE_KEY
+                .may_load_at_height(&storage, ("B", "B"), 7)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn prune_keeps_later_heights() {
+        let mut store = MockStorage::new();
+        init_data(&PRUNED, &mut store);
+        assert_eq!(PRUNED.pruned_height(&store).unwrap(), None);
+
+        // the checkpoint at 3, then A@1, A@3, B@2 and C@3 are older than 4
+        assert_eq!(PRUNED.prune(&mut store, 4, Some(3)).unwrap(), 3);
+        assert_eq!(PRUNED.prune(&mut store, 4, None).unwrap(), 2);
+        assert_eq!(PRUNED.prune(&mut store, 4, None).unwrap(), 0);
+        assert_eq!(PRUNED.pruned_height(&store).unwrap(), Some(4));
+
+        assert_final_values(&PRUNED, &store);
+        assert_values_at_height(
+            &PRUNED,
+            &store,
+            4,
+            &[("A", Some(8)), ("B", Some(7)), ("C", Some(1)), ("D", None)],
+        );
+        assert_values_at_height(&PRUNED, &store, 5, VALUES_START_5);
+        assert_missing_checkpoint(&PRUNED, &store, 3);
+
+        // pruning an earlier height doesn't make history available again
+        assert_eq!(PRUNED.prune(&mut store, 2, None).unwrap(), 0);
+        assert_eq!(PRUNED.pruned_height(&store).unwrap(), Some(4));
+
+        // stored in its own item
+        assert_eq!(Item::<u64>::new("pruned__pruned").load(&store).unwrap(), 4);
+        assert_eq!(store.get(b"pruned__check"), None);
+    }
+
+    #[test]
+    fn prune_needs_pruning_enabled() {
+        let mut store = MockStorage::new();
+        init_data(&NEVER, &mut store);
+
+        let err = NEVER.prune(&mut store, 4, None).unwrap_err();
+        assert!(matches!(err, StdError::GenericErr { .. }));
+        assert_eq!(NEVER.pruned_height(&store).unwrap(), None);
+    }
+
+    #[test]
+    fn prune_drops_old_checkpoints() {
+        let mut store = MockStorage::new();
+        init_data(&PRUNED_SELECT, &mut store);
+        PRUNED_SELECT.add_checkpoint(&mut store, 1).unwrap();
+        PRUNED_SELECT.add_checkpoint(&mut store, 2).unwrap();
+        PRUNED_SELECT.add_checkpoint(&mut store, 5).unwrap();
+        let checkpoints = |store: &dyn Storage| -> Vec<u64> {
+            PRUNED_SELECT
+                .snapshots
+                .checkpoints
+                .keys(store, None, None, Order::Ascending)
+                .collect::<StdResult<_>>()
+                .unwrap()
+        };
+
+        // the limit applies to the checkpoints too
+        assert_eq!(PRUNED_SELECT.prune(&mut store, 4, Some(2)).unwrap(), 2);
+        assert_eq!(checkpoints(&store), vec![3, 5]);
+        assert_missing_checkpoint(&PRUNED_SELECT, &store, 3);
+
+        PRUNED_SELECT.prune(&mut store, 4, None).unwrap();
+        assert_eq!(checkpoints(&store), vec![5]);
+        assert_values_at_height(&PRUNED_SELECT, &store, 5, VALUES_START_5);
+    }
+
+    #[test]
+    fn retention_prunes_on_save() {
+        const RETAINED: TestMap = SnapshotMap::new(
+            "retained",
+            "retained__check",
+            "retained__change",
+            Strategy::EveryBlock,
+        )
+        .with_retention("retained__pruned", 2);
+
+        let mut store = MockStorage::new();
+        for height in 1..=5 {
+            RETAINED
+                .save(&mut store, "A", &(height * 10), height)
+                .unwrap();
+        }
+        RETAINED.save(&mut store, "B", &1, 1).unwrap();
+
+        // writing at 5 pruned the history of A before 3, but not the one of B
+        let heights: Vec<_> = RETAINED
+            .changelog()
+            .keys(&store, None, None, Order::Ascending)
+            .map(|k| k.unwrap())
+            .collect();
+        assert_eq!(
+            heights,
+            vec![
+                ("A".to_string(), 3),
+                ("A".to_string(), 4),
+                ("A".to_string(), 5),
+                ("B".to_string(), 1)
+            ]
+        );
+        assert_eq!(RETAINED.pruned_height(&store).unwrap(), Some(3));
+
+        assert_eq!(
+            RETAINED.may_load_at_height(&store, "A", 3).unwrap(),
+            Some(20)
+        );
+        assert_eq!(
+            RETAINED.may_load_at_height(&store, "A", 5).unwrap(),
+            Some(40)
+        );
+        assert!(RETAINED.may_load_at_height(&store, "A", 2).is_err());
+        assert!(RETAINED.may_load_at_height(&store, "B", 1).is_err());
+
+        RETAINED.remove(&mut store, "B", 6).unwrap();
+        assert_eq!(
+            RETAINED
+                .changelog()
+                .prefix("B")
+                .keys(&store, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()
+                .unwrap(),
+            vec![6]
+        );
+
+        // a write removes a bounded number of expired checkpoints
+        for height in 6..=10 {
+            RETAINED.add_checkpoint(&mut store, height).unwrap();
+        }
+        RETAINED.save(&mut store, "A", &1, 12).unwrap();
+        let checkpoints = |store: &dyn Storage| -> Vec<u64> {
+            RETAINED
+                .snapshots
+                .checkpoints
+                .keys(store, None, None, Order::Ascending)
+                .collect::<StdResult<_>>()
+                .unwrap()
+        };
+        assert_eq!(checkpoints(&store), vec![8, 9, 10]);
+        RETAINED.prune(&mut store, 10, None).unwrap();
+        assert_eq!(checkpoints(&store), vec![10]);
+    }
+
+    #[test]
+    fn snapshots_by_time() {
+        const TIMED: SnapshotMap<&str, u64, Timestamp> = SnapshotMap::new(
+            "timed",
+            "timed__check",
+            "timed__change",
+            Strategy::EveryBlock,
+        )
+        .with_pruning("timed__pruned");
+
+        let mut store = MockStorage::new();
+        let time = Timestamp::from_seconds(1_000);
+
+        TIMED.save(&mut store, "A", &5, time).unwrap();
+        TIMED
+            .save(&mut store, "A", &8, time.plus_seconds(10))
+            .unwrap();
+        TIMED
+            .remove(&mut store, "A", time.plus_seconds(20))
+            .unwrap();
+        TIMED
+            .save(&mut store, "B", &1, time.plus_seconds(20))
+            .unwrap();
+
+        let at = |store: &dyn Storage, seconds| {
+            TIMED.may_load_at_time(store, "A", time.plus_seconds(seconds))
+        };
+        assert_eq!(at(&store, 0).unwrap(), None);
+        assert_eq!(at(&store, 1).unwrap(), Some(5));
+        assert_eq!(at(&store, 10).unwrap(), Some(5));
+        assert_eq!(at(&store, 15).unwrap(), Some(8));
+        assert_eq!(at(&store, 25).unwrap(), None);
+
+        let all: StdResult<Vec<_>> = TIMED
+            .range_at_time(&store, time.plus_seconds(15), None, None, Order::Ascending)
+            .unwrap()
+            .collect();
+        assert_eq!(all.unwrap(), vec![("A".to_string(), 8)]);
+        let all: StdResult<Vec<_>> = TIMED
+            .range_at_time(&store, time.plus_seconds(25), None, None, Order::Ascending)
+            .unwrap()
+            .collect();
+        assert_eq!(all.unwrap(), vec![("B".to_string(), 1)]);
+
+        TIMED
+            .prune(&mut store, time.plus_seconds(10), None)
+            .unwrap();
+        assert_eq!(
+            TIMED.pruned_height(&store).unwrap(),
+            Some(time.plus_seconds(10))
+        );
+        assert!(at(&store, 5).is_err());
+        assert_eq!(at(&store, 15).unwrap(), Some(8));
+    }
+
+    #[test]
+    fn time_retention_prunes_on_save() {
+        const TIMED: SnapshotMap<&str, u64, Timestamp> = SnapshotMap::new(
+            "timed",
+            "timed__check",
+            "timed__change",
+            Strategy::EveryBlock,
+        )
+        .with_time_retention("timed__pruned", Duration::from_secs(15));
+
+        let mut store = MockStorage::new();
+        let time = Timestamp::from_seconds(1_000);
+        for seconds in [0, 10, 20] {
+            TIMED
+                .save(&mut store, "A", &seconds, time.plus_seconds(seconds))
+                .unwrap();
+        }
+
+        // writing at 20s pruned the history before 5s
+        assert_eq!(
+            TIMED.pruned_height(&store).unwrap(),
+            Some(time.plus_seconds(5))
+        );
+        assert!(TIMED
+            .may_load_at_time(&store, "A", time.plus_seconds(1))
+            .is_err());
+        assert_eq!(
+            TIMED
+                .may_load_at_time(&store, "A", time.plus_seconds(10))
+                .unwrap(),
+            Some(0)
+        );
+    }
+
     #[test]
     fn handle_multiple_writes_in_one_block() {
         let mut storage = MockStorage::new();
@@ -598,6 +1275,209 @@ mod tests {
         assert_eq!(all, vec![(("B".into(), "B".into()), 13)]);
     }
 
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_at_height_simple_key() {
+        let mut store = MockStorage::new();
+        init_data(&EVERY, &mut store);
+
+        let all: StdResult<Vec<_>> = EVERY
+            .range_at_height(&store, 3, None, None, Order::Ascending)
+            .unwrap()
+            .collect();
+        assert_eq!(all.unwrap(), vec![("A".into(), 5), ("B".into(), 7)]);
+
+        let all: StdResult<Vec<_>> = EVERY
+            .range_at_height(&store, 5, None, None, Order::Descending)
+            .unwrap()
+            .collect();
+        assert_eq!(all.unwrap(), vec![("C".into(), 13), ("A".into(), 8)]);
+
+        // bounds apply to current and historical entries alike
+        let all: StdResult<Vec<_>> = EVERY
+            .range_at_height(
+                &store,
+                5,
+                Some(Bound::exclusive("A")),
+                Some(Bound::inclusive("D")),
+                Order::Ascending,
+            )
+            .unwrap()
+            .collect();
+        assert_eq!(all.unwrap(), vec![("C".into(), 13)]);
+
+        let keys: StdResult<Vec<_>> = EVERY
+            .keys_at_height(
+                &store,
+                4,
+                None,
+                Some(Bound::exclusive("C")),
+                Order::Ascending,
+            )
+            .unwrap()
+            .collect();
+        assert_eq!(keys.unwrap(), vec!["A".to_string(), "B".to_string()]);
+
+        // after the last change, it's the current state
+        let all: StdResult<Vec<_>> = EVERY
+            .range_at_height(&store, 6, None, None, Order::Ascending)
+            .unwrap()
+            .collect();
+        let current: StdResult<Vec<_>> =
+            EVERY.range(&store, None, None, Order::Ascending).collect();
+        assert_eq!(all.unwrap(), current.unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_at_height_requires_checkpoint() {
+        let mut store = MockStorage::new();
+        init_data(&SELECT, &mut store);
+
+        let all: StdResult<Vec<_>> = SELECT
+            .range_at_height(&store, 3, None, None, Order::Ascending)
+            .unwrap()
+            .collect();
+        assert_eq!(all.unwrap(), vec![("A".into(), 5), ("B".into(), 7)]);
+        assert!(SELECT
+            .range_at_height(&store, 5, None, None, Order::Ascending)
+            .is_err());
+
+        init_data(&NEVER, &mut store);
+        assert!(NEVER
+            .keys_at_height(&store, 3, None, None, Order::Ascending)
+            .is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_at_height_visits_changes_in_bounds() {
+        use std::cell::Cell;
+
+        /// Counts the records read from the wrapped storage
+        #[derive(Default)]
+        struct ReadCounter {
+            storage: MockStorage,
+            reads: Cell<usize>,
+        }
+
+        impl Storage for ReadCounter {
+            fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+                self.reads.set(self.reads.get() + 1);
+                self.storage.get(key)
+            }
+
+            fn range<'a>(
+                &'a self,
+                start: Option<&[u8]>,
+                end: Option<&[u8]>,
+                order: Order,
+            ) -> Box<dyn Iterator<Item = Record> + 'a> {
+                let reads = &self.reads;
+                Box::new(
+                    self.storage
+                        .range(start, end, order)
+                        .inspect(move |_| reads.set(reads.get() + 1)),
+                )
+            }
+
+            fn set(&mut self, key: &[u8], value: &[u8]) {
+                self.storage.set(key, value)
+            }
+
+            fn remove(&mut self, key: &[u8]) {
+                self.storage.remove(key)
+            }
+        }
+
+        // 3 owners with 2 keys each, changed at every height
+        let mut store = ReadCounter::default();
+        for height in 1..=20 {
+            for owner in ["A", "B", "C"] {
+                for key in ["X", "Y"] {
+                    EVERY_COMPOSITE_KEY
+                        .save(&mut store, (owner, key), &height, height)
+                        .unwrap();
+                }
+            }
+        }
+
+        store.reads.set(0);
+        let all: StdResult<Vec<_>> = EVERY_COMPOSITE_KEY
+            .range_at_height(
+                &store,
+                10,
+                Some(Bound::inclusive(("B", "X"))),
+                Some(Bound::inclusive(("B", "Y"))),
+                Order::Ascending,
+            )
+            .unwrap()
+            .collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![(("B".into(), "X".into()), 9), (("B".into(), "Y".into()), 9)]
+        );
+        // two changelog reads per key of "B" and the two current entries, out of 120 changes
+        assert_eq!(store.reads.get(), 2 * 2 + 2);
+
+        store.reads.set(0);
+        let all: StdResult<Vec<_>> = EVERY_COMPOSITE_KEY
+            .prefix_range_at_height(
+                &store,
+                20,
+                Some(PrefixBound::inclusive("C")),
+                None,
+                Order::Descending,
+            )
+            .unwrap()
+            .take(1)
+            .collect();
+        assert_eq!(all.unwrap(), vec![(("C".into(), "Y".into()), 19)]);
+        assert_eq!(store.reads.get(), 2 * 2 + 1);
+    }
+
+    #[test]
+    fn range_at_height_composite_key() {
+        let mut store = MockStorage::new();
+        init_data_composite_key(&EVERY_COMPOSITE_KEY, &mut store);
+
+        let all: StdResult<Vec<_>> = EVERY_COMPOSITE_KEY
+            .range_at_height(&store, 3, None, None, Order::Ascending)
+            .unwrap()
+            .collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![(("A".into(), "B".into()), 5), (("B".into(), "A".into()), 7)]
+        );
+
+        let all: StdResult<Vec<_>> = EVERY_COMPOSITE_KEY
+            .prefix_range_at_height(
+                &store,
+                5,
+                Some(PrefixBound::exclusive("A")),
+                None,
+                Order::Ascending,
+            )
+            .unwrap()
+            .collect();
+        assert_eq!(all.unwrap(), vec![(("B".into(), "B".into()), 13)]);
+
+        let all: StdResult<Vec<_>> = EVERY_COMPOSITE_KEY
+            .prefix_range_at_height(
+                &store,
+                3,
+                None,
+                Some(PrefixBound::inclusive("B")),
+                Order::Descending,
+            )
+            .unwrap()
+            .collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![(("B".into(), "A".into()), 7), (("A".into(), "B".into()), 5)]
+        );
+    }
+
     #[test]
     #[cfg(feature = "iterator")]
     fn prefix_composite_key() {
@@ -3,29 +3,45 @@ mod item;
 mod map;
 
 pub use item::SnapshotItem;
+#[cfg(feature = "iterator")]
+pub(crate) use map::HistoricalRange;
 pub use map::SnapshotMap;
 
-use crate::bound::Bound;
+use crate::bound::{Bound, RawBound};
 use crate::de::KeyDeserialize;
-use crate::{Map, Prefixer, PrimaryKey};
-use cosmwasm_std::{Order, StdError, StdResult, Storage};
+use crate::helpers::{namespaces_with_key, nested_namespaces_with_key};
+use crate::iter_helpers::concat;
+use crate::keys::Key;
+use crate::prefix::range_with_prefix;
+use crate::{IntKey, Item, Map, Prefix, Prefixer, PrimaryKey};
+use cosmwasm_std::{to_vec, Order, Record, StdError, StdResult, Storage, Timestamp};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
+/// How many expired checkpoints a write removes at most, when pruning lazily
+const LAZY_CHECKPOINT_PRUNING: usize = 2;
+
 /// Structure holding a map of checkpoints composited from
 /// height (as u64) and counter of how many times it has
 /// been checkpointed (as u32).
 /// Stores all changes in changelog.
+/// Pruning stores the lowest height still available in its own item.
 #[derive(Debug, Clone)]
 pub(crate) struct Snapshot<'a, K, T> {
     checkpoints: Map<'a, u64, u32>,
 
+    // the lowest height still available, when pruning is enabled
+    pruned: Option<Item<'a, u64>>,
+
     // this stores all changes (key, height). Must differentiate between no data written,
     // and explicit None (just inserted)
     pub changelog: Map<'a, (K, u64), ChangeSet<T>>,
 
     // How aggressive we are about checkpointing all data
     strategy: Strategy,
+
+    // How many blocks of history are kept when pruning lazily on writes
+    retention: Option<u64>,
 }
 
 impl<'a, K, T> Snapshot<'a, K, T> {
@@ -36,8 +52,31 @@ impl<'a, K, T> Snapshot<'a, K, T> {
     ) -> Snapshot<'a, K, T> {
         Snapshot {
             checkpoints: Map::new(checkpoints),
+            pruned: None,
             changelog: Map::new(changelog),
             strategy,
+            retention: None,
+        }
+    }
+
+    pub const fn with_pruning(self, pruned_key: &'a str) -> Snapshot<'a, K, T> {
+        Snapshot {
+            checkpoints: self.checkpoints,
+            pruned: Some(Item::new(pruned_key)),
+            changelog: self.changelog,
+            strategy: self.strategy,
+            retention: self.retention,
+        }
+    }
+
+    /// Enables pruning like `with_pruning`, and prunes lazily on writes
+    pub const fn with_retention(self, pruned_key: &'a str, period: u64) -> Snapshot<'a, K, T> {
+        Snapshot {
+            checkpoints: self.checkpoints,
+            pruned: Some(Item::new(pruned_key)),
+            changelog: self.changelog,
+            strategy: self.strategy,
+            retention: Some(period),
         }
     }
 
@@ -59,6 +98,129 @@ impl<'a, K, T> Snapshot<'a, K, T> {
             self.checkpoints.save(store, height, &(count - 1))
         }
     }
+
+    /// Lowest height that can still be queried, if the history has been pruned
+    pub fn pruned_height(&self, store: &dyn Storage) -> StdResult<Option<u64>> {
+        match &self.pruned {
+            Some(pruned) => pruned.may_load(store),
+            None => Ok(None),
+        }
+    }
+
+    /// Marks the history before `height` as unavailable. The checkpoints before it are
+    /// dropped separately, see `prune_checkpoints`.
+    /// Returns false if it was already pruned up to that height.
+    fn set_pruned_height(&self, store: &mut dyn Storage, height: u64) -> StdResult<bool> {
+        let pruned = self
+            .pruned
+            .as_ref()
+            .ok_or_else(|| StdError::generic_err("Pruning is not enabled, see `with_pruning`"))?;
+        if let Some(pruned) = pruned.may_load(store)? {
+            if pruned >= height {
+                return Ok(false);
+            }
+        }
+        pruned.save(store, &height)?;
+        Ok(true)
+    }
+
+    /// Removes up to `limit` checkpoints before `height`, returning how many were removed
+    fn prune_checkpoints(&self, store: &mut dyn Storage, height: u64, limit: usize) -> usize {
+        let namespace = namespaces_with_key(&[self.checkpoints.namespace()], b"");
+        let expired: Vec<_> = range_with_prefix(
+            store,
+            &namespace,
+            None,
+            Some(RawBound::Exclusive(height.to_cw_bytes().to_vec())),
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|(k, _)| k)
+        .collect();
+        for k in &expired {
+            store.remove(&concat(&namespace, k));
+        }
+        expired.len()
+    }
+
+    /// Removes up to `limit` checkpoints and changelog entries written before `before_height`,
+    /// returning how many were removed. Queries at `before_height` or later are not affected,
+    /// while queries at earlier heights fail from now on. Call it again until it returns less
+    /// than `limit` to prune everything.
+    ///
+    /// Only the expired entries are visited, plus one entry for each key with later changes.
+    pub fn prune(
+        &self,
+        store: &mut dyn Storage,
+        before_height: u64,
+        limit: Option<usize>,
+    ) -> StdResult<usize> {
+        self.set_pruned_height(store, before_height)?;
+
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut removed = self.prune_checkpoints(store, before_height, limit);
+
+        let namespace = namespaces_with_key(&[self.changelog.namespace()], b"");
+        let mut start = None;
+        while removed < limit {
+            let key =
+                match range_with_prefix(store, &namespace, start, None, Order::Ascending).next() {
+                    Some((key, _)) => key,
+                    None => break,
+                };
+            // changelog keys are the length-prefixed key elements, followed by the height
+            if key.len() < 8 {
+                return Err(StdError::generic_err("Invalid changelog key"));
+            }
+            let (elements, height) = key.split_at(key.len() - 8);
+            if height < &before_height.to_cw_bytes()[..] {
+                store.remove(&concat(&namespace, &key));
+                removed += 1;
+                start = Some(RawBound::Exclusive(key));
+            } else {
+                // skips the later changes of this key
+                start = Some(RawBound::Exclusive(concat(
+                    elements,
+                    &u64::MAX.to_cw_bytes(),
+                )));
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Applies the retention policy (if any) when writing the key at the given height:
+    /// prunes the history of this key that is older than the retention period, and up to
+    /// `LAZY_CHECKPOINT_PRUNING` expired checkpoints, so that a write costs a bounded amount
+    /// of gas after a long gap. `prune` removes the remaining checkpoints.
+    pub fn prune_expired_raw(
+        &self,
+        store: &mut dyn Storage,
+        key: &[Key],
+        height: u64,
+    ) -> StdResult<()> {
+        let before_height = match self.retention {
+            Some(blocks) if height > blocks => height - blocks,
+            _ => return Ok(()),
+        };
+        if self.set_pruned_height(store, before_height)? {
+            self.prune_checkpoints(store, before_height, LAZY_CHECKPOINT_PRUNING);
+        }
+
+        let namespace = nested_namespaces_with_key(&[self.changelog.namespace()], key, b"");
+        let expired: Vec<_> = range_with_prefix(
+            store,
+            &namespace,
+            None,
+            Some(RawBound::Exclusive(before_height.to_cw_bytes().to_vec())),
+            Order::Ascending,
+        )
+        .map(|(k, _)| k)
+        .collect();
+        for k in expired {
+            store.remove(&concat(&namespace, &k));
+        }
+        Ok(())
+    }
 }
 
 impl<'a, K, T> Snapshot<'a, K, T>
@@ -77,6 +239,19 @@ where
 
     /// this is just pulled out from above for the selected block
     fn should_checkpoint_selected(&self, store: &dyn Storage, k: &K) -> StdResult<bool> {
+        self.should_checkpoint_selected_raw(store, &k.prefix())
+    }
+
+    /// should_checkpoint for a key given by its (length-prefixed) elements
+    pub fn should_checkpoint_raw(&self, store: &dyn Storage, key: &[Key]) -> StdResult<bool> {
+        match self.strategy {
+            Strategy::EveryBlock => Ok(true),
+            Strategy::Never => Ok(false),
+            Strategy::Selected => self.should_checkpoint_selected_raw(store, key),
+        }
+    }
+
+    fn should_checkpoint_selected_raw(&self, store: &dyn Storage, key: &[Key]) -> StdResult<bool> {
         // most recent checkpoint
         let checkpoint = self
             .checkpoints
@@ -86,9 +261,7 @@ where
         if let Some((height, _)) = checkpoint {
             // any changelog for the given key since then?
             let start = Bound::inclusive(height);
-            let first = self
-                .changelog
-                .prefix(k.clone())
+            let first = Prefix::<u64, ChangeSet<T>, u64>::new(self.changelog.namespace(), key)
                 .range_raw(store, Some(start), None, Order::Ascending)
                 .next()
                 .transpose()?;
@@ -103,6 +276,11 @@ where
 
     // If there is no checkpoint for that height, then we return StdError::NotFound
     pub fn assert_checkpointed(&self, store: &dyn Storage, height: u64) -> StdResult<()> {
+        if let Some(pruned) = self.pruned_height(store)? {
+            if height < pruned {
+                return Err(StdError::not_found("checkpoint"));
+            }
+        }
         let has = match self.strategy {
             Strategy::EveryBlock => true,
             Strategy::Never => false,
@@ -115,7 +293,7 @@ where
     }
 
     pub fn has_changelog(&self, store: &mut dyn Storage, key: K, height: u64) -> StdResult<bool> {
-        Ok(self.changelog.may_load(store, (key, height))?.is_some())
+        self.has_changelog_raw(store, &key.prefix(), height)
     }
 
     pub fn write_changelog(
@@ -125,8 +303,87 @@ where
         height: u64,
         old: Option<T>,
     ) -> StdResult<()> {
-        self.changelog
-            .save(store, (key, height), &ChangeSet { old })
+        self.write_changelog_raw(store, &key.prefix(), height, old)
+    }
+
+    /// Storage key of the changelog entry, equal to the one of `changelog.key((key, height))`
+    fn changelog_key(&self, key: &[Key], height: u64) -> Vec<u8> {
+        nested_namespaces_with_key(&[self.changelog.namespace()], key, &height.to_cw_bytes())
+    }
+
+    /// has_changelog for a key given by its (length-prefixed) elements
+    pub fn has_changelog_raw(
+        &self,
+        store: &dyn Storage,
+        key: &[Key],
+        height: u64,
+    ) -> StdResult<bool> {
+        Ok(store.get(&self.changelog_key(key, height)).is_some())
+    }
+
+    /// write_changelog for a key given by its (length-prefixed) elements
+    pub fn write_changelog_raw(
+        &self,
+        store: &mut dyn Storage,
+        key: &[Key],
+        height: u64,
+        old: Option<T>,
+    ) -> StdResult<()> {
+        store.set(
+            &self.changelog_key(key, height),
+            &to_vec(&ChangeSet { old })?,
+        );
+        Ok(())
+    }
+
+    /// Returns the keys changed since the beginning of the given height, with the raw
+    /// `ChangeSet` holding their value at that time, sorted by their joined primary key.
+    ///
+    /// Only the changelog keys within `min` and `max` are visited. These bounds apply to the
+    /// length-prefixed key elements, without the height. Every changed key costs two reads at
+    /// most, whatever the number of changes it had.
+    pub fn changes_at_height(
+        &self,
+        store: &dyn Storage,
+        height: u64,
+        min: Option<RawBound>,
+        max: Option<RawBound>,
+    ) -> StdResult<Vec<Record>> {
+        self.assert_checkpointed(store, height)?;
+
+        let namespace = namespaces_with_key(&[self.changelog.namespace()], b"");
+        let height = height.to_cw_bytes();
+        let mut start = min;
+        let mut changes = vec![];
+        loop {
+            let (key, value) =
+                match range_with_prefix(store, &namespace, start, max.clone(), Order::Ascending)
+                    .next()
+                {
+                    Some(record) => record,
+                    None => break,
+                };
+            // changelog keys are the length-prefixed key elements, followed by the height
+            if key.len() < 8 {
+                return Err(StdError::generic_err("Invalid changelog key"));
+            }
+            let (elements, change_height) = key.split_at(key.len() - 8);
+            if change_height < &height[..] {
+                // skips the older changes of this key
+                start = Some(RawBound::Inclusive(concat(elements, &height)));
+                continue;
+            }
+            // the first change since the height holds the value at that time
+            changes.push((prefixed_to_joined_key(elements)?, value));
+            // skips the later changes of this key
+            start = Some(RawBound::Exclusive(concat(
+                elements,
+                &u64::MAX.to_cw_bytes(),
+            )));
+        }
+        // the last key element is not length-prefixed in the primary keys, which changes the order
+        changes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(changes)
     }
 
     // may_load_at_height reads historical data from given checkpoints.
@@ -160,6 +417,59 @@ where
     }
 }
 
+/// Converts a key with all elements length-prefixed (as built from `Prefixer::prefix`) into the
+/// `PrimaryKey::joined_key` form, where the last element is not length-prefixed
+fn prefixed_to_joined_key(key: &[u8]) -> StdResult<Vec<u8>> {
+    let mut offset = 0;
+    loop {
+        if key.len() < offset + 2 {
+            return Err(StdError::generic_err("Invalid length-prefixed key"));
+        }
+        let len = u16::from_be_bytes([key[offset], key[offset + 1]]) as usize;
+        let end = offset + 2 + len;
+        match end.cmp(&key.len()) {
+            std::cmp::Ordering::Less => offset = end,
+            std::cmp::Ordering::Equal => {
+                let mut joined = key[..offset].to_vec();
+                joined.extend_from_slice(&key[offset + 2..]);
+                return Ok(joined);
+            }
+            std::cmp::Ordering::Greater => {
+                return Err(StdError::generic_err("Invalid length-prefixed key"))
+            }
+        }
+    }
+}
+
+/// The dimension history is recorded in: block height (`u64`) or block time (`Timestamp`).
+/// Checkpoints and changelog entries are stored by the `u64` representation, so height-based
+/// snapshots keep the same storage layout.
+pub trait CheckpointDimension: Copy {
+    fn to_checkpoint(self) -> u64;
+    fn from_checkpoint(checkpoint: u64) -> Self;
+}
+
+impl CheckpointDimension for u64 {
+    fn to_checkpoint(self) -> u64 {
+        self
+    }
+
+    fn from_checkpoint(checkpoint: u64) -> Self {
+        checkpoint
+    }
+}
+
+/// Timestamps are stored as nanoseconds
+impl CheckpointDimension for Timestamp {
+    fn to_checkpoint(self) -> u64 {
+        self.nanos()
+    }
+
+    fn from_checkpoint(checkpoint: u64) -> Self {
+        Timestamp::from_nanos(checkpoint)
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Strategy {
     EveryBlock,
@@ -0,0 +1,340 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, Record};
+use cosmwasm_std::{StdResult, Storage};
+
+use crate::keys::Prefixer;
+use crate::map::Map;
+
+/// The storage used by the entries of a namespace, or of a key prefix in it
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct StorageUsage {
+    pub entries: u64,
+    /// Total length of the keys, namespace included
+    pub key_bytes: u64,
+    pub value_bytes: u64,
+}
+
+impl StorageUsage {
+    pub fn total_bytes(&self) -> u64 {
+        self.key_bytes + self.value_bytes
+    }
+
+    fn add(&mut self, key_len: usize, value_len: usize) {
+        self.entries += 1;
+        self.key_bytes += key_len as u64;
+        self.value_bytes += value_len as u64;
+    }
+
+    fn sub(&mut self, key_len: usize, value_len: usize) {
+        self.entries = self.entries.saturating_sub(1);
+        self.key_bytes = self.key_bytes.saturating_sub(key_len as u64);
+        self.value_bytes = self.value_bytes.saturating_sub(value_len as u64);
+    }
+}
+
+/// Usage per (namespace, first key element), the latter empty for the whole namespace.
+/// The namespace is reserved: writes to it are not accounted, and contracts must not use it.
+const USAGE: Map<(&[u8], &[u8]), StorageUsage> = Map::new("__usage");
+
+/// Storage wrapper keeping the storage usage of every top-level namespace up to date, as its
+/// entries are saved and removed. See `storage_usage` to query it.
+///
+/// The namespace of an `Item` is its key, and the one of a `Map` (and the maps an `IndexedMap`
+/// or a snapshot type is made of) is the one it was created with.
+///
+/// The usage is only tracked through this wrapper, so it must be used by all the entry points
+/// writing to the accounted namespaces, from the instantiation on. Every write reads the
+/// previous value, and updates the usage entry, so this has a gas cost.
+///
+/// The usage is stored under the reserved `__usage` namespace, which the contract must not use
+/// for its own data. As `Storage` writes can't fail, a corrupted usage entry makes the write
+/// panic, rather than silently resetting the usage.
+///
+/// ## Example:
+///
+/// ```rust
+/// use cosmwasm_std::testing::MockStorage;
+/// use cw_storage_plus::{storage_usage, Map, MeteredStorage};
+///
+/// const BALANCES: Map<&str, u64> = Map::new("balances");
+///
+/// let mut store = MockStorage::new();
+/// let mut metered = MeteredStorage::new(&mut store);
+/// BALANCES.save(&mut metered, "john", &1000).unwrap();
+/// BALANCES.save(&mut metered, "jim", &50).unwrap();
+///
+/// let usage = storage_usage(&store, "balances").unwrap();
+/// assert_eq!(usage.entries, 2);
+/// // "\0\x08balances" ++ "john", and "1000"
+/// assert_eq!((usage.key_bytes, usage.value_bytes), (10 + 4 + 10 + 3, 4 + 2));
+/// ```
+pub struct MeteredStorage<'a> {
+    storage: &'a mut dyn Storage,
+    /// Namespaces also accounted by the first element of their (composite) keys
+    by_key_prefix: Vec<Vec<u8>>,
+}
+
+impl<'a> MeteredStorage<'a> {
+    pub fn new(storage: &'a mut dyn Storage) -> Self {
+        MeteredStorage {
+            storage,
+            by_key_prefix: vec![],
+        }
+    }
+
+    /// Also accounts the usage of the given map by the first element of its keys, e.g. per owner
+    /// for a `Map<(&Addr, u64), _>`. See `key_prefix_usage`.
+    /// Only maps with composite keys can be accounted this way.
+    pub fn by_key_prefix(mut self, namespace: &str) -> Self {
+        self.by_key_prefix.push(namespace.as_bytes().to_vec());
+        self
+    }
+
+    fn account(&mut self, key: &[u8], old: Option<usize>, new: Option<usize>) {
+        let (namespace, rest) = split_namespace(key);
+        if namespace == USAGE.namespace() {
+            return;
+        }
+        let first_key = match rest.map(split_namespace) {
+            // an empty first element would be accounted as the whole namespace
+            Some((first, Some(_)))
+                if !first.is_empty() && self.by_key_prefix.iter().any(|ns| ns == namespace) =>
+            {
+                Some(first)
+            }
+            _ => None,
+        };
+
+        let scopes = std::iter::once(&[][..]).chain(first_key);
+        for scope in scopes {
+            let mut usage = USAGE
+                .may_load(self.storage, (namespace, scope))
+                .expect("invalid storage usage entry in the reserved __usage namespace")
+                .unwrap_or_default();
+            if let Some(len) = old {
+                usage.sub(key.len(), len);
+            }
+            if let Some(len) = new {
+                usage.add(key.len(), len);
+            }
+            if usage.entries == 0 {
+                USAGE.remove(self.storage, (namespace, scope));
+            } else {
+                USAGE
+                    .save(self.storage, (namespace, scope), &usage)
+                    .expect("serializing the storage usage failed");
+            }
+        }
+    }
+}
+
+impl<'a> Storage for MeteredStorage<'a> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.storage.get(key)
+    }
+
+    #[cfg(feature = "iterator")]
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'b> {
+        self.storage.range(start, end, order)
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        let old = self.storage.get(key).map(|v| v.len());
+        self.storage.set(key, value);
+        self.account(key, old, Some(value.len()));
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        let old = self.storage.get(key).map(|v| v.len());
+        self.storage.remove(key);
+        if old.is_some() {
+            self.account(key, old, None);
+        }
+    }
+}
+
+/// Splits the length-prefixed namespace of a map key (or first element of a composite key) from
+/// the rest of the key. Keys that don't start with a length prefix, like the ones of an `Item`,
+/// are all namespace: their first two characters would make a length over 8 KB.
+fn split_namespace(key: &[u8]) -> (&[u8], Option<&[u8]>) {
+    let len = key
+        .get(..2)
+        .map(|len| u16::from_be_bytes(len.try_into().unwrap()) as usize);
+    match len {
+        Some(len) if len > 0 && key.len() >= 2 + len => (&key[2..2 + len], Some(&key[2 + len..])),
+        _ => (key, None),
+    }
+}
+
+/// The storage used by the given namespace, as accounted by `MeteredStorage`
+pub fn storage_usage(store: &dyn Storage, namespace: &str) -> StdResult<StorageUsage> {
+    Ok(USAGE
+        .may_load(store, (namespace.as_bytes(), b""))?
+        .unwrap_or_default())
+}
+
+/// The storage used by the entries of the given map whose keys start with `prefix`, as accounted
+/// by `MeteredStorage::by_key_prefix`
+pub fn key_prefix_usage<'p, P: Prefixer<'p>>(
+    store: &dyn Storage,
+    namespace: &str,
+    prefix: P,
+) -> StdResult<StorageUsage> {
+    let first = prefix.prefix();
+    let first = first.first().map(|k| k.as_ref()).unwrap_or_default();
+    Ok(USAGE
+        .may_load(store, (namespace.as_bytes(), first))?
+        .unwrap_or_default())
+}
+
+/// The storage used by every accounted namespace
+#[cfg(feature = "iterator")]
+pub fn all_storage_usage(store: &dyn Storage) -> StdResult<Vec<(String, StorageUsage)>> {
+    USAGE
+        .range_raw(store, None, None, Order::Ascending)
+        .filter_map(|item| match item {
+            Ok((key, usage)) => {
+                let (namespace, scope) = split_namespace(&key);
+                match scope {
+                    Some(scope) if !scope.is_empty() => None,
+                    _ => Some(
+                        String::from_utf8(namespace.to_vec())
+                            .map(|ns| (ns, usage))
+                            .map_err(Into::into),
+                    ),
+                }
+            }
+            Err(err) => Some(Err(err)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Item;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::Addr;
+
+    const CONFIG: Item<u32> = Item::new("config");
+    const BALANCES: Map<&str, u64> = Map::new("balances");
+    const VOTES: Map<(&Addr, u64), bool> = Map::new("votes");
+
+    #[test]
+    fn accounts_items_and_maps() {
+        let mut store = MockStorage::new();
+        let mut metered = MeteredStorage::new(&mut store);
+        CONFIG.save(&mut metered, &12).unwrap();
+        BALANCES.save(&mut metered, "john", &1000).unwrap();
+        BALANCES.save(&mut metered, "jim", &50).unwrap();
+
+        let config = storage_usage(&metered, "config").unwrap();
+        assert_eq!(
+            config,
+            StorageUsage {
+                entries: 1,
+                key_bytes: 6,
+                value_bytes: 2,
+            }
+        );
+        let balances = storage_usage(&metered, "balances").unwrap();
+        assert_eq!(balances.entries, 2);
+        assert_eq!(balances.total_bytes(), 14 + 13 + 4 + 2);
+
+        // overwriting only changes the value size
+        BALANCES.save(&mut metered, "jim", &5).unwrap();
+        let balances = storage_usage(&metered, "balances").unwrap();
+        assert_eq!((balances.entries, balances.value_bytes), (2, 4 + 1));
+
+        // removing a missing entry changes nothing
+        BALANCES.remove(&mut metered, "jack");
+        BALANCES.remove(&mut metered, "john");
+        let balances = storage_usage(&metered, "balances").unwrap();
+        assert_eq!((balances.entries, balances.total_bytes()), (1, 13 + 1));
+
+        BALANCES.remove(&mut metered, "jim");
+        CONFIG.remove(&mut metered);
+        assert_eq!(
+            storage_usage(&store, "balances").unwrap(),
+            StorageUsage::default()
+        );
+        assert_eq!(storage_usage(&store, "config").unwrap().entries, 0);
+    }
+
+    #[test]
+    fn accounts_by_key_prefix() {
+        let mut store = MockStorage::new();
+        let (john, jim) = (Addr::unchecked("john"), Addr::unchecked("jim"));
+        let mut metered = MeteredStorage::new(&mut store).by_key_prefix("votes");
+        VOTES.save(&mut metered, (&john, 1), &true).unwrap();
+        VOTES.save(&mut metered, (&john, 2), &false).unwrap();
+        VOTES.save(&mut metered, (&jim, 1), &true).unwrap();
+        // not accounted by key prefix
+        BALANCES.save(&mut metered, "john", &1000).unwrap();
+
+        let total = storage_usage(&store, "votes").unwrap();
+        let by_john = key_prefix_usage(&store, "votes", &john).unwrap();
+        let by_jim = key_prefix_usage(&store, "votes", &jim).unwrap();
+        assert_eq!((total.entries, by_john.entries, by_jim.entries), (3, 2, 1));
+        assert_eq!(
+            total.total_bytes(),
+            by_john.total_bytes() + by_jim.total_bytes()
+        );
+        assert_eq!(
+            key_prefix_usage(&store, "balances", "john").unwrap(),
+            StorageUsage::default()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn accounts_snapshot_maps() {
+        use crate::{SnapshotMap, Strategy};
+
+        const STAKES: SnapshotMap<&str, u64> = SnapshotMap::new(
+            "stakes",
+            "stakes__checkpoints",
+            "stakes__changelog",
+            Strategy::EveryBlock,
+        );
+
+        let mut store = MockStorage::new();
+        let mut metered = MeteredStorage::new(&mut store).by_key_prefix("stakes__changelog");
+        STAKES.save(&mut metered, "john", &100, 1).unwrap();
+        STAKES.save(&mut metered, "john", &200, 2).unwrap();
+        CONFIG.save(&mut metered, &1).unwrap();
+
+        assert_eq!(storage_usage(&store, "stakes").unwrap().entries, 1);
+        let changelog = storage_usage(&store, "stakes__changelog").unwrap();
+        assert_eq!(changelog.entries, 2);
+        assert_eq!(
+            key_prefix_usage(&store, "stakes__changelog", "john").unwrap(),
+            changelog
+        );
+
+        let all = all_storage_usage(&store).unwrap();
+        let namespaces: Vec<_> = all.iter().map(|(ns, _)| ns.as_str()).collect();
+        assert_eq!(namespaces, vec!["config", "stakes", "stakes__changelog"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid storage usage entry")]
+    fn corrupted_usage_panics() {
+        let mut store = MockStorage::new();
+        Map::<(&[u8], &[u8]), String>::new("__usage")
+            .save(&mut store, (b"balances", b""), &"corrupted".to_string())
+            .unwrap();
+
+        let mut metered = MeteredStorage::new(&mut store);
+        BALANCES.save(&mut metered, "john", &1000).unwrap();
+    }
+}
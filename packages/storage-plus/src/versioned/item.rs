@@ -0,0 +1,216 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::type_name;
+use std::marker::PhantomData;
+
+use cosmwasm_std::{StdError, StdResult, Storage};
+
+use crate::versioned::{decode, encode, split_version, Upgrade};
+
+/// Item whose value is tagged with its schema version. `upgrades[i]` upgrades a value stored
+/// with version `i`, so the current version is the number of upgrades.
+///
+/// Values stored with an older version are upgraded when loaded, and rewritten with the current
+/// version on the next save. Values stored without a version tag, like those of a plain `Item`,
+/// are version 0.
+pub struct VersionedItem<'a, T> {
+    storage_key: &'a [u8],
+    upgrades: &'a [Upgrade],
+    // see https://doc.rust-lang.org/std/marker/struct.PhantomData.html#unused-type-parameters for why this is needed
+    data_type: PhantomData<T>,
+}
+
+impl<'a, T> VersionedItem<'a, T> {
+    pub const fn new(storage_key: &'a str, upgrades: &'a [Upgrade]) -> Self {
+        VersionedItem {
+            storage_key: storage_key.as_bytes(),
+            upgrades,
+            data_type: PhantomData,
+        }
+    }
+
+    /// The version values are saved with
+    pub fn version(&self) -> u32 {
+        self.upgrades.len() as u32
+    }
+}
+
+impl<'a, T> VersionedItem<'a, T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    // this gets the path of the data to use elsewhere
+    pub fn as_slice(&self) -> &[u8] {
+        self.storage_key
+    }
+
+    /// save will serialize the model with the current version and store it
+    pub fn save(&self, store: &mut dyn Storage, data: &T) -> StdResult<()> {
+        store.set(self.storage_key, &encode(self.version(), data)?);
+        Ok(())
+    }
+
+    pub fn remove(&self, store: &mut dyn Storage) {
+        store.remove(self.storage_key);
+    }
+
+    /// load will return an error if no data is set at the given key, or on parse error.
+    /// An outdated value is upgraded, but not rewritten.
+    pub fn load(&self, store: &dyn Storage) -> StdResult<T> {
+        self.may_load(store)?
+            .ok_or_else(|| StdError::not_found(type_name::<T>()))
+    }
+
+    /// may_load will parse the data stored at the key if present, returns `Ok(None)` if no data there.
+    /// returns an error on issues parsing
+    pub fn may_load(&self, store: &dyn Storage) -> StdResult<Option<T>> {
+        store
+            .get(self.storage_key)
+            .map(|raw| decode(self.upgrades, &raw))
+            .transpose()
+    }
+
+    /// Returns the version the value is stored with, if any
+    pub fn stored_version(&self, store: &dyn Storage) -> StdResult<Option<u32>> {
+        store
+            .get(self.storage_key)
+            .map(|raw| split_version::<T>(&raw).map(|(version, _)| version))
+            .transpose()
+    }
+
+    /// Loads the data, perform the specified action, and store the result
+    /// in the database, with the current version.
+    ///
+    /// It assumes, that data was initialized before, and if it doesn't exist, `Err(StdError::NotFound)`
+    /// is returned.
+    pub fn update<A, E>(&self, store: &mut dyn Storage, action: A) -> Result<T, E>
+    where
+        A: FnOnce(T) -> Result<T, E>,
+        E: From<StdError>,
+    {
+        let input = self.load(store)?;
+        let output = action(input)?;
+        self.save(store, &output)?;
+        Ok(output)
+    }
+
+    /// Rewrites an outdated value with the current version.
+    /// Returns `true` if the value was upgraded.
+    pub fn migrate(&self, store: &mut dyn Storage) -> StdResult<bool> {
+        match self.stored_version(store)? {
+            Some(version) if version < self.version() => {
+                let data = self.load(store)?;
+                self.save(store, &data)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::versioned::upgrade;
+    use crate::Item;
+    use cosmwasm_std::testing::MockStorage;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct ConfigV0 {
+        pub owner: String,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Config {
+        pub owner: String,
+        pub max_tokens: u32,
+    }
+
+    impl From<ConfigV0> for Config {
+        fn from(old: ConfigV0) -> Self {
+            Config {
+                owner: old.owner,
+                max_tokens: 100,
+            }
+        }
+    }
+
+    const OLD_CONFIG: Item<ConfigV0> = Item::new("config");
+    const CONFIG: VersionedItem<Config> =
+        VersionedItem::new("config", &[upgrade::<ConfigV0, Config>]);
+
+    #[test]
+    fn save_and_load() {
+        let mut store = MockStorage::new();
+
+        assert!(CONFIG.load(&store).is_err());
+        assert_eq!(CONFIG.may_load(&store).unwrap(), None);
+        assert_eq!(CONFIG.stored_version(&store).unwrap(), None);
+
+        let cfg = Config {
+            owner: "admin".to_string(),
+            max_tokens: 1234,
+        };
+        CONFIG.save(&mut store, &cfg).unwrap();
+        assert_eq!(CONFIG.load(&store).unwrap(), cfg);
+        assert_eq!(CONFIG.stored_version(&store).unwrap(), Some(1));
+
+        CONFIG.remove(&mut store);
+        assert_eq!(CONFIG.may_load(&store).unwrap(), None);
+    }
+
+    #[test]
+    fn upgrades_lazily() {
+        let mut store = MockStorage::new();
+        OLD_CONFIG
+            .save(
+                &mut store,
+                &ConfigV0 {
+                    owner: "admin".to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(CONFIG.stored_version(&store).unwrap(), Some(0));
+
+        // loading upgrades, but doesn't write
+        let expected = Config {
+            owner: "admin".to_string(),
+            max_tokens: 100,
+        };
+        assert_eq!(CONFIG.load(&store).unwrap(), expected);
+        assert_eq!(CONFIG.stored_version(&store).unwrap(), Some(0));
+
+        // the next save does
+        let updated = CONFIG
+            .update(&mut store, |mut cfg| -> StdResult<_> {
+                cfg.max_tokens += 1;
+                Ok(cfg)
+            })
+            .unwrap();
+        assert_eq!(updated.max_tokens, 101);
+        assert_eq!(CONFIG.stored_version(&store).unwrap(), Some(1));
+        assert_eq!(CONFIG.load(&store).unwrap(), updated);
+        // the plain item can't read it anymore
+        assert!(OLD_CONFIG.load(&store).is_err());
+    }
+
+    #[test]
+    fn migrate_rewrites_outdated_value() {
+        let mut store = MockStorage::new();
+        assert!(!CONFIG.migrate(&mut store).unwrap());
+
+        OLD_CONFIG
+            .save(
+                &mut store,
+                &ConfigV0 {
+                    owner: "admin".to_string(),
+                },
+            )
+            .unwrap();
+        assert!(CONFIG.migrate(&mut store).unwrap());
+        assert_eq!(CONFIG.stored_version(&store).unwrap(), Some(1));
+        assert!(!CONFIG.migrate(&mut store).unwrap());
+        assert_eq!(CONFIG.load(&store).unwrap().max_tokens, 100);
+    }
+}
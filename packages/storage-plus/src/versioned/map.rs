@@ -0,0 +1,457 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::type_name;
+
+#[cfg(feature = "iterator")]
+use cosmwasm_std::Binary;
+use cosmwasm_std::{StdError, StdResult, Storage};
+
+#[cfg(feature = "iterator")]
+use crate::bound::{Bound, RawBound};
+#[cfg(feature = "iterator")]
+use crate::de::KeyDeserialize;
+#[cfg(feature = "iterator")]
+use crate::helpers::namespaces_with_key;
+use crate::keys::PrimaryKey;
+use crate::map::Map;
+#[cfg(feature = "iterator")]
+use crate::prefix::range_with_prefix;
+use crate::versioned::{decode, encode, split_version, Upgrade};
+
+/// Map whose values are tagged with their schema version. `upgrades[i]` upgrades a value stored
+/// with version `i`, so the current version is the number of upgrades.
+///
+/// Values stored with an older version are upgraded when loaded, and rewritten with the current
+/// version on the next save. Values stored without a version tag, like those of a plain `Map`,
+/// are version 0. Use `migrate` to rewrite all the outdated values over several transactions.
+pub struct VersionedMap<'a, K, T> {
+    primary: Map<'a, K, T>,
+    upgrades: &'a [Upgrade],
+}
+
+impl<'a, K, T> VersionedMap<'a, K, T> {
+    pub const fn new(namespace: &'a str, upgrades: &'a [Upgrade]) -> Self {
+        VersionedMap {
+            primary: Map::new(namespace),
+            upgrades,
+        }
+    }
+
+    pub fn namespace(&self) -> &'a [u8] {
+        self.primary.namespace()
+    }
+
+    /// The version values are saved with
+    pub fn version(&self) -> u32 {
+        self.upgrades.len() as u32
+    }
+}
+
+impl<'a, K, T> VersionedMap<'a, K, T>
+where
+    T: Serialize + DeserializeOwned,
+    K: PrimaryKey<'a>,
+{
+    /// save will serialize the model with the current version and store it
+    pub fn save(&self, store: &mut dyn Storage, k: K, data: &T) -> StdResult<()> {
+        store.set(&self.primary.key(k), &encode(self.version(), data)?);
+        Ok(())
+    }
+
+    pub fn remove(&self, store: &mut dyn Storage, k: K) {
+        self.primary.remove(store, k)
+    }
+
+    /// load will return an error if no data is set at the given key, or on parse error.
+    /// An outdated value is upgraded, but not rewritten.
+    pub fn load(&self, store: &dyn Storage, k: K) -> StdResult<T> {
+        self.may_load(store, k)?
+            .ok_or_else(|| StdError::not_found(type_name::<T>()))
+    }
+
+    /// may_load will parse the data stored at the key if present, returns Ok(None) if no data there.
+    /// returns an error on issues parsing
+    pub fn may_load(&self, store: &dyn Storage, k: K) -> StdResult<Option<T>> {
+        store
+            .get(&self.primary.key(k))
+            .map(|raw| decode(self.upgrades, &raw))
+            .transpose()
+    }
+
+    /// has returns true or false if any data is at this key, without parsing or interpreting the
+    /// contents.
+    pub fn has(&self, store: &dyn Storage, k: K) -> bool {
+        self.primary.has(store, k)
+    }
+
+    /// Returns the version the value at this key is stored with, if any
+    pub fn stored_version(&self, store: &dyn Storage, k: K) -> StdResult<Option<u32>> {
+        store
+            .get(&self.primary.key(k))
+            .map(|raw| split_version::<T>(&raw).map(|(version, _)| version))
+            .transpose()
+    }
+
+    /// Loads the data, perform the specified action, and store the result
+    /// in the database, with the current version.
+    ///
+    /// If the data exists, `action(Some(value))` is called. Otherwise `action(None)` is called.
+    pub fn update<A, E>(&self, store: &mut dyn Storage, k: K, action: A) -> Result<T, E>
+    where
+        A: FnOnce(Option<T>) -> Result<T, E>,
+        E: From<StdError>,
+        K: Clone,
+    {
+        let input = self.may_load(store, k.clone())?;
+        let output = action(input)?;
+        self.save(store, k, &output)?;
+        Ok(output)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, K, T> VersionedMap<'a, K, T>
+where
+    T: Serialize + DeserializeOwned,
+    K: PrimaryKey<'a>,
+{
+    fn range_records<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<RawBound>,
+        max: Option<RawBound>,
+        order: cosmwasm_std::Order,
+    ) -> Box<dyn Iterator<Item = cosmwasm_std::Record> + 'c> {
+        let namespace = namespaces_with_key(&[self.namespace()], b"");
+        range_with_prefix(store, &namespace, min, max, order)
+    }
+
+    /// Visits up to `limit` entries in ascending key order, starting after the `cursor`
+    /// returned by the previous call, and rewrites the outdated ones with the current version.
+    ///
+    /// Returns the number of rewritten entries, and the cursor to continue from in a later
+    /// transaction, like a `Page`. The cursor is `None` when all the entries have been visited.
+    /// Fails if `limit` is 0, as no progress could be made.
+    pub fn migrate(
+        &self,
+        store: &mut dyn Storage,
+        cursor: Option<Binary>,
+        limit: usize,
+    ) -> StdResult<(usize, Option<Binary>)> {
+        if limit == 0 {
+            return Err(StdError::generic_err("Migration limit must be at least 1"));
+        }
+        let mut batch: Vec<_> = self
+            .range_records(
+                store,
+                cursor.map(|c| RawBound::Exclusive(c.0)),
+                None,
+                cosmwasm_std::Order::Ascending,
+            )
+            .take(limit.saturating_add(1))
+            .collect();
+        let more = batch.len() > limit;
+        batch.truncate(limit);
+
+        let namespace = namespaces_with_key(&[self.namespace()], b"");
+        let mut rewritten = 0;
+        for (key, raw) in &batch {
+            let (version, _) = split_version::<T>(raw)?;
+            if version < self.version() {
+                let data: T = decode(self.upgrades, raw)?;
+                let full_key = [namespace.as_slice(), key].concat();
+                store.set(&full_key, &encode(self.version(), &data)?);
+                rewritten += 1;
+            }
+        }
+
+        let cursor = match batch.pop() {
+            Some((last, _)) if more => Some(Binary(last)),
+            _ => None,
+        };
+        Ok((rewritten, cursor))
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, K, T> VersionedMap<'a, K, T>
+where
+    T: Serialize + DeserializeOwned,
+    K: PrimaryKey<'a> + KeyDeserialize,
+{
+    /// Iterates over the entries, upgrading the outdated values
+    pub fn range<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: cosmwasm_std::Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'c>
+    where
+        T: 'c,
+        K::Output: 'static,
+        'a: 'c,
+    {
+        let upgrades = self.upgrades;
+        let mapped = self
+            .range_records(
+                store,
+                min.map(|b| b.to_raw_bound()),
+                max.map(|b| b.to_raw_bound()),
+                order,
+            )
+            .map(move |(k, v)| Ok((K::from_vec(k)?, decode(upgrades, &v)?)));
+        Box::new(mapped)
+    }
+
+    pub fn keys<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: cosmwasm_std::Order,
+    ) -> Box<dyn Iterator<Item = StdResult<K::Output>> + 'c>
+    where
+        K::Output: 'static,
+    {
+        let mapped = self
+            .range_records(
+                store,
+                min.map(|b| b.to_raw_bound()),
+                max.map(|b| b.to_raw_bound()),
+                order,
+            )
+            .map(|(k, _)| K::from_vec(k));
+        Box::new(mapped)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::versioned::upgrade;
+    use cosmwasm_std::testing::MockStorage;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    struct ChannelInfoV0 {
+        pub id: String,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    struct ChannelInfoV1 {
+        pub id: String,
+        pub counterparty: String,
+    }
+
+    impl From<ChannelInfoV0> for ChannelInfoV1 {
+        fn from(old: ChannelInfoV0) -> Self {
+            ChannelInfoV1 {
+                counterparty: format!("{}-counterparty", old.id),
+                id: old.id,
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    struct ChannelInfo {
+        pub id: String,
+        pub counterparty: String,
+        pub open: bool,
+    }
+
+    impl From<ChannelInfoV1> for ChannelInfo {
+        fn from(old: ChannelInfoV1) -> Self {
+            ChannelInfo {
+                id: old.id,
+                counterparty: old.counterparty,
+                open: true,
+            }
+        }
+    }
+
+    const OLD_CHANNELS: Map<&str, ChannelInfoV0> = Map::new("channels");
+    const CHANNELS_V1: VersionedMap<&str, ChannelInfoV1> =
+        VersionedMap::new("channels", &[upgrade::<ChannelInfoV0, ChannelInfoV1>]);
+    const CHANNELS: VersionedMap<&str, ChannelInfo> = VersionedMap::new(
+        "channels",
+        &[
+            upgrade::<ChannelInfoV0, ChannelInfoV1>,
+            upgrade::<ChannelInfoV1, ChannelInfo>,
+        ],
+    );
+
+    fn channel(id: &str) -> ChannelInfo {
+        ChannelInfo {
+            id: id.to_string(),
+            counterparty: format!("{}-counterparty", id),
+            open: true,
+        }
+    }
+
+    fn save_old(store: &mut dyn Storage, ids: &[&str]) {
+        for id in ids {
+            let info = ChannelInfoV0 { id: id.to_string() };
+            OLD_CHANNELS.save(store, id, &info).unwrap();
+        }
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut store = MockStorage::new();
+        assert_eq!(CHANNELS.version(), 2);
+
+        assert!(CHANNELS.load(&store, "channel-0").is_err());
+        assert_eq!(CHANNELS.may_load(&store, "channel-0").unwrap(), None);
+        assert!(!CHANNELS.has(&store, "channel-0"));
+
+        CHANNELS
+            .save(&mut store, "channel-0", &channel("channel-0"))
+            .unwrap();
+        assert_eq!(
+            CHANNELS.load(&store, "channel-0").unwrap(),
+            channel("channel-0")
+        );
+        assert_eq!(
+            CHANNELS.stored_version(&store, "channel-0").unwrap(),
+            Some(2)
+        );
+
+        CHANNELS.remove(&mut store, "channel-0");
+        assert!(!CHANNELS.has(&store, "channel-0"));
+    }
+
+    #[test]
+    fn upgrades_lazily_from_any_version() {
+        let mut store = MockStorage::new();
+        save_old(&mut store, &["channel-0"]);
+        CHANNELS_V1
+            .save(
+                &mut store,
+                "channel-1",
+                &ChannelInfoV1 {
+                    id: "channel-1".to_string(),
+                    counterparty: "channel-1-counterparty".to_string(),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            CHANNELS.stored_version(&store, "channel-0").unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            CHANNELS.stored_version(&store, "channel-1").unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            CHANNELS.load(&store, "channel-0").unwrap(),
+            channel("channel-0")
+        );
+        assert_eq!(
+            CHANNELS.load(&store, "channel-1").unwrap(),
+            channel("channel-1")
+        );
+
+        // the older map doesn't know about version 2
+        CHANNELS
+            .update(&mut store, "channel-1", |info| -> StdResult<_> {
+                Ok(info.unwrap())
+            })
+            .unwrap();
+        assert_eq!(
+            CHANNELS.stored_version(&store, "channel-1").unwrap(),
+            Some(2)
+        );
+        assert!(CHANNELS_V1.load(&store, "channel-1").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_upgrades_values() {
+        let mut store = MockStorage::new();
+        save_old(&mut store, &["channel-0", "channel-1"]);
+        CHANNELS
+            .save(&mut store, "channel-2", &channel("channel-2"))
+            .unwrap();
+
+        let all: StdResult<Vec<_>> = CHANNELS
+            .range(&store, None, None, cosmwasm_std::Order::Ascending)
+            .collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![
+                ("channel-0".to_string(), channel("channel-0")),
+                ("channel-1".to_string(), channel("channel-1")),
+                ("channel-2".to_string(), channel("channel-2")),
+            ]
+        );
+
+        let keys: StdResult<Vec<_>> = CHANNELS
+            .keys(
+                &store,
+                Some(Bound::exclusive("channel-0")),
+                None,
+                cosmwasm_std::Order::Descending,
+            )
+            .collect();
+        assert_eq!(keys.unwrap(), vec!["channel-2", "channel-1"]);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn migrate_in_batches() {
+        let mut store = MockStorage::new();
+        save_old(
+            &mut store,
+            &["channel-0", "channel-1", "channel-2", "channel-3"],
+        );
+        CHANNELS
+            .save(&mut store, "channel-4", &channel("channel-4"))
+            .unwrap();
+
+        let (rewritten, next) = CHANNELS.migrate(&mut store, None, 2).unwrap();
+        assert_eq!(rewritten, 2);
+        assert_eq!(next, Some(Binary::from(b"channel-1")));
+        let versions = |store: &dyn Storage| -> Vec<Option<u32>> {
+            (0..5)
+                .map(|i| {
+                    CHANNELS
+                        .stored_version(store, &format!("channel-{}", i))
+                        .unwrap()
+                })
+                .collect()
+        };
+        assert_eq!(
+            versions(&store),
+            vec![Some(2), Some(2), Some(0), Some(0), Some(2)]
+        );
+
+        // the last batch visits exactly the remaining entries
+        // the up to date channel-4 is visited but not rewritten
+        let (rewritten, next) = CHANNELS.migrate(&mut store, next, 3).unwrap();
+        assert_eq!((rewritten, next), (2, None));
+        assert_eq!(versions(&store), vec![Some(2); 5]);
+
+        for i in 0..5 {
+            let id = format!("channel-{}", i);
+            assert_eq!(CHANNELS.load(&store, &id).unwrap(), channel(&id));
+        }
+        assert_eq!(CHANNELS.migrate(&mut store, None, 10).unwrap(), (0, None));
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn migrate_rejects_zero_limit() {
+        let mut store = MockStorage::new();
+        save_old(&mut store, &["channel-0"]);
+
+        // an empty batch must not report the migration as done
+        let err = CHANNELS.migrate(&mut store, None, 0).unwrap_err();
+        assert!(matches!(err, StdError::GenericErr { .. }));
+        assert_eq!(
+            CHANNELS.stored_version(&store, "channel-0").unwrap(),
+            Some(0)
+        );
+    }
+}
@@ -0,0 +1,211 @@
+mod item;
+mod map;
+
+pub use item::VersionedItem;
+pub use map::VersionedMap;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::type_name;
+use std::borrow::Cow;
+use std::convert::TryInto;
+
+use cosmwasm_std::{from_slice, to_vec, StdError, StdResult};
+
+/// Upgrades the JSON of a stored value from one schema version to the next.
+/// See `upgrade` for the usual conversion through `From`.
+pub type Upgrade = fn(&[u8]) -> StdResult<Vec<u8>>;
+
+/// The upgrade parsing the stored value as `Old`, and converting it to `New` with `From`.
+///
+/// ## Example:
+///
+/// ```rust
+/// use cw_storage_plus::{upgrade, VersionedItem};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct ConfigV0 {
+///     pub owner: String,
+/// }
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Config {
+///     pub owner: String,
+///     pub paused: bool,
+/// }
+///
+/// impl From<ConfigV0> for Config {
+///     fn from(old: ConfigV0) -> Self {
+///         Config {
+///             owner: old.owner,
+///             paused: false,
+///         }
+///     }
+/// }
+///
+/// const CONFIG: VersionedItem<Config> =
+///     VersionedItem::new("config", &[upgrade::<ConfigV0, Config>]);
+/// ```
+pub fn upgrade<Old, New>(data: &[u8]) -> StdResult<Vec<u8>>
+where
+    Old: DeserializeOwned,
+    New: Serialize + From<Old>,
+{
+    to_vec(&New::from(from_slice::<Old>(data)?))
+}
+
+// Values from version 1 on are stored as TAG ++ version (u32 big endian) ++ JSON.
+// No JSON document starts with this byte, so untagged values are read as version 0,
+// which allows versioning the values previously stored by a plain `Item` or `Map`.
+const TAG: u8 = 0;
+const HEADER_LEN: usize = 5;
+
+/// Encodes the value, tagged with the given version
+pub(crate) fn encode<T: Serialize>(version: u32, data: &T) -> StdResult<Vec<u8>> {
+    let json = to_vec(data)?;
+    if version == 0 {
+        return Ok(json);
+    }
+    let mut out = Vec::with_capacity(HEADER_LEN + json.len());
+    out.push(TAG);
+    out.extend_from_slice(&version.to_be_bytes());
+    out.extend(json);
+    Ok(out)
+}
+
+/// Splits the stored bytes into the version and the JSON of the value
+pub(crate) fn split_version<T>(raw: &[u8]) -> StdResult<(u32, &[u8])> {
+    match raw.first() {
+        Some(&TAG) => {
+            let version = raw
+                .get(1..HEADER_LEN)
+                .and_then(|v| v.try_into().ok())
+                .map(u32::from_be_bytes)
+                .ok_or_else(|| StdError::parse_err(type_name::<T>(), "truncated version"))?;
+            Ok((version, &raw[HEADER_LEN..]))
+        }
+        _ => Ok((0, raw)),
+    }
+}
+
+/// Decodes the stored bytes, upgrading the value to the last version
+pub(crate) fn decode<T: DeserializeOwned>(upgrades: &[Upgrade], raw: &[u8]) -> StdResult<T> {
+    let (version, json) = split_version::<T>(raw)?;
+    let pending = upgrades.get(version as usize..).ok_or_else(|| {
+        StdError::parse_err(
+            type_name::<T>(),
+            format!(
+                "stored version {} is newer than the current version {}",
+                version,
+                upgrades.len()
+            ),
+        )
+    })?;
+    let mut json = Cow::Borrowed(json);
+    for upgrade in pending {
+        json = Cow::Owned(upgrade(&json)?);
+    }
+    from_slice(&json)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct V0 {
+        pub name: String,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct V1 {
+        pub name: String,
+        pub age: u32,
+    }
+
+    impl From<V0> for V1 {
+        fn from(old: V0) -> Self {
+            V1 {
+                name: old.name,
+                age: 0,
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct V2 {
+        pub names: Vec<String>,
+        pub age: u32,
+    }
+
+    impl From<V1> for V2 {
+        fn from(old: V1) -> Self {
+            V2 {
+                names: vec![old.name],
+                age: old.age,
+            }
+        }
+    }
+
+    const UPGRADES: &[Upgrade] = &[upgrade::<V0, V1>, upgrade::<V1, V2>];
+
+    #[test]
+    fn version_zero_is_untagged() {
+        let v0 = V0 {
+            name: "john".to_string(),
+        };
+        let raw = encode(0, &v0).unwrap();
+        assert_eq!(raw, to_vec(&v0).unwrap());
+        assert_eq!(split_version::<V0>(&raw).unwrap(), (0, raw.as_slice()));
+        assert_eq!(decode::<V0>(&[], &raw).unwrap(), v0);
+    }
+
+    #[test]
+    fn decode_runs_pending_upgrades() {
+        let expected = V2 {
+            names: vec!["john".to_string()],
+            age: 0,
+        };
+        let raw = encode(
+            0,
+            &V0 {
+                name: "john".to_string(),
+            },
+        )
+        .unwrap();
+        assert_eq!(decode::<V2>(UPGRADES, &raw).unwrap(), expected);
+
+        let raw = encode(
+            1,
+            &V1 {
+                name: "john".to_string(),
+                age: 0,
+            },
+        )
+        .unwrap();
+        assert_eq!(raw[..HEADER_LEN], [TAG, 0, 0, 0, 1]);
+        assert_eq!(decode::<V2>(UPGRADES, &raw).unwrap(), expected);
+
+        let raw = encode(2, &expected).unwrap();
+        assert_eq!(split_version::<V2>(&raw).unwrap().0, 2);
+        assert_eq!(decode::<V2>(UPGRADES, &raw).unwrap(), expected);
+    }
+
+    #[test]
+    fn decode_rejects_unknown_versions() {
+        let raw = encode(
+            3,
+            &V1 {
+                name: "john".to_string(),
+                age: 1,
+            },
+        )
+        .unwrap();
+        let err = decode::<V2>(UPGRADES, &raw).unwrap_err();
+        assert!(matches!(err, StdError::ParseErr { .. }));
+
+        assert!(split_version::<V2>(&[TAG, 0, 1]).is_err());
+    }
+}